@@ -0,0 +1,41 @@
+extern crate futures;
+extern crate scheduled_executor;
+
+use futures::Future;
+use futures::sync::oneshot;
+use scheduled_executor::CoreExecutor;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+fn main() {
+    let start_time = Instant::now();
+    let ticks = Arc::new(AtomicUsize::new(0));
+
+    CoreExecutor::run_on_current_thread(|executor| {
+        let (done_tx, done_rx) = oneshot::channel();
+        let mut done_tx = Some(done_tx);
+        let ticks_clone = ticks.clone();
+        executor.schedule_fixed_rate(
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            move |_| {
+                let tick = ticks_clone.fetch_add(1, Ordering::Relaxed) + 1;
+                println!("> Tick {} after {:?} seconds", tick, start_time.elapsed().as_secs());
+                if tick == 5 {
+                    let _ = done_tx.take().map(|done_tx| done_tx.send(()));
+                }
+            }
+        );
+        executor.schedule_fixed_rate(
+            Duration::from_millis(2500),
+            Duration::from_secs(2),
+            |_| println!("> Slower task")
+        );
+        println!("Tasks have been scheduled, running on the main thread");
+        done_rx.map_err(|_| ())
+    }).expect("Event loop creation failed");
+
+    println!("Terminating after {} ticks", ticks.load(Ordering::Relaxed));
+}
@@ -0,0 +1,259 @@
+//! Durations written in a human-friendly way, as they usually appear in configuration files.
+//!
+//! A duration is a sequence of integer amounts, each followed by its unit, for example `30s`,
+//! `5m`, `1h30m` or `1d 12h`. The supported units are `d` (days), `h` (hours), `m` (minutes),
+//! `s` (seconds), `ms` (milliseconds), `us` (microseconds) and `ns` (nanoseconds); whitespace is
+//! allowed between the components.
+//!
+//! The `IntoInterval` trait converts the other representations of the intervals accepted by
+//! `TaskBuilder`, checking that they're in range.
+//!
+#[cfg(feature = "chrono")]
+use chrono;
+
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The units of the components of a duration, with their length in nanoseconds.
+const UNITS: &[(&str, u64)] = &[
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// The error returned when a duration is not valid. Every variant carries the offending token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The string is empty, or only contains whitespace.
+    Empty,
+    /// A component doesn't start with a number, like `s` or `-5s`.
+    InvalidNumber(String),
+    /// A number isn't followed by a unit, like `30`.
+    MissingUnit(String),
+    /// The unit of a component isn't supported, like `5y`.
+    UnknownUnit {
+        /// The component containing the unit.
+        token: String,
+        /// The unit.
+        unit: String,
+    },
+    /// The duration doesn't fit in a `Duration`.
+    Overflow(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Empty => write!(f, "empty duration"),
+            ParseError::InvalidNumber(ref token) => write!(f, "expected a number in duration component {:?}", token),
+            ParseError::MissingUnit(ref token) =>
+                write!(f, "missing unit after {:?}, expected one of d, h, m, s, ms, us, ns", token),
+            ParseError::UnknownUnit { ref token, ref unit } =>
+                write!(f, "unknown unit {:?} in duration component {:?}, expected one of d, h, m, s, ms, us, ns", unit, token),
+            ParseError::Overflow(ref token) => write!(f, "duration component {:?} is too large", token),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        "invalid duration"
+    }
+}
+
+/// The longest interval accepted by `IntoInterval`, 100 years: anything longer is most likely a
+/// unit mistake. The executors lower the longer intervals of the schedules to it.
+pub const MAX_INTERVAL: Duration = Duration::from_secs(100 * 365 * 86_400);
+
+/// Returns the instant `wait` after `now`. A wait too long to be represented is clamped to
+/// `MAX_INTERVAL`, rather than panicking on the executor thread.
+pub(crate) fn deadline_after(now: Instant, wait: Duration) -> Instant {
+    now.checked_add(wait).unwrap_or_else(|| now + MAX_INTERVAL)
+}
+
+/// The error returned when an interval is out of range, see `IntoInterval`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntervalError {
+    /// The interval is negative; contains the interval.
+    Negative(String),
+    /// The interval is longer than `MAX_INTERVAL`; contains the interval.
+    TooLarge(String),
+}
+
+impl fmt::Display for IntervalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntervalError::Negative(ref interval) => write!(f, "negative interval {}", interval),
+            IntervalError::TooLarge(ref interval) =>
+                write!(f, "interval {} is longer than the maximum of {}", interval, format_duration(MAX_INTERVAL)),
+        }
+    }
+}
+
+impl Error for IntervalError {
+    fn description(&self) -> &str {
+        "interval out of range"
+    }
+}
+
+/// A value that can be used as the interval, or any other duration, of a task: a `Duration`, a
+/// number of milliseconds as a `u64`, or, with the `chrono` feature, a `chrono::Duration`. The
+/// conversion fails for negative values and for values longer than `MAX_INTERVAL`.
+pub trait IntoInterval {
+    /// Converts the value to a `Duration`, checking that it's in range.
+    fn into_interval(self) -> Result<Duration, IntervalError>;
+}
+
+impl IntoInterval for Duration {
+    fn into_interval(self) -> Result<Duration, IntervalError> {
+        if self > MAX_INTERVAL {
+            return Err(IntervalError::TooLarge(format_duration(self)));
+        }
+        Ok(self)
+    }
+}
+
+impl IntoInterval for u64 {
+    fn into_interval(self) -> Result<Duration, IntervalError> {
+        Duration::from_millis(self).into_interval()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl IntoInterval for chrono::Duration {
+    fn into_interval(self) -> Result<Duration, IntervalError> {
+        if self < chrono::Duration::zero() {
+            return Err(IntervalError::Negative(self.to_string()));
+        }
+        self.to_std().map_err(|_| IntervalError::TooLarge(self.to_string()))?.into_interval()
+    }
+}
+
+/// Parses a duration like `30s`, `5m` or `1h30m`, see the module documentation for the syntax.
+pub fn parse_duration(text: &str) -> Result<Duration, ParseError> {
+    let mut rest = text.trim_start();
+    if rest.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    let mut nanos: u128 = 0;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit_len = rest[digits..].find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len() - digits);
+        let token = &rest[..digits + unit_len];
+        let (number, unit) = token.split_at(digits);
+        let word = &rest[..rest.find(char::is_whitespace).unwrap_or(rest.len())];
+        if number.is_empty() || (unit.is_empty() && word.len() > token.len()) {
+            return Err(ParseError::InvalidNumber(word.to_owned()));
+        }
+        if unit.is_empty() {
+            return Err(ParseError::MissingUnit(token.to_owned()));
+        }
+        let unit_nanos = UNITS.iter()
+            .find(|&&(name, _)| name == unit)
+            .map(|&(_, unit_nanos)| unit_nanos)
+            .ok_or_else(|| ParseError::UnknownUnit { token: token.to_owned(), unit: unit.to_owned() })?;
+        let amount = number.parse::<u64>().map_err(|_| ParseError::Overflow(token.to_owned()))?;
+        nanos += u128::from(amount) * u128::from(unit_nanos);
+        if nanos > Duration::MAX.as_nanos() {
+            return Err(ParseError::Overflow(token.to_owned()));
+        }
+        rest = rest[token.len()..].trim_start();
+    }
+    let secs = (nanos / 1_000_000_000) as u64;
+    Ok(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/// Formats a duration in the syntax accepted by `parse_duration`, using the largest units first,
+/// like `1h30m` or `2s500ms`. A zero duration is formatted as `0s`.
+pub fn format_duration(duration: Duration) -> String {
+    let mut nanos = duration.as_nanos();
+    if nanos == 0 {
+        return "0s".to_owned();
+    }
+    let mut text = String::new();
+    for &(name, unit_nanos) in UNITS {
+        let amount = nanos / u128::from(unit_nanos);
+        if amount > 0 {
+            text.push_str(&amount.to_string());
+            text.push_str(name);
+            nanos %= u128::from(unit_nanos);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration(" 1d 12h "), Ok(Duration::from_secs(129_600)));
+        assert_eq!(parse_duration("1s500ms"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("3us"), Ok(Duration::from_micros(3)));
+        assert_eq!(parse_duration("0ms"), Ok(Duration::from_secs(0)));
+
+        assert_eq!(parse_duration(" "), Err(ParseError::Empty));
+        assert_eq!(parse_duration("30"), Err(ParseError::MissingUnit("30".to_owned())));
+        assert_eq!(parse_duration("5m 10"), Err(ParseError::MissingUnit("10".to_owned())));
+        assert_eq!(parse_duration("1h -5m"), Err(ParseError::InvalidNumber("-5m".to_owned())));
+        assert_eq!(parse_duration("5y"), Err(ParseError::UnknownUnit { token: "5y".to_owned(), unit: "y".to_owned() }));
+        assert_eq!(parse_duration("1.5s"), Err(ParseError::InvalidNumber("1.5s".to_owned())));
+        assert_eq!(parse_duration("99999999999999999999s"),
+                   Err(ParseError::Overflow("99999999999999999999s".to_owned())));
+        assert_eq!(parse_duration("18446744073709551615s 1s"), Err(ParseError::Overflow("1s".to_owned())));
+        assert!(parse_duration("5y").unwrap_err().to_string().contains("\"5y\""));
+    }
+
+    #[test]
+    fn into_interval_test() {
+        assert_eq!(Duration::from_secs(5).into_interval(), Ok(Duration::from_secs(5)));
+        assert_eq!(MAX_INTERVAL.into_interval(), Ok(MAX_INTERVAL));
+        assert_eq!((MAX_INTERVAL + Duration::from_nanos(1)).into_interval(),
+                   Err(IntervalError::TooLarge("36500d1ns".to_owned())));
+        assert_eq!(1500u64.into_interval(), Ok(Duration::from_millis(1500)));
+        assert_eq!(u64::MAX.into_interval(), Err(IntervalError::TooLarge("213503982334d14h25m51s615ms".to_owned())));
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn chrono_into_interval_test() {
+        use chrono;
+
+        assert_eq!(chrono::Duration::milliseconds(1500).into_interval(), Ok(Duration::from_millis(1500)));
+        assert_eq!(chrono::Duration::zero().into_interval(), Ok(Duration::from_secs(0)));
+        assert_eq!(chrono::Duration::seconds(-5).into_interval(), Err(IntervalError::Negative("-PT5S".to_owned())));
+        assert_eq!(chrono::Duration::nanoseconds(-1).into_interval(),
+                   Err(IntervalError::Negative("-PT0.000000001S".to_owned())));
+        assert!(matches!(chrono::Duration::MAX.into_interval(), Err(IntervalError::TooLarge(_))));
+        assert!(matches!(chrono::Duration::MIN.into_interval(), Err(IntervalError::Negative(_))));
+        assert!(matches!(chrono::Duration::days(365 * 101).into_interval(), Err(IntervalError::TooLarge(_))));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for &(text, duration) in &[
+            ("250ms", Duration::from_millis(250)),
+            ("45s", Duration::from_secs(45)),
+            ("5m", Duration::from_secs(300)),
+            ("2h", Duration::from_secs(7200)),
+            ("7d", Duration::from_secs(604_800)),
+            ("1d2h3m4s5ms", Duration::from_millis(93_784_005)),
+        ] {
+            assert_eq!(parse_duration(text), Ok(duration));
+            assert_eq!(format_duration(duration), text);
+        }
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(parse_duration(&format_duration(Duration::new(90_061, 1_001))), Ok(Duration::new(90_061, 1_001)));
+    }
+}
@@ -5,17 +5,49 @@
 //! the scheduling of the functions (and for the `CoreExecutor`, also their execution). A reference
 //! to the event loop is passed to every function when executed, allowing it to register additional
 //! events if needed.
-use futures::future::Future;
-use futures::sync::oneshot::{channel, Sender};
-use futures_cpupool::{Builder, CpuPool};
+use futures::{Async, Poll};
+use futures::future::{self, Either, ExecuteError, ExecuteErrorKind, Executor, Future};
+use futures::sink::Sink;
+use futures::stream::Stream;
+use futures::task;
+use futures::sync::oneshot::{channel, Canceled, Receiver, Sender};
+use futures_cpupool::{Builder, CpuFuture, CpuPool};
+use log::{self, LogLevel, LogLevelFilter};
 use tokio_core::reactor::Timeout;
 use tokio_core::reactor::{Core, Handle, Remote};
 
-use std::io;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use duration::{deadline_after, parse_duration, IntervalError, IntoInterval, ParseError, MAX_INTERVAL};
+use timer_wheel::{TimerCanceller, TimerKey, TimerWheel};
+use schedule::{Aligned, Anchored, Backoff, CronError, CronSchedule, DelaySequence, FixedInterval, FixedRate, Jittered, MissedTickPolicy, RetryPolicy, Schedule, ScheduleKind, Until};
+
+#[cfg(feature = "chrono")]
+use chrono_tz::Tz;
+#[cfg(feature = "compat")]
+use futures03::compat::{Future01CompatExt, Stream01CompatExt};
+#[cfg(feature = "compat")]
+use futures03::{FutureExt, Stream as Stream03, StreamExt};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize, Serializer};
+
+use std::any::{Any, TypeId};
+#[cfg(feature = "serde")]
+use std::cmp;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+#[cfg(feature = "compat")]
+use std::future::Future as StdFuture;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
+use std::ptr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, RwLock, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::thread::{self, JoinHandle};
-use std::time::{Instant, Duration};
+use std::time::{Duration, Instant, SystemTime};
 
 
 /// A handle that allows a task to be stopped. A new handle is returned every time a new task is
@@ -23,350 +55,6172 @@ use std::time::{Instant, Duration};
 /// to run, but it won't interrupt a task that is currently being executed.
 #[derive(Clone)]
 pub struct TaskHandle {
-    should_stop: Arc<AtomicBool>,
+    inner: Arc<TaskState>,
 }
 
-impl TaskHandle {
-    fn new() -> TaskHandle {
-        TaskHandle { should_stop: Arc::new(AtomicBool::new(false)) }
-    }
+struct TaskState {
+    id: TaskId,
+    should_stop: AtomicBool,
+    paused: AtomicBool,
+    /// The number of executions currently in progress. Only updated with `executions` locked, so
+    /// that the waiters on `execution_done` see every change, but read without it.
+    running: AtomicUsize,
+    executions: Mutex<Executions>,
+    /// Notified every time an execution completes.
+    execution_done: Condvar,
+    /// The durations of the completed executions, updated without taking the lock.
+    durations: DurationRecorder,
+    /// The total duration of the completed executions, in nanoseconds.
+    busy: AtomicU64,
+    /// When the task was created, the start of the window of `TaskHandle::busy_fraction`.
+    created: Instant,
+}
 
-    /// Stops the correspondent task. Not that a running task won't be interrupted, but
-    /// future tasks executions will be prevented.
-    pub fn stop(&self) {
-        self.should_stop.store(true, Ordering::Relaxed);
-    }
+struct Executions {
+    /// The `TaskCompletion` futures waiting for the task to terminate.
+    waiters: Vec<task::Task>,
+    /// The `ExecutionSlot` futures waiting for an execution to complete.
+    slot_waiters: Vec<task::Task>,
+    /// Cancel the pending timers of the task when it's stopped, see `TaskHandle::cancellation`.
+    cancellations: Vec<Sender<()>>,
+    /// The entries of the task in the timer wheel of its executor, cancelled when it's stopped.
+    timers: Vec<TimerCanceller>,
+    stats: TaskStats,
+    /// The start and the end of the last `BUSY_HISTORY` completed executions.
+    recent_executions: VecDeque<(Instant, Instant)>,
+    /// When the pending timer of the task will fire.
+    next_run: Option<Instant>,
+    /// Requests an immediate run of a recurring task, released when the task is stopped.
+    trigger: Option<Arc<Trigger>>,
+    /// The interval of the schedule, if it has one.
+    interval: Option<Duration>,
+    /// Set when the interval is changed, until the schedule is updated.
+    interval_changed: bool,
+    /// How long the next execution has to be postponed.
+    postponement: Duration,
+    /// The number of executions started so far.
+    runs: u64,
+    /// The maximum number of executions, after which the task stops.
+    max_runs: Option<u64>,
+    /// What happens when an execution on a thread pool is due while the previous one is still
+    /// running; `OverlapPolicy::Allow` by default.
+    overlap_policy: Option<OverlapPolicy>,
+    /// The key of the thread the executions on a thread pool are bound to.
+    affinity: Option<u64>,
+    /// Whether the executions beyond the limit of `OverlapPolicy::Concurrent` are queued instead
+    /// of skipped.
+    queue_at_limit: bool,
+    /// How long an execution on a thread pool can take before it's reported as timed out.
+    execution_timeout: Option<Duration>,
+    /// How long an execution can take before a warning is logged, see
+    /// `TaskHandle::warn_if_slower_than`.
+    slow_threshold: Option<Duration>,
+    /// The clock the waits of the task are measured with.
+    clock_source: ClockSource,
+    /// Invoked when an execution takes longer than the interval of the task.
+    overrun_handler: Option<Arc<OverrunHandler>>,
+    /// The panic policy of the task, overriding the one of the executor.
+    panic_policy: Option<Arc<PanicPolicy>>,
+    /// The settings of the executor running the task.
+    executor_hooks: Option<Arc<ExecutorHooks>>,
+    /// The name of the task, reported to the panic handler.
+    name: Option<String>,
+    /// The kind of the schedule of the task.
+    kind: ScheduleKind,
+    /// The tags of the task, used by the executor's bulk operations.
+    tags: BTreeSet<String>,
+    /// The tasks stopped together with this one.
+    children: Vec<Weak<TaskState>>,
+    /// The task this one is a child of, if any.
+    parent: Option<Weak<TaskState>>,
+    /// The number of executions in a row that failed.
+    consecutive_failures: usize,
+    /// The number of consecutive failures after which the task stops.
+    max_consecutive_failures: Option<usize>,
+    termination_reason: Option<TerminationReason>,
+    /// The error that terminated the task.
+    error: Option<String>,
+    /// When the task was stopped.
+    stopped_at: Option<Instant>,
+    /// Receives the state of a task scheduled with one of the `_with_state` methods, with its
+    /// type, see `TaskHandle::into_state_receiver`.
+    state_receiver: Option<(TypeId, Receiver<Box<dyn Any + Send>>)>,
+}
 
-    /// Returns true if the task is stopped.
-    pub fn stopped(&self) -> bool {
-        self.should_stop.load(Ordering::Relaxed)
+/// Identifies a task, unique within the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TaskId(u64);
+
+impl TaskId {
+    fn next() -> TaskId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
     }
 }
 
-fn fixed_interval_loop<F>(mut scheduled_fn: F, interval: Duration, handle: &Handle, task_handle: TaskHandle)
-    where F: FnMut(&Handle) + Send + 'static
-{
-    if task_handle.stopped() {
-        return;
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
-    let start_time = Instant::now();
-    scheduled_fn(handle);
-    let execution = start_time.elapsed();
-    let next_iter_wait = if execution >= interval {
-        Duration::from_secs(0)
-    } else {
-        interval - execution
-    };
-    let handle_clone = handle.clone();
-    let t = Timeout::new(next_iter_wait, handle).unwrap()
-        .then(move |_| {
-            fixed_interval_loop(scheduled_fn, interval, &handle_clone, task_handle);
-            Ok::<(), ()>(())
-        });
-    handle.spawn(t);
 }
 
-fn calculate_delay(interval: Duration, execution: Duration, delay: Duration) -> (Duration, Duration) {
-    if execution >= interval {
-        (Duration::from_secs(0), delay + execution - interval)
-    } else {
-        let wait_gap = interval - execution;
-        if delay == Duration::from_secs(0) {
-            (wait_gap, Duration::from_secs(0))
-        } else if delay < wait_gap {
-            (wait_gap - delay, Duration::from_secs(0))
-        } else {
-            (Duration::from_secs(0), delay - wait_gap)
+/// A snapshot of the state of a task, as returned by the executors' `tasks` methods.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TaskInfo {
+    /// The id of the task.
+    pub id: TaskId,
+    /// The name of the task, if it was given one.
+    pub name: Option<String>,
+    /// The tags of the task, sorted.
+    pub tags: Vec<String>,
+    /// The kind of the schedule of the task.
+    pub kind: ScheduleKind,
+    /// The interval of the task, if its schedule has one.
+    #[cfg_attr(feature = "serde", serde(rename = "interval_ms", serialize_with = "serialize_millis"))]
+    pub interval: Option<Duration>,
+    /// The number of executions started so far.
+    pub runs: u64,
+    /// Whether the task is paused.
+    pub paused: bool,
+    /// Whether the task is stopped. A stopped task is still reported until its last execution
+    /// completes.
+    pub stopped: bool,
+    /// The total time spent running the task, see `TaskHandle::total_busy_time`.
+    #[cfg_attr(feature = "serde", serde(rename = "busy_time_ms", serialize_with = "serialize_total_millis"))]
+    pub busy_time: Duration,
+}
+
+/// Reports an execution that took at least as long as the interval of its task, see
+/// `TaskHandle::on_overrun`.
+#[derive(Clone, Debug)]
+pub struct Overrun {
+    /// The id of the task.
+    pub task: TaskId,
+    /// The name of the task, if it was given one.
+    pub name: Option<String>,
+    /// The index of the execution.
+    pub run: u64,
+    /// How long the execution took.
+    pub took: Duration,
+    /// The interval of the task when the execution started.
+    pub interval: Duration,
+    /// The delay accumulated by the schedule when the execution started, as returned by
+    /// `Schedule::delay`. Only fixed rate schedules accumulate a delay.
+    pub delay: Duration,
+}
+
+type OverrunHandler = dyn Fn(Overrun) + Send + Sync;
+
+type SaturationHandler = dyn Fn(usize) + Send + Sync;
+
+/// The number of executions in flight on a thread pool above which it's saturated, see
+/// `ThreadPoolExecutor::on_saturation`.
+struct Saturation {
+    threshold: usize,
+    handler: Arc<SaturationHandler>,
+}
+
+/// Aggregate metrics of an executor, as returned by the executors' `metrics` methods.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ExecutorMetrics {
+    /// The number of tasks that are scheduled and not stopped.
+    pub live_tasks: usize,
+    /// The number of completed executions since the executor was created, including the ones
+    /// that panicked.
+    pub executions: u64,
+    /// The number of completed executions since the previous call to `metrics`, or since the
+    /// executor was created for the first call.
+    pub recent_executions: u64,
+    /// The number of executions that panicked since the executor was created.
+    pub panics: u64,
+    /// The number of executions submitted to the thread pool that didn't start yet. Always 0 for
+    /// a `CoreExecutor`.
+    pub queued: usize,
+}
+
+/// Why a task was stopped, as returned by `TaskHandle::terminated_reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationReason {
+    /// The task was stopped through its `TaskHandle`, or the function returned `Control::Stop`.
+    Stopped,
+    /// The task ran out of scheduled executions: its schedule ended, or it reached its maximum
+    /// number of runs, its deadline or a false predicate.
+    Completed,
+    /// An execution panicked, with `PanicPolicy::StopTask`.
+    Panicked,
+    /// The task failed the given number of times in a row.
+    TooManyFailures(usize),
+    /// The timer for the next execution couldn't be created.
+    TimerFailed,
+    /// The parent of the task was stopped, see `TaskHandle::add_child`.
+    ParentStopped,
+    /// The executor running the task was dropped or shut down, or its thread terminated.
+    ExecutorDropped,
+    /// The sink the task was sending to returned an error, see `TaskHandle::error`.
+    SinkFailed,
+}
+
+/// The error returned when an executor can't be created, or can't schedule a task.
+#[derive(Debug)]
+pub enum ExecutorError {
+    /// The executor thread, or its watchdog thread, couldn't be spawned.
+    ThreadSpawn(io::Error),
+    /// The event loop of the executor couldn't be started.
+    ReactorInit(io::Error),
+    /// The thread of the executor terminated (for example because it panicked), so the task
+    /// would never run.
+    Shutdown,
+    /// The schedule of the task is not valid.
+    InvalidSchedule(String),
+    /// The grace period of a shutdown expired while the given number of executions were still
+    /// in progress.
+    ShutdownTimedOut(usize),
+    /// Any other I/O error.
+    Io(io::Error),
+}
+
+impl fmt::Display for ExecutorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExecutorError::ThreadSpawn(ref e) => write!(f, "failed to spawn the executor thread: {}", e),
+            ExecutorError::ReactorInit(ref e) => write!(f, "failed to start the event loop: {}", e),
+            ExecutorError::Shutdown => write!(f, "the executor thread has terminated"),
+            ExecutorError::InvalidSchedule(ref reason) => write!(f, "invalid schedule: {}", reason),
+            ExecutorError::ShutdownTimedOut(running) =>
+                write!(f, "{} executions still in progress at the end of the shutdown", running),
+            ExecutorError::Io(ref e) => write!(f, "I/O error: {}", e),
         }
     }
 }
 
-fn fixed_rate_loop<F>(mut scheduled_fn: F, interval: Duration, handle: &Handle, delay: Duration, task_handle: TaskHandle)
-    where F: FnMut(&Handle) + Send + 'static
-{
-    if task_handle.stopped() {
-        return;
+impl Error for ExecutorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ExecutorError::ThreadSpawn(ref e) | ExecutorError::ReactorInit(ref e) | ExecutorError::Io(ref e) => Some(e),
+            ExecutorError::Shutdown | ExecutorError::InvalidSchedule(_) | ExecutorError::ShutdownTimedOut(_) => None,
+        }
     }
-    let start_time = Instant::now();
-    scheduled_fn(handle);
-    let execution = start_time.elapsed();
-    let (next_iter_wait, updated_delay) = calculate_delay(interval, execution, delay);
-    let handle_clone = handle.clone();
-    let t = Timeout::new(next_iter_wait, handle).unwrap()
-        .then(move |_| {
-            fixed_rate_loop(scheduled_fn, interval, &handle_clone, updated_delay, task_handle);
-            Ok::<(), ()>(())
-        });
-    handle.spawn(t);
 }
 
-
-struct CoreExecutorInner {
-    remote: Remote,
-    termination_sender: Option<Sender<()>>,
-    thread_handle: Option<JoinHandle<()>>,
+impl From<io::Error> for ExecutorError {
+    fn from(e: io::Error) -> ExecutorError {
+        ExecutorError::Io(e)
+    }
 }
 
-impl Drop for CoreExecutorInner {
-    fn drop(&mut self) {
-        let _ = self.termination_sender.take().unwrap().send(());
-        let _ = self.thread_handle.take().unwrap().join();
+impl From<ParseError> for ExecutorError {
+    fn from(e: ParseError) -> ExecutorError {
+        ExecutorError::InvalidSchedule(e.to_string())
     }
 }
 
-/// A `CoreExecutor` is the most simple executor provided. It runs a single thread, which is
-/// responsible for both scheduling the function (registering the timer for the wakeup),
-/// and the actual execution. The executor will stop once dropped. The `CoreExecutor`
-/// can be cloned to generate a new reference to the same underlying executor.
-/// Given the single threaded nature of this executor, tasks are executed sequentially, and a long
-/// running task will cause delay in other subsequent executions.
-pub struct CoreExecutor {
-    inner: Arc<CoreExecutorInner>
+impl From<IntervalError> for ExecutorError {
+    fn from(e: IntervalError) -> ExecutorError {
+        ExecutorError::InvalidSchedule(e.to_string())
+    }
 }
 
-impl Clone for CoreExecutor {
-    fn clone(&self) -> Self {
-        CoreExecutor { inner: Arc::clone(&self.inner) }
+impl From<CronError> for ExecutorError {
+    fn from(e: CronError) -> ExecutorError {
+        ExecutorError::InvalidSchedule(e.to_string())
     }
 }
 
-impl CoreExecutor {
-    /// Creates a new `CoreExecutor`.
-    pub fn new() -> Result<CoreExecutor, io::Error> {
-        CoreExecutor::with_name("core_executor")
+/// Allows the executor constructors to be used in functions returning `io::Error`.
+impl From<ExecutorError> for io::Error {
+    fn from(e: ExecutorError) -> io::Error {
+        match e {
+            ExecutorError::ThreadSpawn(e) | ExecutorError::ReactorInit(e) | ExecutorError::Io(e) => e,
+            e => io::Error::other(e),
+        }
     }
+}
 
-    /// Creates a new `CoreExecutor` with the specified thread name.
-    pub fn with_name(thread_name: &str) -> Result<CoreExecutor, io::Error> {
-        let (termination_tx, termination_rx) = channel();
-        let (core_tx, core_rx) = channel();
-        let thread_handle = thread::Builder::new()
-            .name(thread_name.to_owned())
-            .spawn(move || {
-                debug!("Core starting");
-                let mut core = Core::new().expect("Failed to start core");
-                let _ = core_tx.send(core.remote());
-                match core.run(termination_rx) {
-                    Ok(v) => debug!("Core terminated correctly {:?}", v),
-                    Err(e) => debug!("Core terminated with error: {:?}", e),
-                }
-            })?;
-        let inner = CoreExecutorInner {
-            remote: core_rx.wait().expect("Failed to receive remote"),
-            termination_sender: Some(termination_tx),
-            thread_handle: Some(thread_handle),
-        };
-        let executor = CoreExecutor {
-            inner: Arc::new(inner)
-        };
-        debug!("Executor created");
-        Ok(executor)
-    }
+/// Defines what happens when an execution of a task panics. The panic is always caught and
+/// logged, so it won't affect the executor or the other tasks.
+#[derive(Default)]
+pub enum PanicPolicy {
+    /// The task keeps running according to its schedule. This is the default.
+    #[default]
+    Ignore,
+    /// The task is stopped.
+    StopTask,
+    /// The callback is invoked with the panic payload, and the task keeps running.
+    Callback(Box<dyn Fn(Box<dyn Any + Send>) + Send + Sync>),
+}
 
-    /// Schedule a function for running at fixed intervals. The executor will try to run the
-    /// function every `interval`, but if one execution takes longer than `interval` it will delay
-    /// all the subsequent calls.
-    pub fn schedule_fixed_interval<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
-        where F: FnMut(&Handle) + Send + 'static
-    {
-        let task_handle = TaskHandle::new();
-        let task_handle_clone = task_handle.clone();
-        self.inner.remote.spawn(move |handle| {
-            let handle_clone = handle.clone();
-            let t = Timeout::new(initial, handle).unwrap()
-                .then(move |_| {
-                    fixed_interval_loop(scheduled_fn, interval, &handle_clone, task_handle_clone);
-                    Ok::<(), ()>(())
-                });
-            handle.spawn(t);
-            Ok::<(), ()>(())
-        });
-        task_handle
+/// Defines what happens on a `ThreadPoolExecutor` when an execution of a task is due while the
+/// previous one is still running on the pool. Executions on a `CoreExecutor` never overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(rename_all = "snake_case"))]
+pub enum OverlapPolicy {
+    /// The execution is submitted to the pool anyway, and runs concurrently with the previous
+    /// one. This is the default.
+    Allow,
+    /// The execution is skipped, and counted in `TaskStats::skipped`.
+    Skip,
+    /// The schedule waits for the previous execution to complete before computing the time of
+    /// the next one, so executions run one after the other; a fixed rate schedule then runs
+    /// the delayed executions back-to-back to recover. This is the policy of the tasks scheduled
+    /// with `ThreadPoolExecutor::schedule_fixed_interval`.
+    Queue,
+    /// Up to the given number of executions run concurrently (a limit of 0 is treated as 1).
+    /// Once the limit is reached, further executions are skipped, or queued if
+    /// `TaskHandle::set_queue_at_limit` was set: the schedule then waits for one of the running
+    /// executions to complete.
+    Concurrent(usize),
+}
+
+/// Defines what happens when a task scheduled with one of the `_results` methods produces a value
+/// while its `ResultStream` is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The schedule waits for the consumer to make room before computing the time of the next
+    /// execution, like a fixed interval schedule.
+    Block,
+    /// The oldest value of the stream is dropped, and counted in `ResultStream::dropped`.
+    DropOldest,
+}
+
+/// The clock the waits of a task are measured with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockSource {
+    /// The waits are measured with `Instant`, which doesn't advance while the system is
+    /// suspended: a task due during a suspension runs later after resuming. This is the default.
+    #[default]
+    Monotonic,
+    /// The waits are also checked against the system clock, every `WALL_CLOCK_CHECK_INTERVAL`:
+    /// a task due during a suspension runs promptly after resuming, once. The system clock only
+    /// makes the waits shorter, so setting it backwards doesn't delay the task.
+    Wall,
+}
+
+/// How often a task following `ClockSource::Wall` checks the system clock while waiting.
+pub const WALL_CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The default minimum interval of the tasks, see `ExecutorBuilder::min_interval`. A task
+/// scheduled with a shorter interval, zero in particular, would keep the executor thread busy,
+/// so its interval is raised to this. This doesn't apply to schedules without an interval, nor
+/// to a fixed rate task catching up on its delay.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The name returned by `Task::name` by default; the tasks with this name are left unnamed.
+const DEFAULT_TASK_NAME: &str = "unnamed";
+
+/// A job that can be scheduled as is, instead of being wrapped in a closure, see
+/// `CoreExecutor::schedule_task`. `H` is what the executor passes to the executions: a `Handle`
+/// on a `CoreExecutor`, a `Remote` on a `ThreadPoolExecutor`. Boxed tasks of different types can
+/// be kept in the same collection.
+pub trait Task<H = Handle>: Send {
+    /// Runs an execution of the task.
+    fn run(&mut self, handle: &H, context: &TaskContext);
+
+    /// Returns the name the task is registered with, see `TaskHandle::set_name`.
+    fn name(&self) -> &str {
+        DEFAULT_TASK_NAME
     }
+}
 
-    /// Schedule a function for running at fixed rate. The executor will try to run the function
-    /// every `interval`, and if a task execution takes longer than `interval`, the wait time
-    /// between task will be reduced to decrease the overall delay.
-    pub fn schedule_fixed_rate<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
-        where F: FnMut(&Handle) + Send + 'static
-    {
-        let task_handle = TaskHandle::new();
-        let task_handle_clone = task_handle.clone();
-        self.inner.remote.spawn(move |handle| {
-            let handle_clone = handle.clone();
-            let t = Timeout::new(initial, handle).unwrap()
-                .then(move |_| {
-                    fixed_rate_loop(scheduled_fn, interval, &handle_clone, Duration::from_secs(0), task_handle_clone);
-                    Ok::<(), ()>(())
-                });
-            handle.spawn(t);
-            Ok::<(), ()>(())
-        });
-        task_handle
+/// Creates the handle of a task, named after it.
+fn task_trait_handle<H>(task: &dyn Task<H>) -> TaskHandle {
+    let task_handle = TaskHandle::new();
+    if task.name() != DEFAULT_TASK_NAME {
+        task_handle.set_name(task.name());
     }
+    task_handle
 }
 
+/// Reports the panics of all the tasks of an executor, see `CoreExecutor::set_panic_handler`.
+type PanicHandler = dyn Fn(&str, Box<dyn Any + Send>) + Send + Sync;
 
-/// A `ThreadPoolExecutor` will use one thread for the task scheduling and a thread pool for
-/// task execution, allowing multiple tasks to run in parallel.
-#[derive(Clone)]
-pub struct ThreadPoolExecutor {
-    executor: CoreExecutor,
-    pool: CpuPool
+/// Observes the lifecycle of all the tasks of an executor, see `CoreExecutor::set_observer`. All
+/// the methods do nothing by default. The methods are invoked from the thread where the event
+/// happens (for example, a pool thread for the executions of a `ThreadPoolExecutor`), possibly
+/// while the state of the task is locked: they should return quickly, and they must not call
+/// the methods of the executor or of the task handles.
+pub trait ExecutorObserver: Send + Sync {
+    /// Invoked when a task is scheduled.
+    fn on_schedule(&self, _task: &TaskInfo) {}
+
+    /// Invoked right before an execution of a task starts.
+    fn on_run_start(&self, _context: &TaskContext) {}
+
+    /// Invoked after an execution of a task returns, including when it panicked.
+    fn on_run_end(&self, _context: &TaskContext, _took: Duration) {}
+
+    /// Invoked when an execution of a task panics, before `on_run_end`.
+    fn on_panic(&self, _context: &TaskContext, _message: &str) {}
+
+    /// Invoked when an execution on a thread pool doesn't complete within the execution timeout
+    /// of its task, see `TaskHandle::set_execution_timeout`.
+    fn on_timeout(&self, _task: &TaskInfo, _timeout: Duration) {}
+
+    /// Invoked when a task is stopped, for whatever reason.
+    fn on_stop(&self, _task: &TaskInfo, _reason: TerminationReason) {}
 }
 
-impl ThreadPoolExecutor {
-    /// Creates a new `ThreadPoolExecutor` with the specified number of threads. Threads will
-    /// be named "pool_thread_0", "pool_thread_1" and so on.
-    pub fn new(threads: usize) -> Result<ThreadPoolExecutor, io::Error> {
-        ThreadPoolExecutor::with_prefix(threads, "pool_thread_")
+/// An `ExecutorObserver` that logs every event through the `log` crate: executions are logged at
+/// debug level, panics at warn level and everything else at info level.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LoggingObserver;
+
+impl ExecutorObserver for LoggingObserver {
+    fn on_schedule(&self, task: &TaskInfo) {
+        info!("Task {} ({}) scheduled", task.id, task.name.as_deref().unwrap_or(UNNAMED_TASK));
     }
 
-    /// Creates a new `ThreadPoolExecutor` with the specified number of threads and prefix for
-    /// the thread names.
-    pub fn with_prefix(threads: usize, prefix: &str) -> Result<ThreadPoolExecutor, io::Error> {
-        let new_executor = CoreExecutor::with_name(&format!("{}executor", prefix))?;
-        Ok(ThreadPoolExecutor::with_executor(threads, prefix, new_executor))
+    fn on_run_start(&self, context: &TaskContext) {
+        debug!("Task {} run {} started, {:?} late", context.task_handle().id(), context.run(), context.lateness());
     }
 
-    /// Creates a new `ThreadPoolExecutor` with the specified number of threads, prefix and
-    /// using the given `CoreExecutor` for scheduling.
-    pub fn with_executor(threads: usize, prefix: &str, executor: CoreExecutor) -> ThreadPoolExecutor {
-        let pool = Builder::new()
-            .pool_size(threads)
-            .name_prefix(prefix)
-            .create();
-        ThreadPoolExecutor { pool, executor }
+    fn on_run_end(&self, context: &TaskContext, took: Duration) {
+        debug!("Task {} run {} completed in {:?}", context.task_handle().id(), context.run(), took);
     }
 
-    /// Schedules the given function to be executed every `interval`. The function will be
-    /// scheduled on one of the threads in the thread pool.
-    pub fn schedule_fixed_rate<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
-        where F: Fn(&Remote) + Send + Sync + 'static
-    {
-        let pool_clone = self.pool.clone();
-        let arc_fn = Arc::new(scheduled_fn);
-        self.executor.schedule_fixed_interval(  // Fixed interval is enough
-            initial,
-            interval,
-            move |handle| {
-                let arc_fn_clone = arc_fn.clone();
-                let remote = handle.remote().clone();
-                let t = pool_clone.spawn_fn(move || {
-                    arc_fn_clone(&remote);
-                    Ok::<(),()>(())
-                });
-                handle.spawn(t);
-            }
-        )
+    fn on_panic(&self, context: &TaskContext, message: &str) {
+        warn!("Task {} run {} panicked: {}", context.task_handle().id(), context.run(), message);
     }
 
-    // TODO: make pub(crate)
-    /// Returns the thread pool used internally.
-    pub fn pool(&self) -> &CpuPool {
-        &self.pool
+    fn on_timeout(&self, task: &TaskInfo, timeout: Duration) {
+        warn!("Task {} ({}) execution timed out after {:?}", task.id, task.name.as_deref().unwrap_or(UNNAMED_TASK), timeout);
+    }
+
+    fn on_stop(&self, task: &TaskInfo, reason: TerminationReason) {
+        info!("Task {} ({}) stopped: {:?}", task.id, task.name.as_deref().unwrap_or(UNNAMED_TASK), reason);
     }
 }
 
+/// Receives the metrics of an executor, to bridge them to a metrics library, see
+/// `CoreExecutor::set_metrics_sink`. The labels are pairs of label names and values.
+pub trait MetricsSink: Send + Sync {
+    /// Sets the current value of a gauge.
+    fn gauge(&self, name: &str, labels: &[(&str, &str)], value: f64);
 
-#[cfg(test)]
-mod tests {
-    use std::sync::{Arc, RwLock};
-    use std::thread;
-    use std::time::{Duration, Instant};
+    /// Sets the current total of a counter, which only ever increases. The default implementation
+    /// sets it as a gauge.
+    fn counter(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.gauge(name, labels, value);
+    }
 
-    use super::{CoreExecutor, ThreadPoolExecutor, calculate_delay};
+    /// Records an observation of a histogram.
+    fn histogram_observe(&self, name: &str, labels: &[(&str, &str)], value: f64);
 
-    #[test]
-    fn fixed_interval_test() {
-        let timings = Arc::new(RwLock::new(Vec::new()));
-        {
-            let executor = CoreExecutor::new().unwrap();
-            let timings_clone = Arc::clone(&timings);
-            executor.schedule_fixed_rate(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
-                    timings_clone.write().unwrap().push(Instant::now());
-                }
-            );
-            thread::sleep(Duration::from_millis(5500));
-        }
+    /// Invoked once all the gauges and counters of a periodic report were set. The default
+    /// implementation does nothing.
+    fn flush(&self) {}
+}
 
-        let timings = timings.read().unwrap();
-        assert_eq!(timings.len(), 6);
-        for i in 1..6 {
-            let execution_interval = timings[i] - timings[i-1];
-            assert!(execution_interval < Duration::from_millis(1020));
-            assert!(execution_interval > Duration::from_millis(980));
+/// The prefix of the log targets of the tasks, followed by the name of the task, or its id if it
+/// wasn't given one.
+pub const TASK_LOG_TARGET: &str = "scheduled_executor::task";
+
+/// The label identifying a task in the metrics: its name, or its id if it wasn't given one.
+fn task_label(id: TaskId, name: Option<&str>) -> String {
+    name.map(|name| name.to_owned()).unwrap_or_else(|| id.to_string())
+}
+
+/// Reports the gauges and counters of an executor and of its tasks to the sink, then flushes it.
+fn report_metrics(inner: &CoreExecutorInner, sink: &dyn MetricsSink) {
+    let tasks = inner.live_tasks();
+    let metrics = inner.hooks.metrics(tasks.iter().filter(|task| !task.stopped()).count(), false);
+    sink.gauge("scheduled_executor_live_tasks", &[], metrics.live_tasks as f64);
+    sink.counter("scheduled_executor_executions_total", &[], metrics.executions as f64);
+    sink.counter("scheduled_executor_panics_total", &[], metrics.panics as f64);
+    sink.gauge("scheduled_executor_queued_executions", &[], metrics.queued as f64);
+    for task in &tasks {
+        let executions = task.executions();
+        let label = task_label(task.id, executions.name.as_deref());
+        let labels = [("task", label.as_str())];
+        sink.counter("scheduled_executor_task_executions_total", &labels, executions.stats.executions as f64);
+        sink.counter("scheduled_executor_task_panics_total", &labels, executions.stats.panics as f64);
+        sink.counter("scheduled_executor_task_skipped_total", &labels, executions.stats.skipped as f64);
+    }
+    sink.flush();
+}
+
+/// The labels of a metric, sorted by name.
+type Labels = Vec<(String, String)>;
+
+/// A `MetricsSink` keeping the metrics in memory, and writing all of them to the given writer in
+/// the Prometheus text exposition format on every flush. The histograms use the buckets of
+/// `Histogram`, in seconds.
+pub struct PrometheusTextSink<W> {
+    writer: Mutex<W>,
+    metrics: Mutex<PrometheusMetrics>,
+}
+
+#[derive(Default)]
+struct PrometheusMetrics {
+    counters: BTreeMap<String, BTreeMap<Labels, f64>>,
+    gauges: BTreeMap<String, BTreeMap<Labels, f64>>,
+    histograms: BTreeMap<String, BTreeMap<Labels, PrometheusHistogram>>,
+}
+
+#[derive(Default)]
+struct PrometheusHistogram {
+    /// The number of observations in each bucket, see `HISTOGRAM_BOUNDS_MS`.
+    buckets: [u64; 18],
+    sum: f64,
+}
+
+fn to_labels(labels: &[(&str, &str)]) -> Labels {
+    let mut labels = labels.iter().map(|&(name, value)| (name.to_owned(), value.to_owned())).collect::<Labels>();
+    labels.sort();
+    labels
+}
+
+fn write_labels(out: &mut String, labels: &Labels, le: Option<&str>) {
+    let le = le.map(|le| ("le", le));
+    let mut labels = labels.iter().map(|(name, value)| (name.as_str(), value.as_str())).chain(le).peekable();
+    if labels.peek().is_none() {
+        return;
+    }
+    out.push('{');
+    for (index, (name, value)) in labels.enumerate() {
+        if index > 0 {
+            out.push(',');
         }
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        out.push_str(&format!("{}=\"{}\"", name, value));
     }
+    out.push('}');
+}
 
-    #[test]
-    fn fixed_interval_slow_task_test() {
-        let counter = Arc::new(RwLock::new(0));
-        let counter_clone = Arc::clone(&counter);
-        {
-            let executor = CoreExecutor::new().unwrap();
-            executor.schedule_fixed_interval(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
-                    // TODO: use atomic int when available
-                    let counter = {
-                        let mut counter = counter_clone.write().unwrap();
-                        (*counter) += 1;
-                        *counter
+impl<W: Write + Send> PrometheusTextSink<W> {
+    /// Creates a sink writing to `writer`.
+    pub fn new(writer: W) -> PrometheusTextSink<W> {
+        PrometheusTextSink { writer: Mutex::new(writer), metrics: Mutex::new(PrometheusMetrics::default()) }
+    }
+
+    /// Returns the current metrics in the Prometheus text exposition format, as written on every
+    /// flush.
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let mut out = String::new();
+        for (kind, metrics) in &[("counter", &metrics.counters), ("gauge", &metrics.gauges)] {
+            for (name, series) in *metrics {
+                out.push_str(&format!("# TYPE {} {}\n", name, kind));
+                for (labels, value) in series {
+                    out.push_str(name);
+                    write_labels(&mut out, labels, None);
+                    out.push_str(&format!(" {}\n", value));
+                }
+            }
+        }
+        for (name, series) in &metrics.histograms {
+            out.push_str(&format!("# TYPE {} histogram\n", name));
+            for (labels, histogram) in series {
+                let mut count = 0;
+                for (bucket, &bucket_count) in histogram.buckets.iter().enumerate() {
+                    count += bucket_count;
+                    let le = match HISTOGRAM_BOUNDS_MS.get(bucket) {
+                        Some(&bound) => (bound as f64 / 1000.0).to_string(),
+                        None => "+Inf".to_owned(),
                     };
-                    if counter == 1 {
-                        thread::sleep(Duration::from_secs(3));
-                    }
+                    out.push_str(&format!("{}_bucket", name));
+                    write_labels(&mut out, labels, Some(&le));
+                    out.push_str(&format!(" {}\n", count));
                 }
-            );
-            thread::sleep(Duration::from_millis(5500));
+                out.push_str(&format!("{}_sum", name));
+                write_labels(&mut out, labels, None);
+                out.push_str(&format!(" {}\n{}_count", histogram.sum, name));
+                write_labels(&mut out, labels, None);
+                out.push_str(&format!(" {}\n", count));
+            }
         }
-        assert_eq!(*counter.read().unwrap(), 4);
+        out
+    }
+}
+
+impl<W: Write + Send> MetricsSink for PrometheusTextSink<W> {
+    fn gauge(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.gauges.entry(name.to_owned()).or_default().insert(to_labels(labels), value);
     }
 
-    #[test]
-    fn calculate_delay_test() {
-        fn s(n: u64) -> Duration { Duration::from_secs(n) };
-        assert_eq!(calculate_delay(s(10), s(3), s(0)), (s(7), s(0)));
-        assert_eq!(calculate_delay(s(10), s(11), s(0)), (s(0), s(1)));
-        assert_eq!(calculate_delay(s(10), s(3), s(3)), (s(4), s(0)));
-        assert_eq!(calculate_delay(s(10), s(3), s(9)), (s(0), s(2)));
-        assert_eq!(calculate_delay(s(10), s(12), s(15)), (s(0), s(17)));
+    fn counter(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.counters.entry(name.to_owned()).or_default().insert(to_labels(labels), value);
     }
 
-    #[test]
-    fn fixed_rate_test() {
-        let counter = Arc::new(RwLock::new(0));
-        let counter_clone = Arc::clone(&counter);
-        {
-            let executor = CoreExecutor::new().unwrap();
-            executor.schedule_fixed_rate(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
-                    let mut counter = counter_clone.write().unwrap();
-                    (*counter) += 1;
-                }
-            );
-            thread::sleep(Duration::from_millis(5500));
+    fn histogram_observe(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        let histogram = metrics.histograms.entry(name.to_owned()).or_default().entry(to_labels(labels)).or_default();
+        let bucket = HISTOGRAM_BOUNDS_MS.iter().position(|&bound| value * 1000.0 <= bound as f64)
+            .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+        histogram.buckets[bucket] += 1;
+        histogram.sum += value;
+    }
+
+    fn flush(&self) {
+        let text = self.render();
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(e) = writer.write_all(text.as_bytes()).and_then(|_| writer.flush()) {
+            warn!("Failed to write the metrics: {}", e);
         }
-        assert_eq!(*counter.read().unwrap(), 6);
     }
+}
 
-    #[test]
-    fn fixed_rate_slow_task_test() {
-        let counter = Arc::new(RwLock::new(0));
-        let counter_clone = Arc::clone(&counter);
-        {
-            let executor = CoreExecutor::new().unwrap();
-            executor.schedule_fixed_rate(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
+/// The settings of an executor that apply to all of its tasks, shared with them.
+#[derive(Default)]
+struct ExecutorHooks {
+    /// The panic policy of the tasks that don't define their own.
+    panic_policy: RwLock<Arc<PanicPolicy>>,
+    /// Reports the panics of all the tasks, if set.
+    panic_handler: RwLock<Option<Arc<PanicHandler>>>,
+    observer: RwLock<Option<Arc<dyn ExecutorObserver>>>,
+    /// Records the durations and lateness of the executions, if set.
+    metrics_sink: RwLock<Option<Arc<dyn MetricsSink>>>,
+    /// The number of completed executions of all the tasks.
+    executions: AtomicU64,
+    /// The value of `executions` when the last metrics snapshot was taken.
+    reported_executions: AtomicU64,
+    panics: AtomicU64,
+    /// The number of executions submitted to the thread pool that didn't start yet.
+    queued: AtomicUsize,
+    /// The number of executions submitted to the thread pool that didn't complete yet.
+    in_flight: AtomicUsize,
+    saturation: RwLock<Option<Saturation>>,
+    /// The level of the lifecycle log lines of the tasks, debug if not set.
+    task_log_level: RwLock<Option<LogLevelFilter>>,
+}
+
+impl ExecutorHooks {
+    fn panic_policy(&self) -> Arc<PanicPolicy> {
+        self.panic_policy.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn panic_handler(&self) -> Option<Arc<PanicHandler>> {
+        self.panic_handler.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn observer(&self) -> Option<Arc<dyn ExecutorObserver>> {
+        self.observer.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn metrics_sink(&self) -> Option<Arc<dyn MetricsSink>> {
+        self.metrics_sink.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns the threshold and the handler of the saturation of the pool, if set.
+    fn saturation(&self) -> Option<(usize, Arc<SaturationHandler>)> {
+        let saturation = self.saturation.read().unwrap_or_else(|e| e.into_inner());
+        saturation.as_ref().map(|saturation| (saturation.threshold, saturation.handler.clone()))
+    }
+
+    fn task_log_level(&self) -> Option<LogLevel> {
+        let level = *self.task_log_level.read().unwrap_or_else(|e| e.into_inner());
+        level.unwrap_or(LogLevelFilter::Debug).to_log_level()
+    }
+
+    /// Returns the metrics of the executor; `recent_executions` is reset only if `report` is set.
+    fn metrics(&self, live_tasks: usize, report: bool) -> ExecutorMetrics {
+        let executions = self.executions.load(Ordering::Relaxed);
+        let reported_executions = if report {
+            self.reported_executions.swap(executions, Ordering::Relaxed)
+        } else {
+            self.reported_executions.load(Ordering::Relaxed)
+        };
+        ExecutorMetrics {
+            live_tasks,
+            executions,
+            recent_executions: executions.saturating_sub(reported_executions),
+            panics: self.panics.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The single-thread pools running the tasks with an affinity, see `TaskHandle::set_affinity`.
+struct AffinityPools {
+    /// The number of pools, one for each thread of the shared pool. With no pools, the tasks
+    /// with an affinity run on the shared pool.
+    threads: usize,
+    /// The prefix of the names of the threads.
+    prefix: String,
+    /// The configuration of the threads, the same as the shared pool.
+    config: ThreadConfig,
+    /// Created the first time a task with an affinity runs.
+    pools: Mutex<Vec<CpuPool>>,
+}
+
+impl AffinityPools {
+    /// Returns the pool dedicated to the given key, if any.
+    fn pool(&self, key: u64) -> Option<CpuPool> {
+        if self.threads == 0 {
+            return None;
+        }
+        let mut pools = self.pools.lock().unwrap();
+        if pools.is_empty() {
+            debug!("Creating {} single-thread pools for the tasks with an affinity", self.threads);
+            *pools = (0..self.threads)
+                .map(|index| self.config.pool_builder(1, &format!("{}affine_{}_", self.prefix, index)).create())
+                .collect();
+        }
+        Some(pools[(key % self.threads as u64) as usize].clone())
+    }
+}
+
+/// The pool of a `ThreadPoolExecutor`, replaced by `ThreadPoolExecutor::resize`. The executions
+/// always go to the current pool, while the ones already submitted to a replaced pool complete
+/// there.
+#[derive(Clone)]
+pub(crate) struct SharedPool {
+    inner: Arc<RwLock<SizedPool>>,
+}
+
+struct SizedPool {
+    pool: CpuPool,
+    /// The number of threads of the pool, 0 if unknown.
+    size: usize,
+}
+
+impl SharedPool {
+    fn new(pool: CpuPool, size: usize) -> SharedPool {
+        SharedPool { inner: Arc::new(RwLock::new(SizedPool { pool, size })) }
+    }
+
+    /// Returns the pool new executions should be submitted to.
+    pub(crate) fn current(&self) -> CpuPool {
+        self.inner.read().unwrap().pool.clone()
+    }
+
+    fn size(&self) -> usize {
+        self.inner.read().unwrap().size
+    }
+
+    fn replace(&self, pool: CpuPool, size: usize) {
+        *self.inner.write().unwrap() = SizedPool { pool, size };
+    }
+}
+
+/// Submits the executions of the tasks of a `ThreadPoolExecutor` to its pool.
+#[derive(Clone)]
+struct PoolSpawner {
+    pool: SharedPool,
+    affinity_pools: Arc<AffinityPools>,
+    hooks: Arc<ExecutorHooks>,
+}
+
+impl PoolSpawner {
+    /// Runs `job` on the pool, watching for the execution timeout of the task.
+    fn spawn<F>(&self, handle: &Handle, task_handle: &TaskHandle, job: F)
+        where F: FnOnce() + Send + 'static
+    {
+        handle.spawn(self.submit(handle, task_handle, job));
+    }
+
+    /// Submits `job` to the pool, returning a future that resolves once the execution completes,
+    /// or once it times out.
+    fn submit<F>(&self, handle: &Handle, task_handle: &TaskHandle, job: F) -> impl Future<Item = (), Error = ()>
+        where F: FnOnce() + Send + 'static
+    {
+        let in_flight = InFlightExecution::new(&self.hooks);
+        let queued = QueuedExecution::new(&self.hooks);
+        let affinity = task_handle.inner.executions().affinity;
+        let pool = affinity.and_then(|key| self.affinity_pools.pool(key));
+        let pool = pool.unwrap_or_else(|| self.pool.current());
+        let t = pool.spawn_fn(move || {
+            drop(queued);
+            job();
+            drop(in_flight);
+            Ok::<(),()>(())
+        });
+        let timeout = match task_handle.inner.executions().execution_timeout {
+            Some(timeout) => timeout,
+            None => return Either::A(t),
+        };
+        let timer = match new_timeout(timeout, handle) {
+            Ok(timer) => timer,
+            Err(e) => {
+                warn!("Failed to create the execution timer, the execution is not timed: {}", e);
+                return Either::A(t);
+            }
+        };
+        let task_handle = task_handle.clone();
+        Either::B(t.select2(timer).then(move |result| {
+            match result {
+                Ok(Either::A(_)) | Err(Either::A(_)) => (),
+                // Dropping the pool future would cancel the execution if it didn't start yet
+                Ok(Either::B((_, execution))) => {
+                    task_handle.inner.record_timeout(timeout);
+                    execution.forget();
+                }
+                Err(Either::B((e, execution))) => {
+                    warn!("Execution timer failed, the execution is not timed: {}", e);
+                    execution.forget();
+                }
+            }
+            Ok(())
+        }))
+    }
+}
+
+/// Counts an execution as queued on the thread pool until dropped, that is until the pool
+/// starts running it (or discards it).
+struct QueuedExecution(Arc<ExecutorHooks>);
+
+impl QueuedExecution {
+    fn new(hooks: &Arc<ExecutorHooks>) -> QueuedExecution {
+        hooks.queued.fetch_add(1, Ordering::Relaxed);
+        QueuedExecution(hooks.clone())
+    }
+}
+
+impl Drop for QueuedExecution {
+    fn drop(&mut self) {
+        self.0.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts an execution as in flight on the thread pool until dropped, that is until the execution
+/// completes (or the pool discards it). Reports the saturation of the pool when the count reaches
+/// its threshold.
+struct InFlightExecution(Arc<ExecutorHooks>);
+
+impl InFlightExecution {
+    fn new(hooks: &Arc<ExecutorHooks>) -> InFlightExecution {
+        let in_flight = hooks.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some((threshold, handler)) = hooks.saturation().filter(|&(threshold, _)| in_flight == threshold) {
+            warn!("Thread pool saturated, {} executions in flight", threshold);
+            handler(in_flight);
+        }
+        InFlightExecution(hooks.clone())
+    }
+}
+
+impl Drop for InFlightExecution {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The number of executions of a task `TaskHandle::busy_fraction` is computed from.
+pub const BUSY_HISTORY: usize = 256;
+
+/// The name used for the tasks that weren't given one.
+const UNNAMED_TASK: &str = "<unnamed>";
+
+/// Extracts the message from a panic payload, if it's a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string payload>"
+    }
+}
+
+/// Runs a task out of schedule; the flag tells whether its schedule should restart from there.
+type Trigger = dyn Fn(&TaskHandle, bool) + Send + Sync;
+
+/// Execution statistics of a task, as returned by `TaskHandle::stats`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TaskStats {
+    /// The number of completed executions, including the ones that panicked.
+    pub executions: u64,
+    /// The number of executions that panicked.
+    pub panics: u64,
+    /// The number of executions that didn't complete within the execution timeout of the task.
+    /// They are still counted in `executions` once they complete.
+    pub timeouts: u64,
+    /// The number of executions skipped because the previous ones were still running, see
+    /// `OverlapPolicy`.
+    pub skipped: u64,
+    /// The number of ticks the schedule skipped because the task fell behind, see
+    /// `MissedTickPolicy`.
+    pub skipped_ticks: u64,
+    /// The number of executions slower than the threshold of the task, see
+    /// `TaskHandle::warn_if_slower_than`.
+    pub slow: u64,
+    /// When the last execution started.
+    #[cfg_attr(feature = "serde", serde(rename = "last_start_ms_ago", serialize_with = "serialize_millis_ago"))]
+    pub last_start: Option<Instant>,
+    /// When the last completed execution ended.
+    #[cfg_attr(feature = "serde", serde(rename = "last_end_ms_ago", serialize_with = "serialize_millis_ago"))]
+    pub last_end: Option<Instant>,
+    /// How long the last completed execution took.
+    #[cfg_attr(feature = "serde", serde(rename = "last_duration_ms", serialize_with = "serialize_millis"))]
+    pub last_duration: Option<Duration>,
+}
+
+/// Serializes a duration as a number of milliseconds.
+#[cfg(feature = "serde")]
+fn serialize_millis<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.map(|duration| duration.as_secs_f64() * 1000.0).serialize(serializer)
+}
+
+#[cfg(feature = "serde")]
+fn serialize_total_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_millis(&Some(*duration), serializer)
+}
+
+/// Serializes an instant as the number of milliseconds elapsed since, which stays meaningful
+/// outside of the process, unlike the instant itself.
+#[cfg(feature = "serde")]
+fn serialize_millis_ago<S: Serializer>(instant: &Option<Instant>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_millis(&instant.map(|instant| instant.elapsed()), serializer)
+}
+
+/// The state of all the tasks of an executor, see `CoreExecutor::dump_json`.
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct RegistrySnapshot {
+    metrics: ExecutorMetrics,
+    tasks: Vec<TaskSnapshot>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct TaskSnapshot {
+    #[serde(flatten)]
+    info: TaskInfo,
+    stats: TaskStats,
+}
+
+/// The upper bounds of the buckets of a `Histogram`, in milliseconds: powers of two from 1ms, up
+/// to 60s. Longer durations fall in a last, unbounded bucket.
+const HISTOGRAM_BOUNDS_MS: [u64; 17] = [1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 60000];
+
+/// The distribution of the execution durations of a task, as returned by
+/// `TaskHandle::duration_histogram`. Durations are counted in logarithmic buckets, so the
+/// percentiles are approximated by the upper bound of the bucket they fall in.
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    /// The number of durations in each bucket, see `HISTOGRAM_BOUNDS_MS`.
+    buckets: [u64; 18],
+    max: Duration,
+}
+
+impl Histogram {
+    /// Returns the number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Returns the longest recorded duration, or `None` if nothing was recorded.
+    pub fn max(&self) -> Option<Duration> {
+        if self.count() == 0 { None } else { Some(self.max) }
+    }
+
+    /// Returns an upper bound of the given percentile (between 0 and 100) of the recorded
+    /// durations, or `None` if nothing was recorded. The bound is never above `max`.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0 * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &bucket_count) in self.buckets.iter().enumerate() {
+            seen += bucket_count;
+            if seen >= rank {
+                return Some(match HISTOGRAM_BOUNDS_MS.get(bucket) {
+                    Some(&bound) => Duration::from_millis(bound).min(self.max),
+                    None => self.max,
+                });
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Records durations into atomic `Histogram` buckets.
+#[derive(Default)]
+struct DurationRecorder {
+    buckets: [AtomicU64; 18],
+    /// The longest duration, in nanoseconds.
+    max: AtomicU64,
+}
+
+impl DurationRecorder {
+    fn record(&self, duration: Duration) {
+        let millis = duration.as_millis();
+        let bucket = HISTOGRAM_BOUNDS_MS.iter()
+            .position(|&bound| millis < u128::from(bound))
+            .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        let nanos = duration.as_nanos().min(u128::from(u64::MAX)) as u64;
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Histogram {
+        let mut histogram = Histogram::default();
+        for (count, bucket) in histogram.buckets.iter_mut().zip(&self.buckets) {
+            *count = bucket.load(Ordering::Relaxed);
+        }
+        histogram.max = Duration::from_nanos(self.max.load(Ordering::Relaxed));
+        histogram
+    }
+}
+
+impl TaskState {
+    fn executions(&self) -> MutexGuard<'_, Executions> {
+        self.executions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stopped(&self) -> bool {
+        self.should_stop.load(Ordering::Relaxed)
+    }
+
+    fn running(&self) -> usize {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Sets the stop flag. The lock must be held, so that no new execution can start afterwards.
+    /// Only the first reason a task is stopped for is recorded. The children of the task are
+    /// stopped as well. Returns true if the task wasn't already stopped.
+    fn stop(&self, executions: &mut Executions, reason: TerminationReason) -> bool {
+        let transitioned = !self.should_stop.swap(true, Ordering::Relaxed);
+        if transitioned {
+            executions.termination_reason = Some(reason);
+            executions.stopped_at = Some(Instant::now());
+            if let Some(observer) = executions.executor_hooks.as_ref().and_then(|hooks| hooks.observer()) {
+                observer.on_stop(&self.info_locked(executions), reason);
+            }
+            if let Some((level, target)) = self.log_target(executions) {
+                log!(target: &target, level, "Task {} stopped after {} runs: {:?}", self.id, executions.runs, reason);
+            }
+        }
+        executions.next_run = None;
+        executions.trigger = None;
+        for cancellation in executions.cancellations.drain(..) {
+            let _ = cancellation.send(());
+        }
+        for timer in executions.timers.drain(..) {
+            timer.cancel();
+        }
+        self.notify_if_terminated(executions);
+        // Tasks only form trees, so the locks are always taken from parent to child
+        for child in executions.children.drain(..).filter_map(|child| child.upgrade()) {
+            child.stop(&mut child.executions(), TerminationReason::ParentStopped);
+        }
+        transitioned
+    }
+
+    /// Returns true if `ancestor` is this task, or one of its ancestors.
+    fn descends_from(&self, ancestor: &TaskState) -> bool {
+        if ptr::eq(self, ancestor) {
+            return true;
+        }
+        let parent = self.executions().parent.as_ref().and_then(|parent| parent.upgrade());
+        parent.is_some_and(|parent| parent.descends_from(ancestor))
+    }
+
+    fn handle_panic(&self, payload: Box<dyn Any + Send>) {
+        let (policy, handler, name) = {
+            let executions = self.executions();
+            let hooks = executions.executor_hooks.as_ref();
+            let policy = executions.panic_policy.clone().or_else(|| hooks.map(|hooks| hooks.panic_policy()));
+            let handler = hooks.and_then(|hooks| hooks.panic_handler());
+            (policy, handler, executions.name.clone())
+        };
+        let name = name.as_deref().unwrap_or(UNNAMED_TASK);
+        let callback = match policy.as_deref() {
+            Some(PanicPolicy::Callback(callback)) => Some(callback),
+            _ => None,
+        };
+        match (handler, callback) {
+            // The payload can only be handed over once, so the handler gets a copy of its message
+            (Some(handler), Some(callback)) => {
+                handler(name, Box::new(panic_message(&*payload).to_owned()));
+                callback(payload);
+            }
+            (Some(handler), None) => handler(name, payload),
+            (None, callback) => {
+                error!("Task {} panicked: {}", name, panic_message(&*payload));
+                if let Some(callback) = callback {
+                    callback(payload);
+                }
+            }
+        }
+        if let Some(PanicPolicy::StopTask) = policy.as_deref() {
+            debug!("Stopping task after panic");
+            self.stop(&mut self.executions(), TerminationReason::Panicked);
+        }
+    }
+
+    fn record_timeout(&self, timeout: Duration) {
+        let (info, observer) = {
+            let mut executions = self.executions();
+            executions.stats.timeouts += 1;
+            let observer = executions.executor_hooks.as_ref().and_then(|hooks| hooks.observer());
+            (self.info_locked(&executions), observer)
+        };
+        warn!("Task {} ({}) execution timed out after {:?}", self.id, info.name.as_deref().unwrap_or(UNNAMED_TASK), timeout);
+        if let Some(observer) = observer {
+            observer.on_timeout(&info, timeout);
+        }
+    }
+
+    fn record_slow(&self, run: u64, took: Duration, threshold: Duration, interval: Option<Duration>) {
+        let mut executions = self.executions();
+        executions.stats.slow += 1;
+        warn!("Task {} ({}) run {} took {:?}, longer than {:?} (interval {:?})", self.id,
+              executions.name.as_deref().unwrap_or(UNNAMED_TASK), run, took, threshold, interval);
+    }
+
+    fn report_overrun(&self, run: u64, took: Duration, interval: Duration, delay: Duration) {
+        let (handler, name) = {
+            let executions = self.executions();
+            if let Some((level, target)) = self.log_target(&executions) {
+                log!(target: &target, level, "Task {} run {} overran its interval of {:?}, took {:?}",
+                     self.id, run, interval, took);
+            }
+            (executions.overrun_handler.clone(), executions.name.clone())
+        };
+        if let Some(handler) = handler {
+            handler(Overrun { task: self.id, name, run, took, interval, delay });
+        }
+    }
+
+    /// Keeps track of the consecutive failures of the task, stopping it if they're too many.
+    fn record_outcome(&self, failed: bool) {
+        let mut executions = self.executions();
+        if !failed {
+            executions.consecutive_failures = 0;
+            return;
+        }
+        executions.consecutive_failures += 1;
+        let failures = executions.consecutive_failures;
+        if executions.max_consecutive_failures.is_some_and(|max| failures >= max) {
+            warn!("Task failed {} times in a row, stopping it", failures);
+            self.stop(&mut executions, TerminationReason::TooManyFailures(failures));
+        }
+    }
+
+    /// Blocks until no execution of the task is in progress, or until `deadline` expires. Returns
+    /// the number of executions still in progress. When called from the function of the task, the
+    /// calling execution can't complete meanwhile, so only the other ones are waited for.
+    fn wait_idle(&self, mut executions: MutexGuard<'_, Executions>, deadline: Option<Instant>) -> usize {
+        let own = RUNNING_TASK.with(|task| task.get()) == Some(self.id);
+        while self.running() > own as usize {
+            executions = match deadline {
+                None => self.execution_done.wait(executions).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    self.execution_done.wait_timeout(executions, deadline - now).unwrap().0
+                }
+            };
+        }
+        self.running()
+    }
+
+    fn info(&self) -> TaskInfo {
+        self.info_locked(&self.executions())
+    }
+
+    /// Returns the level and the target of the lifecycle log lines of the task, or `None` if
+    /// they wouldn't be logged. See `CoreExecutor::set_task_log_level`.
+    fn log_target(&self, executions: &Executions) -> Option<(LogLevel, String)> {
+        let level = executions.executor_hooks.as_ref().map_or(Some(LogLevel::Debug), |hooks| hooks.task_log_level())?;
+        if level > log::max_log_level() {
+            return None;
+        }
+        Some((level, format!("{}::{}", TASK_LOG_TARGET, task_label(self.id, executions.name.as_deref()))))
+    }
+
+    fn info_locked(&self, executions: &Executions) -> TaskInfo {
+        TaskInfo {
+            id: self.id,
+            name: executions.name.clone(),
+            tags: executions.tags.iter().cloned().collect(),
+            kind: executions.kind,
+            interval: executions.interval,
+            runs: executions.runs,
+            paused: self.paused.load(Ordering::Relaxed),
+            stopped: self.stopped(),
+            busy_time: Duration::from_nanos(self.busy.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn notify_if_terminated(&self, executions: &mut Executions) {
+        if self.stopped() && self.running() == 0 {
+            for waiter in executions.waiters.drain(..) {
+                waiter.notify();
+            }
+        }
+    }
+}
+
+/// A started execution of a task, counted as in progress until dropped (even if the task
+/// panicked). On a thread pool, the execution starts when it's submitted to the pool.
+struct Execution {
+    state: Arc<TaskState>,
+    /// The index of the execution.
+    run: u64,
+    started: Option<Instant>,
+    panicked: bool,
+}
+
+/// The observers of an execution, looked up when it starts.
+struct RunHooks {
+    observer: Option<Arc<dyn ExecutorObserver>>,
+    metrics_sink: Option<(Arc<dyn MetricsSink>, String)>,
+    interval: Option<Duration>,
+    log_target: Option<(LogLevel, String)>,
+    slow_threshold: Option<Duration>,
+}
+
+impl Execution {
+    /// Runs the task function, recording the execution in the task stats. If the function
+    /// panics, the panic is handled according to the panic policy of the task, and `None` is
+    /// returned.
+    fn run<R, F>(mut self, tick: Tick, scheduled_fn: F) -> Option<R>
+        where F: FnOnce(&TaskContext) -> R
+    {
+        let (hooks, context) = self.start(tick);
+        let result = match running_task(self.state.id, || panic::catch_unwind(AssertUnwindSafe(|| scheduled_fn(&context)))) {
+            Ok(result) => {
+                self.state.record_outcome(context.failed());
+                Some(result)
+            }
+            Err(payload) => {
+                self.fail(&hooks, &context, payload);
+                None
+            }
+        };
+        self.finish(&hooks, &context, tick);
+        result
+    }
+
+    /// Like `run`, but the function returns a future, and the execution lasts until the future
+    /// resolves: the returned completion drives it. An error of the future, or a panic while
+    /// polling it, is handled like a panic of the function.
+    fn run_async<Fut, F>(mut self, tick: Tick, scheduled_fn: F) -> Completion
+        where F: FnOnce(&TaskContext) -> Fut,
+              Fut: Future<Item = (), Error = ()> + 'static
+    {
+        let (hooks, context) = self.start(tick);
+        let future = match running_task(self.state.id, || panic::catch_unwind(AssertUnwindSafe(|| scheduled_fn(&context)))) {
+            Ok(future) => future,
+            Err(payload) => {
+                self.fail(&hooks, &context, payload);
+                self.finish(&hooks, &context, tick);
+                return None;
+            }
+        };
+        Some(Box::new(AssertUnwindSafe(future).catch_unwind().then(move |result| {
+            match result {
+                Ok(Ok(())) => self.state.record_outcome(context.failed()),
+                Ok(Err(())) => self.fail(&hooks, &context, Box::new("the future of the execution failed")),
+                Err(payload) => self.fail(&hooks, &context, payload),
+            }
+            self.finish(&hooks, &context, tick);
+            Ok(())
+        })))
+    }
+
+    /// Records the start of the execution, and returns its hooks and its context.
+    fn start(&mut self, tick: Tick) -> (RunHooks, TaskContext) {
+        let now = Instant::now();
+        self.started = Some(now);
+        let hooks = {
+            let mut executions = self.state.executions();
+            executions.stats.last_start = Some(now);
+            let log_target = self.state.log_target(&executions);
+            if let Some((level, ref target)) = log_target {
+                log!(target: target, level, "Task {} run {} started, {:?} late", self.state.id, self.run,
+                     now.saturating_duration_since(tick.scheduled_at));
+            }
+            let hooks = executions.executor_hooks.as_ref();
+            RunHooks {
+                observer: hooks.and_then(|hooks| hooks.observer()),
+                metrics_sink: hooks.and_then(|hooks| hooks.metrics_sink())
+                    .map(|sink| (sink, task_label(self.state.id, executions.name.as_deref()))),
+                interval: executions.interval,
+                log_target,
+                slow_threshold: executions.slow_threshold
+                    .or_else(|| executions.interval.filter(|_| executions.kind == ScheduleKind::FixedRate)),
+            }
+        };
+        let context = TaskContext {
+            task_handle: TaskHandle { inner: self.state.clone() },
+            run: self.run,
+            scheduled_at: tick.scheduled_at,
+            started_at: now,
+            delay: tick.delay,
+            failed: Arc::new(AtomicBool::new(false)),
+        };
+        if let Some(ref observer) = hooks.observer {
+            observer.on_run_start(&context);
+        }
+        (hooks, context)
+    }
+
+    /// Handles a panic of the execution according to the panic policy of the task.
+    fn fail(&mut self, hooks: &RunHooks, context: &TaskContext, payload: Box<dyn Any + Send>) {
+        self.panicked = true;
+        if let Some(ref observer) = hooks.observer {
+            observer.on_panic(context, panic_message(&*payload));
+        }
+        self.state.handle_panic(payload);
+        self.state.record_outcome(true);
+    }
+
+    /// Reports the end of the execution to the hooks.
+    fn finish(&self, hooks: &RunHooks, context: &TaskContext, tick: Tick) {
+        let took = context.started_at.elapsed();
+        if let Some(ref observer) = hooks.observer {
+            observer.on_run_end(context, took);
+        }
+        if let Some((level, ref target)) = hooks.log_target {
+            let outcome = if self.panicked { "panicked" } else { "completed" };
+            log!(target: target, level, "Task {} run {} {} in {:?}", self.state.id, self.run, outcome, took);
+        }
+        if let Some((ref sink, ref label)) = hooks.metrics_sink {
+            let labels = [("task", label.as_str())];
+            sink.histogram_observe("scheduled_executor_task_duration_seconds", &labels, took.as_secs_f64());
+            sink.histogram_observe("scheduled_executor_task_lateness_seconds", &labels, context.lateness().as_secs_f64());
+        }
+        if let Some(threshold) = hooks.slow_threshold.filter(|&threshold| took > threshold) {
+            self.state.record_slow(self.run, took, threshold, hooks.interval);
+        }
+        if let Some(interval) = hooks.interval.filter(|&interval| took >= interval) {
+            self.state.report_overrun(self.run, took, interval, tick.delay);
+        }
+    }
+}
+
+/// What the scheduling loop knows about an execution, completed into a `TaskContext` when the
+/// execution starts.
+#[derive(Clone, Copy)]
+struct Tick {
+    scheduled_at: Instant,
+    delay: Duration,
+}
+
+/// Information about the current execution of a task, passed to the functions scheduled with
+/// the `_ctx` methods.
+#[derive(Clone)]
+pub struct TaskContext {
+    task_handle: TaskHandle,
+    run: u64,
+    scheduled_at: Instant,
+    started_at: Instant,
+    delay: Duration,
+    /// Set when the function returned an error, see `record_error`.
+    failed: Arc<AtomicBool>,
+}
+
+impl fmt::Debug for TaskContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskContext")
+            .field("run", &self.run)
+            .field("scheduled_at", &self.scheduled_at)
+            .field("started_at", &self.started_at)
+            .field("delay", &self.delay)
+            .finish()
+    }
+}
+
+impl TaskContext {
+    /// Returns the handle of the running task. The function can use it to stop its own task, or
+    /// to check, during a long execution, whether the task was stopped in the meantime and bail
+    /// out early.
+    pub fn task_handle(&self) -> &TaskHandle {
+        &self.task_handle
+    }
+
+    /// Returns the index of the execution, starting from 0.
+    pub fn run(&self) -> u64 {
+        self.run
+    }
+
+    /// Returns when the execution was scheduled to start. For runs requested through
+    /// `TaskHandle::trigger_now`, this is when the trigger was processed.
+    pub fn scheduled_at(&self) -> Instant {
+        self.scheduled_at
+    }
+
+    /// Returns when the execution actually started.
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
+    /// Returns how late the execution started, compared to its scheduled time.
+    pub fn lateness(&self) -> Duration {
+        self.started_at.saturating_duration_since(self.scheduled_at)
+    }
+
+    /// Returns the delay accumulated by the schedule, as returned by `Schedule::delay`: for a
+    /// fixed rate schedule, that's the delay it still has to recover.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Logs an error returned by the function, and counts the execution as failed, as if it
+    /// panicked, towards `TaskHandle::set_max_consecutive_failures`.
+    fn record_error(&self, error: &dyn fmt::Display) {
+        warn!("Task {} failed: {}", self.task_handle.name().as_deref().unwrap_or(UNNAMED_TASK), error);
+        self.mark_failed();
+    }
+
+    fn mark_failed(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    fn failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Execution {
+    fn drop(&mut self) {
+        let now = Instant::now();
+        if let Some(started) = self.started {
+            self.state.durations.record(now - started);
+            let nanos = (now - started).as_nanos().min(u128::from(u64::MAX)) as u64;
+            self.state.busy.fetch_add(nanos, Ordering::Relaxed);
+        }
+        let mut executions = self.state.executions();
+        if let Some(started) = self.started {
+            if executions.recent_executions.len() == BUSY_HISTORY {
+                executions.recent_executions.pop_front();
+            }
+            executions.recent_executions.push_back((started, now));
+            let stats = &mut executions.stats;
+            stats.executions += 1;
+            if self.panicked {
+                stats.panics += 1;
+            }
+            stats.last_end = Some(now);
+            stats.last_duration = Some(now - started);
+            if let Some(ref hooks) = executions.executor_hooks {
+                hooks.executions.fetch_add(1, Ordering::Relaxed);
+                if self.panicked {
+                    hooks.panics.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        self.state.running.fetch_sub(1, Ordering::Release);
+        for waiter in executions.slot_waiters.drain(..) {
+            waiter.notify();
+        }
+        self.state.execution_done.notify_all();
+        self.state.notify_if_terminated(&mut executions);
+    }
+}
+
+impl fmt::Debug for TaskHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let info = self.inner.info();
+        f.debug_struct("TaskHandle")
+            .field("id", &info.id)
+            .field("name", &info.name)
+            .field("kind", &info.kind)
+            .field("interval", &info.interval)
+            .field("runs", &info.runs)
+            .field("paused", &info.paused)
+            .field("stopped", &info.stopped)
+            .finish()
+    }
+}
+
+/// Describes the task in one line, for log messages, like `task 3 (flush): 12 runs, every 1s,
+/// running`.
+impl fmt::Display for TaskHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let info = self.inner.info();
+        write!(f, "task {}", info.id)?;
+        if let Some(name) = info.name {
+            write!(f, " ({})", name)?;
+        }
+        write!(f, ": {} runs, ", info.runs)?;
+        if let Some(interval) = info.interval {
+            write!(f, "every {:?}, ", interval)?;
+        }
+        let state = if info.stopped {
+            "stopped"
+        } else if info.paused {
+            "paused"
+        } else {
+            "running"
+        };
+        f.write_str(state)
+    }
+}
+
+impl TaskHandle {
+    fn named(name: &str) -> TaskHandle {
+        let task_handle = TaskHandle::new();
+        task_handle.set_name(name);
+        task_handle
+    }
+
+    fn new() -> TaskHandle {
+        let state = TaskState {
+            id: TaskId::next(),
+            should_stop: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            running: AtomicUsize::new(0),
+            executions: Mutex::new(Executions {
+                waiters: Vec::new(),
+                slot_waiters: Vec::new(),
+                cancellations: Vec::new(),
+                timers: Vec::new(),
+                stats: TaskStats::default(),
+                recent_executions: VecDeque::new(),
+                next_run: None,
+                trigger: None,
+                interval: None,
+                interval_changed: false,
+                postponement: Duration::from_secs(0),
+                runs: 0,
+                max_runs: None,
+                overlap_policy: None,
+                queue_at_limit: false,
+                affinity: None,
+                execution_timeout: None,
+                slow_threshold: None,
+                clock_source: ClockSource::Monotonic,
+                overrun_handler: None,
+                panic_policy: None,
+                executor_hooks: None,
+                name: None,
+                kind: ScheduleKind::Custom,
+                tags: BTreeSet::new(),
+                children: Vec::new(),
+                parent: None,
+                consecutive_failures: 0,
+                max_consecutive_failures: None,
+                termination_reason: None,
+                error: None,
+                stopped_at: None,
+                state_receiver: None,
+            }),
+            execution_done: Condvar::new(),
+            durations: DurationRecorder::default(),
+            busy: AtomicU64::new(0),
+            created: Instant::now(),
+        };
+        TaskHandle { inner: Arc::new(state) }
+    }
+
+    fn with_max_runs(max_runs: u64) -> TaskHandle {
+        let task_handle = TaskHandle::new();
+        {
+            let mut executions = task_handle.inner.executions();
+            executions.max_runs = Some(max_runs);
+            if max_runs == 0 {
+                task_handle.inner.stop(&mut executions, TerminationReason::Completed);
+            }
+        }
+        task_handle
+    }
+
+    /// Starts a new execution of the task, unless it's stopped.
+    fn begin_execution(&self) -> Option<Execution> {
+        let mut executions = self.inner.executions();
+        if self.stopped() {
+            return None;
+        }
+        let run = executions.runs;
+        self.inner.running.fetch_add(1, Ordering::Release);
+        executions.runs += 1;
+        if Some(executions.runs) == executions.max_runs {
+            debug!("Maximum number of runs reached, stopping task");
+            self.inner.stop(&mut executions, TerminationReason::Completed);
+        }
+        Some(Execution { state: self.inner.clone(), run, started: None, panicked: false })
+    }
+
+    /// Returns a future resolving once the task is stopped, used to cancel a pending timer, so
+    /// that the function and everything it captured are released right away rather than when
+    /// the timer would have fired.
+    fn cancellation(&self) -> Receiver<()> {
+        let (tx, rx) = channel();
+        let mut executions = self.inner.executions();
+        if self.stopped() {
+            let _ = tx.send(());
+        } else {
+            // The timers that fired dropped their receiver
+            executions.cancellations.retain(|cancellation| !cancellation.is_canceled());
+            executions.cancellations.push(tx);
+        }
+        rx
+    }
+
+    fn set_skipped_ticks(&self, skipped_ticks: u64) {
+        self.inner.executions().stats.skipped_ticks = skipped_ticks;
+    }
+
+    fn set_next_run(&self, next_run: Option<Instant>) {
+        let mut executions = self.inner.executions();
+        if !self.stopped() {
+            let postponement = executions.postponement;
+            executions.next_run = next_run.map(|next_run| deadline_after(next_run, postponement));
+        }
+    }
+
+    /// Returns when the task will run next, or `None` if the task is stopped or an execution is
+    /// currently in progress. For schedules depending on the previous execution (like fixed rate
+    /// ones), this already accounts for the time that execution took.
+    pub fn next_run_at(&self) -> Option<Instant> {
+        if self.stopped() || self.inner.running() > 0 {
+            return None;
+        }
+        self.inner.executions().next_run
+    }
+
+    /// Returns how long until the task runs next, with the same semantics as `next_run_at`.
+    pub fn next_run_in(&self) -> Option<Duration> {
+        self.next_run_at().map(|next_run| next_run.saturating_duration_since(Instant::now()))
+    }
+
+    /// Runs the task as soon as possible on its executor, independently of the pending timer,
+    /// which is left untouched: the regular schedule continues as if the triggered run never
+    /// happened. Stopped, paused and one-shot tasks ignore triggers.
+    pub fn trigger_now(&self) {
+        self.trigger(false);
+    }
+
+    /// Like `trigger_now`, but the pending timer is cancelled and the schedule restarts from the
+    /// triggered run, as if that run was a scheduled one. For example, a fixed interval task will
+    /// next run one interval after the triggered run completes.
+    pub fn trigger_now_and_reschedule(&self) {
+        self.trigger(true);
+    }
+
+    fn trigger(&self, reschedule: bool) {
+        let trigger = self.inner.executions().trigger.clone();
+        if let Some(trigger) = trigger {
+            trigger(self, reschedule);
+        }
+    }
+
+    /// Changes the interval of the task. The timer currently pending is not affected, but the
+    /// new interval will be used to compute the following waits. This has no effect on tasks
+    /// whose schedule doesn't have an interval (like cron or one-shot ones).
+    pub fn set_interval(&self, interval: Duration) {
+        let mut executions = self.inner.executions();
+        if executions.interval.is_some() {
+            executions.interval = Some(interval);
+            executions.interval_changed = true;
+        }
+    }
+
+    /// Returns the interval of the task, or `None` if its schedule doesn't have one.
+    pub fn interval(&self) -> Option<Duration> {
+        self.inner.executions().interval
+    }
+
+    /// Returns the new interval of the task if it was changed since the last call.
+    fn take_interval_change(&self) -> Option<Duration> {
+        let mut executions = self.inner.executions();
+        if executions.interval_changed {
+            executions.interval_changed = false;
+            executions.interval
+        } else {
+            None
+        }
+    }
+
+    /// Postpones the next execution of the task by `extra`, without affecting the following
+    /// ones (unless the schedule depends on when the previous execution ran). Multiple calls
+    /// before the next execution accumulate.
+    pub fn postpone(&self, extra: Duration) {
+        let mut executions = self.inner.executions();
+        if !self.stopped() {
+            executions.postponement = executions.postponement.saturating_add(extra);
+            executions.next_run = executions.next_run.map(|next_run| deadline_after(next_run, extra));
+        }
+    }
+
+    /// Returns the pending postponement, if any, and clears it.
+    fn take_postponement(&self) -> Option<Duration> {
+        let mut executions = self.inner.executions();
+        let postponement = mem::replace(&mut executions.postponement, Duration::from_secs(0));
+        if postponement > Duration::from_secs(0) {
+            Some(postponement)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the id of the task.
+    pub fn id(&self) -> TaskId {
+        self.inner.id
+    }
+
+    /// Sets the name of the task, which appears in the logs, is passed to the panic handler of
+    /// its executor, and can be used to stop it through the executor's `stop_task`.
+    pub fn set_name(&self, name: &str) {
+        self.inner.executions().name = Some(name.to_owned());
+    }
+
+    /// Returns the name of the task, if it was given one.
+    pub fn name(&self) -> Option<String> {
+        self.inner.executions().name.clone()
+    }
+
+    /// Adds a tag to the task, which can be used to act on all the tasks with the same tag
+    /// through the executor's `_tagged` methods.
+    pub fn add_tag(&self, tag: &str) {
+        self.inner.executions().tags.insert(tag.to_owned());
+    }
+
+    /// Removes a tag from the task.
+    pub fn remove_tag(&self, tag: &str) {
+        self.inner.executions().tags.remove(tag);
+    }
+
+    /// Returns the tags of the task, sorted.
+    pub fn tags(&self) -> Vec<String> {
+        self.inner.executions().tags.iter().cloned().collect()
+    }
+
+    /// Makes `child` a child of this task: stopping this task, for whatever reason, also stops
+    /// `child` and all of its own children, while stopping `child` leaves this task running. If
+    /// this task is already stopped, `child` is stopped right away. This is useful for tasks
+    /// scheduling follow-up work from their function, through `TaskContext::task_handle`. A
+    /// task can only have one parent, and a task can't be made a child of one of its
+    /// descendants: in both cases, the call has no effect and returns false.
+    pub fn add_child(&self, child: &TaskHandle) -> bool {
+        if self.inner.descends_from(&child.inner) {
+            return false;
+        }
+        {
+            let mut child_executions = child.inner.executions();
+            if child_executions.parent.is_some() {
+                return false;
+            }
+            child_executions.parent = Some(Arc::downgrade(&self.inner));
+        }
+        let mut executions = self.inner.executions();
+        if self.stopped() {
+            drop(executions);
+            child.finish(TerminationReason::ParentStopped);
+        } else {
+            executions.children.retain(|child| child.upgrade().is_some_and(|child| !child.stopped()));
+            executions.children.push(Arc::downgrade(&child.inner));
+        }
+        true
+    }
+
+    /// Sets the panic policy of the task, overriding the one of its executor.
+    pub fn set_panic_policy(&self, policy: PanicPolicy) {
+        self.inner.executions().panic_policy = Some(Arc::new(policy));
+    }
+
+    /// Sets what happens when an execution of the task on a `ThreadPoolExecutor` is due while the
+    /// previous one is still running. The policy applies from the next scheduled execution.
+    pub fn set_overlap_policy(&self, policy: OverlapPolicy) {
+        self.inner.executions().overlap_policy = Some(policy);
+    }
+
+    /// Returns the overlap policy of the task: the one set through `set_overlap_policy`, or
+    /// `OverlapPolicy::Allow`.
+    pub fn overlap_policy(&self) -> OverlapPolicy {
+        self.inner.executions().overlap_policy.unwrap_or(OverlapPolicy::Allow)
+    }
+
+    /// Binds the executions of the task on a `ThreadPoolExecutor` to a single thread, chosen from
+    /// `key`: all the tasks with the same key run on the same thread, which is useful for the
+    /// tasks relying on thread-local state. The first time a task with an affinity runs, the
+    /// executor creates a dedicated thread for each thread of its pool, which the tasks with an
+    /// affinity share; the executions that are already submitted are not affected.
+    pub fn set_affinity(&self, key: u64) {
+        self.inner.executions().affinity = Some(key);
+    }
+
+    /// With `OverlapPolicy::Concurrent`, sets whether the executions beyond the limit are queued
+    /// instead of skipped.
+    pub fn set_queue_at_limit(&self, queue: bool) {
+        self.inner.executions().queue_at_limit = queue;
+    }
+
+    /// Counts a skipped execution, if `limit` executions are already running.
+    fn skip_at_limit(&self, limit: usize) -> bool {
+        if self.inner.running() < limit {
+            return false;
+        }
+        self.inner.executions().stats.skipped += 1;
+        true
+    }
+
+    /// Sets how long an execution of the task on a `ThreadPoolExecutor` can take. An execution
+    /// that doesn't complete in time is counted in `TaskStats::timeouts` and reported to the
+    /// executor observer; it can't be interrupted though, so it keeps running in the background,
+    /// and the schedule of the task goes on regardless. Executions on a `CoreExecutor` are not
+    /// affected.
+    pub fn set_execution_timeout(&self, timeout: Duration) {
+        self.inner.executions().execution_timeout = Some(timeout);
+    }
+
+    /// Logs a warning, and counts the execution in `TaskStats::slow`, every time an execution of
+    /// the task takes longer than `threshold`. Fixed rate tasks warn when an execution takes
+    /// longer than their interval by default, the other tasks don't warn unless this is set.
+    pub fn warn_if_slower_than(&self, threshold: Duration) {
+        self.inner.executions().slow_threshold = Some(threshold);
+    }
+
+    /// Sets the clock the waits of the task are measured with, starting from the next one.
+    pub fn set_clock_source(&self, clock_source: ClockSource) {
+        self.inner.executions().clock_source = clock_source;
+    }
+
+    /// Returns the clock the waits of the task are measured with.
+    pub fn clock_source(&self) -> ClockSource {
+        self.inner.executions().clock_source
+    }
+
+    /// Sets the handler invoked after every execution that takes at least as long as the interval
+    /// of the task, for the schedules that have one. The handler is invoked, with no lock held,
+    /// on the thread that ran the execution: the executor thread for a `CoreExecutor`, where it
+    /// delays the other tasks, so it should return quickly.
+    pub fn on_overrun<F>(&self, handler: F)
+        where F: Fn(Overrun) + Send + Sync + 'static
+    {
+        self.inner.executions().overrun_handler = Some(Arc::new(handler));
+    }
+
+    /// Stops the task once `max` executions in a row have failed; a successful execution resets
+    /// the count.
+    pub fn set_max_consecutive_failures(&self, max: usize) {
+        self.inner.executions().max_consecutive_failures = Some(max);
+    }
+
+    /// Returns the number of executions in a row that failed, up to the last one.
+    pub fn failure_count(&self) -> usize {
+        self.inner.executions().consecutive_failures
+    }
+
+    /// Returns why the task was stopped, or `None` if it's still running.
+    pub fn terminated_reason(&self) -> Option<TerminationReason> {
+        self.inner.executions().termination_reason
+    }
+
+    /// Returns the error that terminated the task, for example with
+    /// `TerminationReason::SinkFailed`.
+    pub fn error(&self) -> Option<String> {
+        self.inner.executions().error.clone()
+    }
+
+    /// Returns the execution statistics of the task.
+    pub fn stats(&self) -> TaskStats {
+        self.inner.executions().stats.clone()
+    }
+
+    /// Returns the distribution of the durations of the completed executions of the task,
+    /// including the ones that panicked.
+    pub fn duration_histogram(&self) -> Histogram {
+        self.inner.durations.snapshot()
+    }
+
+    /// Returns the total time spent running the task, summing the durations of its completed
+    /// executions. On a thread pool, that's the time spent on the pool threads, not including
+    /// the time the executions were queued.
+    pub fn total_busy_time(&self) -> Duration {
+        Duration::from_nanos(self.inner.busy.load(Ordering::Relaxed))
+    }
+
+    /// Returns the fraction of the last `window` the task spent running, or of the time since it
+    /// was scheduled if that's shorter. It's computed from the last `BUSY_HISTORY` completed
+    /// executions, and can exceed 1 on a thread pool when the executions overlap.
+    pub fn busy_fraction(&self, window: Duration) -> f64 {
+        let now = Instant::now();
+        let start = now.checked_sub(window).map_or(self.inner.created, |start| start.max(self.inner.created));
+        let elapsed = now - start;
+        if elapsed == Duration::from_secs(0) {
+            return 0.0;
+        }
+        let busy = self.inner.executions().recent_executions.iter()
+            .filter(|&&(_, end)| end > start)
+            .map(|&(started, end)| end - started.max(start))
+            .sum::<Duration>();
+        busy.as_secs_f64() / elapsed.as_secs_f64()
+    }
+
+    /// Returns a future that resolves once the task is stopped and its last execution has
+    /// returned, regardless of whether the task was stopped through a handle, ran out of
+    /// scheduled executions, or its executor was dropped.
+    pub fn on_stop(&self) -> TaskCompletion {
+        TaskCompletion { inner: self.inner.clone() }
+    }
+
+    /// Like `on_stop`, but returns a `std::future::Future`, which can be awaited.
+    #[cfg(feature = "compat")]
+    pub fn on_stop_compat(&self) -> impl StdFuture<Output = ()> {
+        self.on_stop().compat().map(|_| ())
+    }
+
+    /// Returns a future resolving to the final state of a task scheduled with one of the
+    /// `_with_state` methods, once the task is stopped and its last execution has returned. The
+    /// future fails if the executor is dropped without releasing the task function. Returns
+    /// `None` if the task wasn't scheduled with a state of type `T`, or if the state was already
+    /// claimed.
+    pub fn into_state_receiver<T: Send + 'static>(self) -> Option<StateReceiver<T>> {
+        let mut executions = self.inner.executions();
+        match executions.state_receiver.take() {
+            Some((type_id, receiver)) if type_id == TypeId::of::<T>() =>
+                Some(StateReceiver { receiver, state: PhantomData }),
+            other => {
+                executions.state_receiver = other;
+                None
+            }
+        }
+    }
+
+    /// Pauses the correspondent task. A paused task stays scheduled, but every execution will be
+    /// skipped until the task is resumed. Skipped executions are not considered late: after
+    /// resuming, a fixed rate task will continue with its normal schedule, without trying to
+    /// catch up.
+    pub fn pause(&self) {
+        self.inner.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes a paused task.
+    pub fn resume(&self) {
+        self.inner.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns true if the task is paused.
+    pub fn paused(&self) -> bool {
+        self.inner.paused.load(Ordering::Relaxed)
+    }
+
+    /// Stops the correspondent task. Not that a running task won't be interrupted, but
+    /// future tasks executions will be prevented. Returns true if this call stopped the task,
+    /// or false if it was already stopped: when a handle is shared, exactly one of the callers
+    /// gets true.
+    pub fn stop(&self) -> bool {
+        self.finish(TerminationReason::Stopped)
+    }
+
+    fn finish(&self, reason: TerminationReason) -> bool {
+        self.inner.stop(&mut self.inner.executions(), reason)
+    }
+
+    /// Stops the task because of the given error, unless it was already stopped.
+    fn fail(&self, reason: TerminationReason, error: String) {
+        let mut executions = self.inner.executions();
+        if self.inner.stop(&mut executions, reason) {
+            error!("Task {} failed: {}", executions.name.as_deref().unwrap_or(UNNAMED_TASK), error);
+            executions.error = Some(error);
+        }
+    }
+
+    /// Returns when the task was stopped, or `None` if it's still running.
+    pub fn stopped_at(&self) -> Option<Instant> {
+        self.inner.executions().stopped_at
+    }
+
+    /// Returns true if the task is stopped.
+    pub fn stopped(&self) -> bool {
+        self.inner.stopped()
+    }
+
+    /// Converts the handle into a `ScopedTaskHandle`, which stops the task when dropped. Other
+    /// `TaskHandle`s of the same task are not affected, and don't keep the task running.
+    pub fn into_scoped(self) -> ScopedTaskHandle {
+        ScopedTaskHandle { inner: Arc::new(TaskScope(self)) }
+    }
+
+    /// Stops the correspondent task, and blocks until the executions currently in progress (if
+    /// any) are completed, or until `timeout` expires. Returns true if all the executions
+    /// completed. Once this method returns true, the task is guaranteed not to run anymore. When
+    /// called from the function of the task itself, the other executions are waited for, but
+    /// false is returned, as the calling execution is still in progress.
+    pub fn stop_and_wait(&self, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut executions = self.inner.executions();
+        self.inner.stop(&mut executions, TerminationReason::Stopped);
+        self.inner.wait_idle(executions, deadline) == 0
+    }
+}
+
+/// A handle that stops its task when dropped, obtained through `TaskHandle::into_scoped`. It can
+/// be cloned, and the task is stopped when the last clone is dropped. It dereferences to the
+/// underlying `TaskHandle`.
+#[derive(Clone)]
+pub struct ScopedTaskHandle {
+    inner: Arc<TaskScope>,
+}
+
+struct TaskScope(TaskHandle);
+
+impl Drop for TaskScope {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+impl Deref for ScopedTaskHandle {
+    type Target = TaskHandle;
+
+    fn deref(&self) -> &TaskHandle {
+        &self.inner.0
+    }
+}
+
+/// A set of tasks that can be controlled together, for example all the tasks of a subsystem.
+/// Tasks can be added to the set through `add`, or scheduled directly in it through the
+/// executors' `_in` methods. Dropping the set doesn't stop its tasks, unless `stop_on_drop` is
+/// set.
+#[derive(Default)]
+pub struct TaskSet {
+    tasks: Mutex<Vec<TaskHandle>>,
+    stop_on_drop: AtomicBool,
+}
+
+impl TaskSet {
+    /// Creates a new empty set.
+    pub fn new() -> TaskSet {
+        TaskSet::default()
+    }
+
+    /// Adds a task to the set.
+    pub fn add(&self, task_handle: TaskHandle) {
+        let mut tasks = self.tasks.lock().unwrap();
+        // Stopped tasks are kept until their last execution completes, for `stop_and_wait`
+        tasks.retain(|task| !task.stopped() || task.inner.running() > 0);
+        tasks.push(task_handle);
+    }
+
+    /// Sets whether the tasks should be stopped when the set is dropped.
+    pub fn stop_on_drop(&self, stop: bool) {
+        self.stop_on_drop.store(stop, Ordering::Relaxed);
+    }
+
+    /// Stops all the tasks of the set.
+    pub fn stop(&self) {
+        for task in self.tasks.lock().unwrap().iter() {
+            task.stop();
+        }
+    }
+
+    /// Returns true if all the tasks of the set are stopped.
+    pub fn stopped(&self) -> bool {
+        self.tasks.lock().unwrap().iter().all(|task| task.stopped())
+    }
+
+    /// Stops all the tasks of the set, and blocks until their executions in progress are
+    /// completed, or until `timeout` expires, as `TaskHandle::stop_and_wait`. Returns true if all
+    /// the executions completed.
+    pub fn stop_and_wait(&self, timeout: Option<Duration>) -> bool {
+        let tasks = self.tasks.lock().unwrap().clone();
+        self.stop();
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        tasks.iter().all(|task| task.inner.wait_idle(task.inner.executions(), deadline) == 0)
+    }
+}
+
+impl Drop for TaskSet {
+    fn drop(&mut self) {
+        if *self.stop_on_drop.get_mut() {
+            self.stop();
+        }
+    }
+}
+
+/// Returned by the functions scheduled with the `_controlled` methods, to tell whether their task
+/// should keep running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// The task will run again according to its schedule.
+    Continue,
+    /// The task is stopped, as if `TaskHandle::stop` was called.
+    Stop,
+}
+
+/// What happens to the remaining stages of a `ScheduledChain` when a stage panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChainPolicy {
+    /// The remaining stages are skipped until the next tick. This is the default.
+    #[default]
+    AbortOnFailure,
+    /// The remaining stages run anyway.
+    ContinueOnFailure,
+}
+
+/// A stage of a `ScheduledChain`, receiving the `Handle` or the `Remote` of the executor.
+type ChainStage<A> = dyn FnMut(&A) + Send;
+
+/// A sequence of functions run one after the other on every tick of a task, built with `then`
+/// and scheduled through the executors' `_chain` methods. Every stage is recorded as an
+/// execution of the task, and its panics are handled according to the panic policy of the task.
+pub struct ScheduledChain<A> {
+    stages: Vec<Box<ChainStage<A>>>,
+    policy: ChainPolicy,
+}
+
+impl<A> ScheduledChain<A> {
+    /// Creates an empty chain.
+    pub fn new() -> ScheduledChain<A> {
+        ScheduledChain { stages: Vec::new(), policy: ChainPolicy::default() }
+    }
+
+    /// Adds a stage, run once the previous ones completed.
+    pub fn then<F>(mut self, stage: F) -> ScheduledChain<A>
+        where F: FnMut(&A) + Send + 'static
+    {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Sets what happens when a stage panics.
+    pub fn policy(mut self, policy: ChainPolicy) -> ScheduledChain<A> {
+        self.policy = policy;
+        self
+    }
+}
+
+impl<A> Default for ScheduledChain<A> {
+    fn default() -> ScheduledChain<A> {
+        ScheduledChain::new()
+    }
+}
+
+/// The stages of a chain scheduled on a `ThreadPoolExecutor`, each submitted to the pool once
+/// the previous one completed.
+struct PoolChain {
+    stages: Vec<Mutex<Box<ChainStage<Remote>>>>,
+    policy: ChainPolicy,
+    spawner: PoolSpawner,
+    task_handle: TaskHandle,
+}
+
+impl PoolChain {
+    /// Runs the stages from `index` on, resolving once the last one completed.
+    fn run_from(chain: Arc<PoolChain>, index: usize, handle: Handle, tick: Tick) -> Box<dyn Future<Item = (), Error = ()>> {
+        let execution = match chain.stages.get(index).and_then(|_| chain.task_handle.begin_execution()) {
+            Some(execution) => execution,
+            None => return Box::new(future::ok(())),
+        };
+        let failed = Arc::new(AtomicBool::new(false));
+        let job_failed = failed.clone();
+        let job_chain = chain.clone();
+        let remote = handle.remote().clone();
+        let job = move || {
+            // A panic in a previous execution doesn't invalidate the stage
+            let mut stage = job_chain.stages[index].lock().unwrap_or_else(|e| e.into_inner());
+            if execution.run(tick, |_| (*stage)(&remote)).is_none() {
+                job_failed.store(true, Ordering::Relaxed);
+            }
+        };
+        let submitted = chain.spawner.submit(&handle, &chain.task_handle, job);
+        Box::new(submitted.and_then(move |()| {
+            if failed.load(Ordering::Relaxed) && chain.policy == ChainPolicy::AbortOnFailure {
+                debug!("Stage {} failed, skipping the rest of the chain", index);
+                return Either::A(future::ok(()));
+            }
+            Either::B(PoolChain::run_from(chain, index + 1, handle, tick))
+        }))
+    }
+}
+
+/// Configures a recurring task step by step, obtained through the executors' `task` methods and
+/// scheduled with `spawn`. The options only available on a thread pool are set through the
+/// methods of `TaskBuilder<ThreadPoolExecutor>`.
+pub struct TaskBuilder<'a, E: 'a> {
+    executor: &'a E,
+    initial: Duration,
+    interval: Option<Duration>,
+    fixed_interval: bool,
+    missed_ticks: MissedTickPolicy,
+    max_catchup: Option<Duration>,
+    jitter: Option<Duration>,
+    name: Option<String>,
+    tags: Vec<String>,
+    max_runs: Option<u64>,
+    max_consecutive_failures: Option<usize>,
+    panic_policy: Option<PanicPolicy>,
+    clock_source: Option<ClockSource>,
+    overlap_policy: Option<OverlapPolicy>,
+    execution_timeout: Option<Duration>,
+    slow_threshold: Option<Duration>,
+    affinity: Option<u64>,
+    /// The first duration that couldn't be converted, reported by `spawn`.
+    invalid: Option<ExecutorError>,
+}
+
+impl<'a, E> TaskBuilder<'a, E> {
+    fn new(executor: &'a E) -> TaskBuilder<'a, E> {
+        TaskBuilder {
+            executor,
+            initial: Duration::from_secs(0),
+            interval: None,
+            fixed_interval: false,
+            missed_ticks: MissedTickPolicy::Burst,
+            max_catchup: None,
+            jitter: None,
+            name: None,
+            tags: Vec::new(),
+            max_runs: None,
+            max_consecutive_failures: None,
+            panic_policy: None,
+            clock_source: None,
+            overlap_policy: None,
+            execution_timeout: None,
+            slow_threshold: None,
+            affinity: None,
+            invalid: None,
+        }
+    }
+
+    /// Converts a duration option, recording the error if it's out of range.
+    fn convert<I: IntoInterval>(&mut self, option: &str, value: I) -> Duration {
+        value.into_interval().unwrap_or_else(|e| {
+            if self.invalid.is_none() {
+                self.invalid = Some(ExecutorError::InvalidSchedule(format!("{}: {}", option, e)));
+            }
+            Duration::from_secs(0)
+        })
+    }
+
+    /// Sets the delay before the first execution, zero by default. Like the other durations of
+    /// the schedule, it can be given as any `IntoInterval`; an out of range value makes `spawn`
+    /// fail.
+    pub fn initial<I: IntoInterval>(mut self, initial: I) -> TaskBuilder<'a, E> {
+        self.initial = self.convert("initial", initial);
+        self
+    }
+
+    /// Runs the task at fixed rate, every `interval`.
+    pub fn every<I: IntoInterval>(mut self, interval: I) -> TaskBuilder<'a, E> {
+        self.interval = Some(self.convert("interval", interval));
+        self.fixed_interval = false;
+        self
+    }
+
+    /// Runs the task with `interval` between the end of an execution and the start of the next
+    /// one, instead of at fixed rate.
+    pub fn fixed_interval<I: IntoInterval>(mut self, interval: I) -> TaskBuilder<'a, E> {
+        self.interval = Some(self.convert("interval", interval));
+        self.fixed_interval = true;
+        self
+    }
+
+    /// Like `every`, but the interval is a string like "5m" or "1h30m", see the `duration`
+    /// module.
+    pub fn every_str(self, interval: &str) -> Result<TaskBuilder<'a, E>, ParseError> {
+        Ok(self.every(parse_duration(interval)?))
+    }
+
+    /// Sets how the ticks missed by a fixed rate task are handled, `MissedTickPolicy::Burst` by
+    /// default.
+    pub fn missed_ticks(mut self, policy: MissedTickPolicy) -> TaskBuilder<'a, E> {
+        self.missed_ticks = policy;
+        self
+    }
+
+    /// Discards the delay of a fixed rate task once it exceeds `max_catchup`, see
+    /// `FixedRate::max_catchup`.
+    pub fn max_catchup(mut self, max_catchup: Duration) -> TaskBuilder<'a, E> {
+        self.max_catchup = Some(max_catchup);
+        self
+    }
+
+    /// Moves every execution by a random time up to `jitter`, see `Jittered`.
+    pub fn jitter<I: IntoInterval>(mut self, jitter: I) -> TaskBuilder<'a, E> {
+        self.jitter = Some(self.convert("jitter", jitter));
+        self
+    }
+
+    /// Gives the task a name, see `TaskHandle::set_name`.
+    pub fn named(mut self, name: &str) -> TaskBuilder<'a, E> {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Adds a tag to the task, see `TaskHandle::add_tag`.
+    pub fn tag(mut self, tag: &str) -> TaskBuilder<'a, E> {
+        self.tags.push(tag.to_owned());
+        self
+    }
+
+    /// Stops the task after `max_runs` executions.
+    pub fn max_runs(mut self, max_runs: u64) -> TaskBuilder<'a, E> {
+        self.max_runs = Some(max_runs);
+        self
+    }
+
+    /// Stops the task after `max` failed executions in a row, see
+    /// `TaskHandle::set_max_consecutive_failures`.
+    pub fn max_consecutive_failures(mut self, max: usize) -> TaskBuilder<'a, E> {
+        self.max_consecutive_failures = Some(max);
+        self
+    }
+
+    /// Sets the panic policy of the task, overriding the one of the executor.
+    pub fn on_panic(mut self, policy: PanicPolicy) -> TaskBuilder<'a, E> {
+        self.panic_policy = Some(policy);
+        self
+    }
+
+    /// Sets the clock the waits of the task are measured with, see `ClockSource`.
+    pub fn clock_source(mut self, clock_source: ClockSource) -> TaskBuilder<'a, E> {
+        self.clock_source = Some(clock_source);
+        self
+    }
+
+    /// Warns about the executions slower than `threshold`, see `TaskHandle::warn_if_slower_than`.
+    pub fn warn_if_slower_than(mut self, threshold: Duration) -> TaskBuilder<'a, E> {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// The schedule of a fixed rate task with the configured options.
+    fn fixed_rate(&self, interval: Duration) -> FixedRate {
+        let schedule = FixedRate::with_policy(self.initial, interval, self.missed_ticks);
+        match self.max_catchup {
+            Some(max_catchup) => schedule.max_catchup(max_catchup),
+            None => schedule,
+        }
+    }
+
+    /// Checks the options, and creates the handle of the task with them.
+    fn task_handle(&mut self) -> Result<(TaskHandle, Duration), ExecutorError> {
+        if let Some(e) = self.invalid.take() {
+            return Err(e);
+        }
+        let interval = self.interval.ok_or_else(|| ExecutorError::InvalidSchedule("no interval was given".to_owned()))?;
+        if interval == Duration::from_secs(0) && self.jitter.is_none_or(|jitter| jitter == Duration::from_secs(0)) {
+            return Err(ExecutorError::InvalidSchedule("the interval is zero".to_owned()));
+        }
+        let task_handle = match self.max_runs {
+            Some(max_runs) => TaskHandle::with_max_runs(max_runs),
+            None => TaskHandle::new(),
+        };
+        if let Some(ref name) = self.name {
+            task_handle.set_name(name);
+        }
+        for tag in &self.tags {
+            task_handle.add_tag(tag);
+        }
+        if let Some(max) = self.max_consecutive_failures {
+            task_handle.set_max_consecutive_failures(max);
+        }
+        if let Some(policy) = self.panic_policy.take() {
+            task_handle.set_panic_policy(policy);
+        }
+        if let Some(clock_source) = self.clock_source {
+            task_handle.set_clock_source(clock_source);
+        }
+        if let Some(policy) = self.overlap_policy {
+            task_handle.set_overlap_policy(policy);
+        }
+        if let Some(timeout) = self.execution_timeout {
+            task_handle.set_execution_timeout(timeout);
+        }
+        if let Some(threshold) = self.slow_threshold {
+            task_handle.warn_if_slower_than(threshold);
+        }
+        if let Some(key) = self.affinity {
+            task_handle.set_affinity(key);
+        }
+        Ok((task_handle, interval))
+    }
+}
+
+impl<'a> TaskBuilder<'a, CoreExecutor> {
+    /// Schedules the function on the executor thread with the configured options. Returns an
+    /// error if the options are not valid, or if the executor thread has terminated.
+    pub fn spawn<F>(mut self, mut scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.executor.check_running()?;
+        let (task_handle, interval) = self.task_handle()?;
+        let executor = self.executor;
+        let scheduled_fn = move |handle: &Handle, _: &TaskContext| scheduled_fn(handle);
+        Ok(match (self.fixed_interval, self.jitter) {
+            (false, None) => executor.schedule_with_handle(self.fixed_rate(interval), task_handle, scheduled_fn),
+            (false, Some(jitter)) =>
+                executor.schedule_with_handle(Jittered::new(self.fixed_rate(interval), jitter), task_handle, scheduled_fn),
+            (true, None) => executor.schedule_with_handle(FixedInterval::new(self.initial, interval), task_handle, scheduled_fn),
+            (true, Some(jitter)) =>
+                executor.schedule_with_handle(Jittered::new(FixedInterval::new(self.initial, interval), jitter), task_handle, scheduled_fn),
+        })
+    }
+}
+
+impl<'a> TaskBuilder<'a, ThreadPoolExecutor> {
+    /// Sets the overlap policy of the task, see `TaskHandle::set_overlap_policy`.
+    pub fn overlap_policy(mut self, policy: OverlapPolicy) -> TaskBuilder<'a, ThreadPoolExecutor> {
+        self.overlap_policy = Some(policy);
+        self
+    }
+
+    /// Sets the execution timeout of the task, see `TaskHandle::set_execution_timeout`.
+    pub fn execution_timeout(mut self, timeout: Duration) -> TaskBuilder<'a, ThreadPoolExecutor> {
+        self.execution_timeout = Some(timeout);
+        self
+    }
+
+    /// Binds the executions of the task to a thread, see `TaskHandle::set_affinity`.
+    pub fn affinity(mut self, key: u64) -> TaskBuilder<'a, ThreadPoolExecutor> {
+        self.affinity = Some(key);
+        self
+    }
+
+    /// Schedules the function on the pool with the configured options. Returns an error if the
+    /// options are not valid, or if the scheduling thread has terminated.
+    pub fn spawn<F>(mut self, scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.executor.executor.check_running()?;
+        let (task_handle, interval) = self.task_handle()?;
+        if self.fixed_interval && self.overlap_policy.is_none() {
+            task_handle.set_overlap_policy(OverlapPolicy::Queue);
+        }
+        let executor = self.executor;
+        let scheduled_fn = move |remote: &Remote, _: &TaskContext| scheduled_fn(remote);
+        Ok(match (self.fixed_interval, self.jitter) {
+            (false, None) => executor.schedule_with_handle(self.fixed_rate(interval), task_handle, scheduled_fn),
+            (false, Some(jitter)) =>
+                executor.schedule_with_handle(Jittered::new(self.fixed_rate(interval), jitter), task_handle, scheduled_fn),
+            (true, None) => executor.schedule_with_handle(FixedDelay { initial: Some(self.initial), interval }, task_handle, scheduled_fn),
+            (true, Some(jitter)) =>
+                executor.schedule_with_handle(Jittered::new(FixedDelay { initial: Some(self.initial), interval }, jitter), task_handle, scheduled_fn),
+        })
+    }
+}
+
+/// The kind of schedule of a `ScheduleConfig`, written in snake case.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigKind {
+    /// A `FixedRate` schedule, every `interval`.
+    FixedRate,
+    /// A `FixedInterval` schedule, with `interval` between the executions.
+    FixedInterval,
+    /// A `CronSchedule`, described by the `cron` expression.
+    Cron,
+}
+
+/// The schedule and the options of a task, as declared in a configuration file, see
+/// `CoreExecutor::schedule_from_config`. The durations are strings like "30s" or "1h30m", see the
+/// `duration` module. Unknown fields are rejected when deserializing.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// The kind of schedule.
+    pub kind: ConfigKind,
+    /// The delay before the first execution, zero if not set. Not allowed for cron schedules.
+    pub initial: Option<String>,
+    /// The interval of the schedule, required unless it's a cron schedule.
+    pub interval: Option<String>,
+    /// The cron expression, required for cron schedules only.
+    pub cron: Option<String>,
+    /// Moves every execution by a random time up to this, see `Jittered`. Not allowed for cron
+    /// schedules.
+    pub jitter: Option<String>,
+    /// Stops the task after this number of executions.
+    pub max_runs: Option<u64>,
+    /// The name of the task.
+    pub name: Option<String>,
+    /// The tags of the task, see `TaskHandle::add_tag`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The overlap policy of the task, only supported on a `ThreadPoolExecutor`. The policies
+    /// are written in snake case, like `skip` or `{"concurrent": 4}`.
+    pub overlap_policy: Option<OverlapPolicy>,
+}
+
+/// The schedule of a valid `ScheduleConfig`.
+#[cfg(feature = "serde")]
+enum ConfiguredSchedule {
+    Interval {
+        initial: Duration,
+        interval: Duration,
+        jitter: Option<Duration>,
+        fixed_interval: bool,
+    },
+    Cron(CronSchedule),
+}
+
+/// The error of an invalid `ScheduleConfig`, naming the offending field.
+#[cfg(feature = "serde")]
+fn config_error(field: &str, reason: &dyn fmt::Display) -> ExecutorError {
+    ExecutorError::InvalidSchedule(format!("{}: {}", field, reason))
+}
+
+#[cfg(feature = "serde")]
+impl ScheduleConfig {
+    fn duration(field: &str, value: &Option<String>) -> Result<Option<Duration>, ExecutorError> {
+        value.as_ref().map(|value| parse_duration(value).map_err(|e| config_error(field, &e))).transpose()
+    }
+
+    /// Checks the configuration, returning an error naming the first invalid field.
+    fn validate(&self) -> Result<ConfiguredSchedule, ExecutorError> {
+        let initial = ScheduleConfig::duration("initial", &self.initial)?;
+        let interval = ScheduleConfig::duration("interval", &self.interval)?;
+        let jitter = ScheduleConfig::duration("jitter", &self.jitter)?;
+        if self.max_runs == Some(0) {
+            return Err(config_error("max_runs", &"must be at least 1"));
+        }
+        if self.kind == ConfigKind::Cron {
+            let fields = [("initial", initial.is_some()), ("interval", interval.is_some()), ("jitter", jitter.is_some())];
+            if let Some(&(field, _)) = fields.iter().find(|&&(_, set)| set) {
+                return Err(config_error(field, &"not allowed for a cron schedule"));
+            }
+            let expression = self.cron.as_ref().ok_or_else(|| config_error("cron", &"required for a cron schedule"))?;
+            return CronSchedule::parse(expression).map(ConfiguredSchedule::Cron).map_err(|e| config_error("cron", &e));
+        }
+        if self.cron.is_some() {
+            return Err(config_error("cron", &"only allowed for a cron schedule"));
+        }
+        let interval = interval.ok_or_else(|| config_error("interval", &"required unless the schedule is cron"))?;
+        if interval == Duration::from_secs(0) {
+            return Err(config_error("interval", &"must not be zero"));
+        }
+        Ok(ConfiguredSchedule::Interval {
+            initial: initial.unwrap_or_default(),
+            interval,
+            jitter,
+            fixed_interval: self.kind == ConfigKind::FixedInterval,
+        })
+    }
+
+    /// Configures the builder of a task with an interval schedule.
+    fn builder<'a, E>(&self, mut builder: TaskBuilder<'a, E>, initial: Duration, interval: Duration,
+                      jitter: Option<Duration>, fixed_interval: bool) -> TaskBuilder<'a, E> {
+        builder = builder.initial(initial);
+        builder = if fixed_interval { builder.fixed_interval(interval) } else { builder.every(interval) };
+        if let Some(jitter) = jitter {
+            builder = builder.jitter(jitter);
+        }
+        if let Some(ref name) = self.name {
+            builder = builder.named(name);
+        }
+        for tag in &self.tags {
+            builder = builder.tag(tag);
+        }
+        if let Some(max_runs) = self.max_runs {
+            builder = builder.max_runs(max_runs);
+        }
+        builder
+    }
+
+    /// Creates the handle of a task with a cron schedule.
+    fn task_handle(&self) -> TaskHandle {
+        let task_handle = match self.max_runs {
+            Some(max_runs) => TaskHandle::with_max_runs(max_runs),
+            None => TaskHandle::new(),
+        };
+        if let Some(ref name) = self.name {
+            task_handle.set_name(name);
+        }
+        for tag in &self.tags {
+            task_handle.add_tag(tag);
+        }
+        if let Some(policy) = self.overlap_policy {
+            task_handle.set_overlap_policy(policy);
+        }
+        task_handle
+    }
+}
+
+/// The state of a task, owned by its function: it's sent to the `StateReceiver` of the task once
+/// the function is dropped, that is once the task has terminated.
+struct OwnedState<T: Send + 'static> {
+    state: Option<T>,
+    sender: Option<Sender<Box<dyn Any + Send>>>,
+}
+
+impl<T: Send + 'static> OwnedState<T> {
+    /// Returns the handle of a new task owning `state`, and the state.
+    fn new(state: T) -> (TaskHandle, OwnedState<T>) {
+        let (sender, receiver) = channel();
+        let task_handle = TaskHandle::new();
+        task_handle.inner.executions().state_receiver = Some((TypeId::of::<T>(), receiver));
+        (task_handle, OwnedState { state: Some(state), sender: Some(sender) })
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        // Only taken when dropped
+        self.state.as_mut().unwrap()
+    }
+}
+
+impl<T: Send + 'static> Drop for OwnedState<T> {
+    fn drop(&mut self) {
+        if let (Some(state), Some(sender)) = (self.state.take(), self.sender.take()) {
+            let _ = sender.send(Box::new(state));
+        }
+    }
+}
+
+/// A future resolving to the final state of a task, obtained through
+/// `TaskHandle::into_state_receiver`.
+pub struct StateReceiver<T> {
+    receiver: Receiver<Box<dyn Any + Send>>,
+    state: PhantomData<T>,
+}
+
+impl<T: 'static> Future for StateReceiver<T> {
+    type Item = T;
+    type Error = Canceled;
+
+    fn poll(&mut self) -> Poll<T, Canceled> {
+        match self.receiver.poll()? {
+            // The type was checked when the receiver was claimed
+            Async::Ready(state) => Ok(Async::Ready(*state.downcast::<T>().unwrap())),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// A one-shot task whose function can be taken back, obtained through
+/// `CoreExecutor::schedule_once_cancelable` or `ThreadPoolExecutor::schedule_once_cancelable`.
+/// The function is kept in a slot shared with the timer: whichever of the timer and `cancel`
+/// empties it first gets the function, so it's either run or returned, never both.
+pub struct CancelableOnce<F> {
+    slot: Arc<Mutex<Option<F>>>,
+    task_handle: TaskHandle,
+}
+
+impl<F> CancelableOnce<F> {
+    fn new(task_handle: TaskHandle, slot: Arc<Mutex<Option<F>>>) -> CancelableOnce<F> {
+        CancelableOnce { slot, task_handle }
+    }
+
+    /// Stops the task and returns the function, along with everything it owns, if it didn't run
+    /// yet. Returns `None` if the function already ran, is running, or was already taken back.
+    pub fn cancel(&self) -> Option<F> {
+        let scheduled_fn = self.slot.lock().unwrap().take();
+        self.task_handle.stop();
+        scheduled_fn
+    }
+
+    /// The handle of the task running the function.
+    pub fn handle(&self) -> &TaskHandle {
+        &self.task_handle
+    }
+}
+
+/// A future that resolves once a task has terminated, obtained through `TaskHandle::on_stop`.
+pub struct TaskCompletion {
+    inner: Arc<TaskState>,
+}
+
+impl Future for TaskCompletion {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut executions = self.inner.executions();
+        if self.inner.stopped() && self.inner.running() == 0 {
+            return Ok(Async::Ready(()));
+        }
+        if !executions.waiters.iter().any(|waiter| waiter.will_notify_current()) {
+            executions.waiters.push(task::current());
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+/// A stream of the ticks of a task, obtained through `CoreExecutor::interval_stream`. Every
+/// execution of the task yields the `Instant` at which it ran. When the consumer falls behind,
+/// the ticks it didn't take are coalesced into a single pending tick, and counted by `missed`.
+/// The stream ends once the task is stopped; dropping the stream stops the task.
+pub struct IntervalStream {
+    task_handle: TaskHandle,
+    completion: TaskCompletion,
+    ticks: Arc<Mutex<PendingTicks>>,
+}
+
+#[derive(Default)]
+struct PendingTicks {
+    pending: Option<Instant>,
+    missed: u64,
+    consumer: Option<task::Task>,
+}
+
+impl IntervalStream {
+    fn new<F>(schedule_fn: F) -> IntervalStream
+        where F: FnOnce(Box<dyn FnMut(&Handle) + Send>) -> TaskHandle
+    {
+        let ticks = Arc::new(Mutex::new(PendingTicks::default()));
+        let task_ticks = ticks.clone();
+        let task_handle = schedule_fn(Box::new(move |_| {
+            let mut ticks = task_ticks.lock().unwrap();
+            if ticks.pending.is_some() {
+                ticks.missed += 1;
+            }
+            ticks.pending = Some(Instant::now());
+            if let Some(consumer) = ticks.consumer.take() {
+                consumer.notify();
+            }
+        }));
+        IntervalStream {
+            completion: task_handle.on_stop(),
+            task_handle,
+            ticks,
+        }
+    }
+
+    /// The handle of the task producing the ticks.
+    pub fn handle(&self) -> &TaskHandle {
+        &self.task_handle
+    }
+
+    /// The number of ticks that were coalesced because the previous one wasn't consumed yet.
+    pub fn missed(&self) -> u64 {
+        self.ticks.lock().unwrap().missed
+    }
+}
+
+impl Stream for IntervalStream {
+    type Item = Instant;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Instant>, ()> {
+        let mut ticks = self.ticks.lock().unwrap();
+        if let Some(tick) = ticks.pending.take() {
+            return Ok(Async::Ready(Some(tick)));
+        }
+        if let Async::Ready(()) = self.completion.poll()? {
+            return Ok(Async::Ready(None));
+        }
+        ticks.consumer = Some(task::current());
+        Ok(Async::NotReady)
+    }
+}
+
+impl Drop for IntervalStream {
+    fn drop(&mut self) {
+        self.task_handle.stop();
+    }
+}
+
+/// A stream of the values returned by the executions of a task, obtained through the executors'
+/// `_results` methods. At most `capacity` values are buffered, beyond that the `OverflowPolicy`
+/// of the task applies. The stream ends once the task is stopped and the buffered values have
+/// been consumed; dropping the stream stops the task.
+pub struct ResultStream<T> {
+    task_handle: TaskHandle,
+    completion: TaskCompletion,
+    results: Arc<Mutex<ResultQueue<T>>>,
+}
+
+struct ResultQueue<T> {
+    values: VecDeque<T>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+    dropped: u64,
+    consumer: Option<task::Task>,
+    /// The `PushResult` waiting for room, with `OverflowPolicy::Block`.
+    producer: Option<task::Task>,
+}
+
+impl<T> ResultQueue<T> {
+    fn new(capacity: usize, overflow: OverflowPolicy) -> Arc<Mutex<ResultQueue<T>>> {
+        Arc::new(Mutex::new(ResultQueue {
+            values: VecDeque::new(),
+            capacity: capacity.max(1),
+            overflow,
+            dropped: 0,
+            consumer: None,
+            producer: None,
+        }))
+    }
+
+    /// Buffers the value, unless the queue is full and the overflow policy is `Block`.
+    fn offer(&mut self, value: T) -> Result<(), T> {
+        if self.values.len() >= self.capacity {
+            match self.overflow {
+                OverflowPolicy::Block => return Err(value),
+                OverflowPolicy::DropOldest => {
+                    self.values.pop_front();
+                    self.dropped += 1;
+                }
+            }
+        }
+        self.values.push_back(value);
+        if let Some(consumer) = self.consumer.take() {
+            consumer.notify();
+        }
+        Ok(())
+    }
+}
+
+impl<T> ResultStream<T> {
+    fn new(task_handle: TaskHandle, results: Arc<Mutex<ResultQueue<T>>>) -> ResultStream<T> {
+        ResultStream {
+            completion: task_handle.on_stop(),
+            task_handle,
+            results,
+        }
+    }
+
+    /// The handle of the task producing the values.
+    pub fn handle(&self) -> &TaskHandle {
+        &self.task_handle
+    }
+
+    /// The number of values dropped with `OverflowPolicy::DropOldest`.
+    pub fn dropped(&self) -> u64 {
+        self.results.lock().unwrap().dropped
+    }
+}
+
+impl<T> Stream for ResultStream<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        let mut results = self.results.lock().unwrap();
+        if let Some(value) = results.values.pop_front() {
+            if let Some(producer) = results.producer.take() {
+                producer.notify();
+            }
+            return Ok(Async::Ready(Some(value)));
+        }
+        if let Async::Ready(()) = self.completion.poll()? {
+            return Ok(Async::Ready(None));
+        }
+        results.consumer = Some(task::current());
+        Ok(Async::NotReady)
+    }
+}
+
+impl<T> Drop for ResultStream<T> {
+    fn drop(&mut self) {
+        self.task_handle.stop();
+    }
+}
+
+/// Resolves once the value returned by an execution has been added to the `ResultStream` of the
+/// task, or once the task is stopped, dropping the value.
+struct PushResult<T> {
+    results: Arc<Mutex<ResultQueue<T>>>,
+    completion: TaskCompletion,
+    value: Option<T>,
+}
+
+impl<T> PushResult<T> {
+    fn new(results: Arc<Mutex<ResultQueue<T>>>, task_handle: &TaskHandle, value: T) -> PushResult<T> {
+        PushResult { results, completion: task_handle.on_stop(), value: Some(value) }
+    }
+}
+
+impl<T> Future for PushResult<T> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut results = self.results.lock().unwrap();
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => return Ok(Async::Ready(())),
+        };
+        match results.offer(value) {
+            Ok(()) => Ok(Async::Ready(())),
+            Err(value) => {
+                if let Async::Ready(()) = self.completion.poll()? {
+                    debug!("Task stopped, dropping its pending result");
+                    return Ok(Async::Ready(()));
+                }
+                self.value = Some(value);
+                results.producer = Some(task::current());
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+/// A function run on the executor thread, on demand.
+type HandleFn = dyn FnMut(&Handle) + Send;
+
+/// Runs a function once the triggers stop arriving for a given delay, obtained through
+/// `CoreExecutor::debouncer`. The function runs on the executor thread, as the executions of a
+/// task whose handle is returned by `handle`: stopping the task disables the debouncer. Clones
+/// share the same pending execution.
+#[derive(Clone)]
+pub struct Debouncer {
+    inner: Arc<DebounceState>,
+}
+
+struct DebounceState {
+    task_handle: TaskHandle,
+    remote: Remote,
+    delay: Duration,
+    /// Incremented by every trigger, so that only the timer of the last one runs the function.
+    generation: AtomicU64,
+    pending: AtomicBool,
+    /// The entry of the pending timer in the timer wheel, kept with the timers of the task so
+    /// that stopping the task cancels it.
+    timer: Mutex<Option<TimerKey>>,
+    debounced_fn: Mutex<Box<HandleFn>>,
+}
+
+impl DebounceState {
+    fn run(&self, handle: &Handle) {
+        run_on_demand(&self.task_handle, &self.debounced_fn, handle);
+    }
+
+    fn fire(&self, handle: &Handle, generation: u64) {
+        if self.generation.load(Ordering::SeqCst) == generation && self.pending.swap(false, Ordering::SeqCst) {
+            self.run(handle);
+        }
+    }
+
+    /// Replaces the pending timer with one for the trigger of the given generation, unless a
+    /// later trigger, a flush or a cancellation superseded it already.
+    fn arm(self: &Arc<Self>, handle: &Handle, generation: u64) {
+        let wheel = match timer_wheel(handle) {
+            Ok(wheel) => wheel,
+            Err(e) => {
+                warn!("Failed to create the debounce timer, running immediately: {}", e);
+                self.fire(handle, generation);
+                return;
+            }
+        };
+        let mut timer = self.timer.lock().unwrap();
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        self.cancel_timer(&mut timer);
+        let mut executions = self.task_handle.inner.executions();
+        if self.task_handle.stopped() {
+            return;
+        }
+        let state = self.clone();
+        let canceller = wheel.insert(deadline_after(Instant::now(), self.delay), move |handle, key| {
+            state.task_handle.inner.executions().timers.retain(|timer| timer.key() != key);
+            state.timer.lock().unwrap().take_if(|timer| *timer == key);
+            state.fire(handle, generation);
+        });
+        *timer = Some(canceller.key());
+        executions.timers.push(canceller);
+    }
+
+    /// Cancels the pending timer, if any, so that it releases the state right away.
+    fn cancel_timer(&self, timer: &mut Option<TimerKey>) {
+        if let Some(key) = timer.take() {
+            let mut executions = self.task_handle.inner.executions();
+            // Not found if the task was stopped meanwhile, which cancelled it already
+            if let Some(position) = executions.timers.iter().position(|timer| timer.key() == key) {
+                executions.timers.swap_remove(position).cancel();
+            }
+        }
+    }
+
+    /// Supersedes the pending trigger, if any. Returns true if there was one.
+    fn supersede(&self) -> bool {
+        let mut timer = self.timer.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.cancel_timer(&mut timer);
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Runs an execution of a task whose function is only run on demand.
+fn run_on_demand(task_handle: &TaskHandle, on_demand_fn: &Mutex<Box<HandleFn>>, handle: &Handle) {
+    if let Some(execution) = task_handle.begin_execution() {
+        let tick = Tick { scheduled_at: Instant::now(), delay: Duration::from_secs(0) };
+        // A panic of the function poisons the mutex, which must not prevent the next runs
+        execution.run(tick, |_| (*on_demand_fn.lock().unwrap_or_else(|e| e.into_inner()))(handle));
+    }
+}
+
+impl Debouncer {
+    /// Runs the function after the delay, unless another trigger arrives meanwhile, in which
+    /// case the delay starts over. Can be called from any thread.
+    pub fn trigger(&self) {
+        if self.inner.task_handle.stopped() {
+            return;
+        }
+        let generation = self.inner.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.inner.pending.store(true, Ordering::SeqCst);
+        let inner = self.inner.clone();
+        self.inner.remote.spawn(move |handle| {
+            inner.arm(handle, generation);
+            Ok::<(), ()>(())
+        });
+    }
+
+    /// Runs the pending execution, if any, immediately instead of after the delay.
+    pub fn flush(&self) {
+        if self.inner.supersede() {
+            let inner = self.inner.clone();
+            self.inner.remote.spawn(move |handle| {
+                inner.run(handle);
+                Ok::<(), ()>(())
+            });
+        }
+    }
+
+    /// Drops the pending execution, if any. Returns true if there was one.
+    pub fn cancel(&self) -> bool {
+        self.inner.supersede()
+    }
+
+    /// The handle of the task running the function.
+    pub fn handle(&self) -> &TaskHandle {
+        &self.inner.task_handle
+    }
+}
+
+/// When a `Throttle` runs its function, relative to the window opened by a trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleMode {
+    /// The function runs immediately on the first trigger, and the triggers during the window
+    /// are dropped.
+    Leading,
+    /// The function runs once at the end of the window, however many triggers arrived.
+    Trailing,
+    /// The function runs immediately on the first trigger, and once more at the end of the
+    /// window if other triggers arrived meanwhile.
+    LeadingAndTrailing,
+}
+
+/// Runs a function on demand, but no more than once per window, obtained through
+/// `CoreExecutor::throttler`. The function runs on the executor thread, as the executions of a
+/// task whose handle is returned by `handle`: stopping the task disables the throttle. Clones
+/// share the same window.
+#[derive(Clone)]
+pub struct Throttle {
+    inner: Arc<ThrottleState>,
+}
+
+struct ThrottleState {
+    task_handle: TaskHandle,
+    remote: Remote,
+    window: Duration,
+    mode: ThrottleMode,
+    /// Set by a trigger until it's handled on the executor thread, so that the triggers arriving
+    /// meanwhile are coalesced without involving the executor thread.
+    pending: AtomicBool,
+    /// Only accessed on the executor thread.
+    in_window: AtomicBool,
+    throttled_fn: Mutex<Box<HandleFn>>,
+}
+
+impl ThrottleState {
+    fn on_trigger(self: &Arc<Self>, handle: &Handle) {
+        if self.in_window.load(Ordering::Relaxed) {
+            // Handled once the window closes
+            return;
+        }
+        if self.mode != ThrottleMode::Trailing {
+            self.pending.store(false, Ordering::SeqCst);
+            run_on_demand(&self.task_handle, &self.throttled_fn, handle);
+        }
+        self.open_window(handle);
+    }
+
+    fn open_window(self: &Arc<Self>, handle: &Handle) {
+        self.in_window.store(true, Ordering::Relaxed);
+        match new_timeout(self.window, handle) {
+            Ok(timer) => {
+                let state = self.clone();
+                let handle_clone = handle.clone();
+                handle.spawn(timer.then(move |_| {
+                    state.close_window(&handle_clone);
+                    Ok(())
+                }));
+            }
+            Err(e) => {
+                warn!("Failed to create the throttle timer, closing the window immediately: {}", e);
+                self.close_window(handle);
+            }
+        }
+    }
+
+    fn close_window(self: &Arc<Self>, handle: &Handle) {
+        // With `ThrottleMode::Leading`, the triggers of the window are dropped
+        if self.pending.swap(false, Ordering::SeqCst) && self.mode != ThrottleMode::Leading {
+            run_on_demand(&self.task_handle, &self.throttled_fn, handle);
+            self.open_window(handle);
+        } else {
+            self.in_window.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Throttle {
+    /// Requests an execution of the function, which runs according to the mode of the throttle.
+    /// Can be called from any thread.
+    pub fn trigger(&self) {
+        if self.inner.task_handle.stopped() || self.inner.pending.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let inner = self.inner.clone();
+        self.inner.remote.spawn(move |handle| {
+            inner.on_trigger(handle);
+            Ok::<(), ()>(())
+        });
+    }
+
+    /// The handle of the task running the function.
+    pub fn handle(&self) -> &TaskHandle {
+        &self.inner.task_handle
+    }
+}
+
+/// Triggers a run of a task for each item of a stream, see `CoreExecutor::schedule_with_triggers`.
+/// The items already available when it's polled are coalesced into a single run.
+struct TriggerForwarder<T> {
+    task_handle: TaskHandle,
+    completion: TaskCompletion,
+    triggers: T,
+}
+
+impl<T> Future for TriggerForwarder<T>
+    where T: Stream
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        if let Async::Ready(()) = self.completion.poll()? {
+            return Ok(Async::Ready(()));
+        }
+        let mut triggered = false;
+        let result = loop {
+            match self.triggers.poll() {
+                Ok(Async::Ready(Some(_))) => triggered = true,
+                Ok(Async::NotReady) => break Ok(Async::NotReady),
+                Ok(Async::Ready(None)) | Err(_) => {
+                    debug!("Trigger stream ended, the task keeps its regular schedule");
+                    break Ok(Async::Ready(()));
+                }
+            }
+        };
+        if triggered {
+            self.task_handle.trigger_now_and_reschedule();
+        }
+        result
+    }
+}
+
+/// Resolves once an execution completes, when the execution runs outside of the executor thread
+/// and the schedule of the task has to wait for it.
+type Completion = Option<Box<dyn Future<Item = (), Error = ()>>>;
+
+/// Resolves once fewer than `limit` executions of the task are in progress, or once the task is
+/// stopped.
+struct ExecutionSlot {
+    inner: Arc<TaskState>,
+    limit: usize,
+}
+
+impl Future for ExecutionSlot {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut executions = self.inner.executions();
+        if self.inner.stopped() || self.inner.running() < self.limit {
+            return Ok(Async::Ready(()));
+        }
+        if !executions.slot_waiters.iter().any(|waiter| waiter.will_notify_current()) {
+            executions.slot_waiters.push(task::current());
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+/// The state of the scheduling loop of a recurring task, shared with its `Trigger`.
+struct TaskLoop<S, F> {
+    schedule: S,
+    scheduled_fn: F,
+    min_interval: Duration,
+    /// Identifies the pending timer, incremented to cancel it.
+    generation: u64,
+}
+
+fn schedule_loop<S, F>(task_loop: Arc<Mutex<TaskLoop<S, F>>>, handle: &Handle, task_handle: TaskHandle)
+    where S: Schedule,
+          F: FnMut(&Handle, Tick) -> Completion + Send + 'static
+{
+    let now = Instant::now();
+    let (next_iter_wait, generation) = {
+        let mut task_loop = task_loop.lock().unwrap();
+        if let Some(interval) = task_handle.take_interval_change() {
+            let interval = interval.max(task_loop.min_interval).min(MAX_INTERVAL);
+            task_loop.schedule.set_interval(interval);
+            task_handle.inner.executions().interval = task_loop.schedule.interval();
+        }
+        match task_loop.schedule.next_after(now) {
+            Some(wait) => {
+                task_handle.set_skipped_ticks(task_loop.schedule.skipped_ticks());
+                (wait, task_loop.generation)
+            }
+            None => {
+                debug!("Schedule terminated, stopping task");
+                task_handle.finish(TerminationReason::Completed);
+                return;
+            }
+        }
+    };
+    task_handle.set_next_run(Some(deadline_after(now, next_iter_wait)));
+    wait_next_run(task_loop, next_iter_wait, generation, handle, task_handle);
+}
+
+thread_local! {
+    /// The task whose function is running on the current thread, see `TaskHandle::stop_and_wait`.
+    static RUNNING_TASK: Cell<Option<TaskId>> = const { Cell::new(None) };
+}
+
+/// Runs `f` as the function of the task `id`.
+fn running_task<R, F: FnOnce() -> R>(id: TaskId, f: F) -> R {
+    let previous = RUNNING_TASK.with(|task| task.replace(Some(id)));
+    let result = f();
+    RUNNING_TASK.with(|task| task.set(previous));
+    result
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The number of timers that will fail to be created on the current thread.
+    static TIMER_FAILURES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Makes the creation of the next `count` timers on the current thread fail.
+#[cfg(test)]
+fn fail_next_timers(count: usize) {
+    TIMER_FAILURES.with(|failures| failures.set(count));
+}
+
+#[cfg(not(test))]
+fn new_timeout(wait: Duration, handle: &Handle) -> io::Result<Timeout> {
+    Timeout::new_at(deadline_after(Instant::now(), wait), handle)
+}
+
+#[cfg(test)]
+fn new_timeout(wait: Duration, handle: &Handle) -> io::Result<Timeout> {
+    simulate_timer_failure()?;
+    Timeout::new_at(deadline_after(Instant::now(), wait), handle)
+}
+
+/// Whether the monotonic waits use a timer of their own, as they did before the timer wheel,
+/// rather than an entry in the wheel. Only used by `timer_wheel_benchmark`, through the
+/// `BENCHMARK_TIMERS=per_task` environment variable.
+#[cfg(test)]
+fn per_task_timers() -> bool {
+    static PER_TASK_TIMERS: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new();
+    *PER_TASK_TIMERS.get_or_init(|| ::std::env::var("BENCHMARK_TIMERS").is_ok_and(|timers| timers == "per_task"))
+}
+
+#[cfg(not(test))]
+fn per_task_timers() -> bool {
+    false
+}
+
+#[cfg(not(test))]
+fn timer_wheel(handle: &Handle) -> io::Result<TimerWheel> {
+    TimerWheel::of(handle)
+}
+
+#[cfg(test)]
+fn timer_wheel(handle: &Handle) -> io::Result<TimerWheel> {
+    simulate_timer_failure()?;
+    TimerWheel::of(handle)
+}
+
+#[cfg(test)]
+fn simulate_timer_failure() -> io::Result<()> {
+    let fail = TIMER_FAILURES.with(|failures| {
+        let count = failures.get();
+        failures.set(count.saturating_sub(1));
+        count > 0
+    });
+    if fail {
+        return Err(io::Error::other("simulated timer failure"));
+    }
+    Ok(())
+}
+
+/// The timer of the next execution of a task, following the clock source of the task.
+struct TaskTimer {
+    timeout: Timeout,
+    /// With `ClockSource::Wall`, the timeout only lasts until the next check of the system clock.
+    wall_clock: Option<WallClockDeadline>,
+}
+
+struct WallClockDeadline {
+    deadline: Instant,
+    wall_deadline: SystemTime,
+    check_interval: Duration,
+}
+
+impl TaskTimer {
+    fn new(wait: Duration, clock_source: ClockSource, handle: &Handle) -> io::Result<TaskTimer> {
+        match clock_source {
+            ClockSource::Monotonic => Ok(TaskTimer { timeout: new_timeout(wait, handle)?, wall_clock: None }),
+            ClockSource::Wall => {
+                let now = SystemTime::now();
+                let wall_deadline = now.checked_add(wait).unwrap_or_else(|| now + MAX_INTERVAL);
+                TaskTimer::wall_clock(wait, wall_deadline, WALL_CLOCK_CHECK_INTERVAL, handle)
+            }
+        }
+    }
+
+    /// Fires after `wait`, or once the system clock reaches `wall_deadline`, whichever comes
+    /// first.
+    fn wall_clock(wait: Duration, wall_deadline: SystemTime, check_interval: Duration, handle: &Handle) -> io::Result<TaskTimer> {
+        Ok(TaskTimer {
+            timeout: new_timeout(wait.min(check_interval), handle)?,
+            wall_clock: Some(WallClockDeadline { deadline: deadline_after(Instant::now(), wait), wall_deadline, check_interval }),
+        })
+    }
+}
+
+impl Future for TaskTimer {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(), io::Error> {
+        loop {
+            if let Async::NotReady = self.timeout.poll()? {
+                return Ok(Async::NotReady);
+            }
+            let wall_clock = match self.wall_clock {
+                Some(ref wall_clock) => wall_clock,
+                None => return Ok(Async::Ready(())),
+            };
+            let now = Instant::now();
+            if now >= wall_clock.deadline || SystemTime::now() >= wall_clock.wall_deadline {
+                return Ok(Async::Ready(()));
+            }
+            self.timeout.reset(now + (wall_clock.deadline - now).min(wall_clock.check_interval));
+        }
+    }
+}
+
+/// Creates the timer for the next execution of a task. If that fails, the task is stopped, as it
+/// couldn't run anymore; the other tasks of the executor are not affected.
+fn task_timeout(wait: Duration, handle: &Handle, task_handle: &TaskHandle) -> Option<TaskTimer> {
+    let clock_source = task_handle.inner.executions().clock_source;
+    match TaskTimer::new(wait, clock_source, handle) {
+        Ok(timeout) => Some(timeout),
+        Err(e) => {
+            error!("Failed to create timer, stopping task: {}", e);
+            task_handle.finish(TerminationReason::TimerFailed);
+            None
+        }
+    }
+}
+
+/// Waits for the timer of a task, or for the task to be stopped, whichever comes first. Resolves
+/// to false if the task was stopped: the timer is then dropped, which cancels it.
+fn timer_or_stop(timer: TaskTimer, task_handle: &TaskHandle) -> impl Future<Item = bool, Error = ()> {
+    timer.select2(task_handle.cancellation()).then(|result| {
+        match result {
+            // A failed timer fires right away
+            Ok(Either::A(_)) | Err(Either::A(_)) => Ok(true),
+            Ok(Either::B(_)) | Err(Either::B(_)) => Ok(false),
+        }
+    })
+}
+
+/// Calls `fired` on the executor thread once `wait` has elapsed, unless the task is stopped
+/// first: `fired` is then dropped right away. With `ClockSource::Monotonic`, the wait is an
+/// entry in the timer wheel of the executor, shared by all its tasks, while with
+/// `ClockSource::Wall` it's a `TaskTimer` of its own. If the timer can't be created, the task is
+/// stopped, as it couldn't run anymore; the other tasks of the executor are not affected.
+fn after_wait<F>(wait: Duration, handle: &Handle, task_handle: TaskHandle, fired: F)
+    where F: FnOnce(&Handle, TaskHandle) + 'static
+{
+    if task_handle.clock_source() == ClockSource::Wall || per_task_timers() {
+        let timeout = match task_timeout(wait, handle, &task_handle) {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        let handle_clone = handle.clone();
+        handle.spawn(timer_or_stop(timeout, &task_handle).map(move |timer_fired| {
+            if timer_fired {
+                fired(&handle_clone, task_handle);
+            }
+        }));
+        return;
+    }
+    let wheel = match timer_wheel(handle) {
+        Ok(wheel) => wheel,
+        Err(e) => {
+            error!("Failed to create timer, stopping task: {}", e);
+            task_handle.finish(TerminationReason::TimerFailed);
+            return;
+        }
+    };
+    let task_handle_clone = task_handle.clone();
+    let mut executions = task_handle_clone.inner.executions();
+    if task_handle.stopped() {
+        return;
+    }
+    let timer = wheel.insert(deadline_after(Instant::now(), wait), move |handle, key| {
+        task_handle.inner.executions().timers.retain(|timer| timer.key() != key);
+        fired(handle, task_handle);
+    });
+    executions.timers.push(timer);
+}
+
+fn wait_next_run<S, F>(task_loop: Arc<Mutex<TaskLoop<S, F>>>, wait: Duration, generation: u64, handle: &Handle, task_handle: TaskHandle)
+    where S: Schedule,
+          F: FnMut(&Handle, Tick) -> Completion + Send + 'static
+{
+    let scheduled_at = deadline_after(Instant::now(), wait);
+    // Even a zero wait goes through the timer, so the execution never chains with the previous
+    // one without returning to the event loop, where the other tasks due meanwhile get to run
+    after_wait(wait, handle, task_handle, move |handle, task_handle| {
+        if task_handle.stopped() {
+            return;
+        }
+        let completion = {
+            let mut task_loop_guard = task_loop.lock().unwrap();
+            if task_loop_guard.generation != generation {
+                // Rescheduled by a trigger, which already armed a new timer
+                return;
+            }
+            if let Some(extra) = task_handle.take_postponement() {
+                drop(task_loop_guard);
+                wait_next_run(task_loop, extra, generation, handle, task_handle);
+                return;
+            }
+            task_handle.set_next_run(None);
+            // A skipped execution is seen by the schedule as a run taking no time
+            task_loop_guard.schedule.on_run(Instant::now());
+            if task_handle.paused() {
+                None
+            } else {
+                let tick = Tick { scheduled_at, delay: task_loop_guard.schedule.delay() };
+                (task_loop_guard.scheduled_fn)(handle, tick)
+            }
+        };
+        schedule_loop_after(completion, task_loop, handle, task_handle);
+    });
+}
+
+/// Continues the scheduling loop of a task once `completion` resolves, if there's one, or right
+/// away otherwise: for an execution on the pool, the timer of the next run is only armed once it
+/// completed.
+fn schedule_loop_after<S, F>(completion: Completion, task_loop: Arc<Mutex<TaskLoop<S, F>>>, handle: &Handle, task_handle: TaskHandle)
+    where S: Schedule,
+          F: FnMut(&Handle, Tick) -> Completion + Send + 'static
+{
+    match completion {
+        Some(completion) => {
+            let handle_clone = handle.clone();
+            handle.spawn(completion.then(move |_| {
+                schedule_loop(task_loop, &handle_clone, task_handle);
+                Ok::<(), ()>(())
+            }));
+        }
+        None => schedule_loop(task_loop, handle, task_handle),
+    }
+}
+
+fn trigger_run<S, F>(task_loop: Arc<Mutex<TaskLoop<S, F>>>, reschedule: bool, handle: &Handle, task_handle: TaskHandle)
+    where S: Schedule,
+          F: FnMut(&Handle, Tick) -> Completion + Send + 'static
+{
+    if task_handle.stopped() || task_handle.paused() {
+        return;
+    }
+    let completion = {
+        let mut task_loop = task_loop.lock().unwrap();
+        if reschedule {
+            task_loop.generation += 1;
+            task_handle.set_next_run(None);
+            task_loop.schedule.on_run(Instant::now());
+        }
+        let tick = Tick { scheduled_at: Instant::now(), delay: task_loop.schedule.delay() };
+        (task_loop.scheduled_fn)(handle, tick)
+    };
+    if reschedule {
+        schedule_loop_after(completion, task_loop, handle, task_handle);
+    } else if let Some(completion) = completion {
+        handle.spawn(completion);
+    }
+}
+
+/// Runs the task only when `predicate` holds; otherwise, the execution is skipped, and the task
+/// stopped if `stop` is set. The predicate is evaluated on the executor thread.
+fn gated<P, F>(predicate: P, stop: bool, task_handle: TaskHandle, mut scheduled_fn: F) -> impl FnMut(&Handle, Tick) -> Completion + Send + 'static
+    where P: Fn() -> bool + Send + 'static,
+          F: FnMut(&Handle, Tick) -> Completion + Send + 'static
+{
+    move |handle, tick| {
+        if predicate() {
+            return scheduled_fn(handle, tick);
+        }
+        if stop {
+            debug!("Predicate not satisfied, stopping task");
+            task_handle.finish(TerminationReason::Completed);
+        }
+        None
+    }
+}
+
+fn run_once<F>(scheduled_fn: F, delay: Duration, handle: &Handle, task_handle: TaskHandle)
+    where F: FnOnce(&Handle, Tick) + Send + 'static
+{
+    let scheduled_at = deadline_after(Instant::now(), delay);
+    task_handle.set_next_run(Some(scheduled_at));
+    after_wait(delay, handle, task_handle, move |handle, task_handle| {
+        if task_handle.stopped() {
+            return;
+        }
+        if let Some(extra) = task_handle.take_postponement() {
+            run_once(scheduled_fn, extra, handle, task_handle);
+            return;
+        }
+        task_handle.set_next_run(None);
+        scheduled_fn(handle, Tick { scheduled_at, delay: Duration::from_secs(0) });
+    });
+}
+
+/// The schedule used by `ThreadPoolExecutor::schedule_fixed_interval` and
+/// `CoreExecutor::schedule_async_interval`: every wait is measured from the end of the previous
+/// execution.
+struct FixedDelay {
+    initial: Option<Duration>,
+    interval: Duration,
+}
+
+impl Schedule for FixedDelay {
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        Some(self.initial.take().unwrap_or(self.interval))
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    fn kind(&self) -> ScheduleKind {
+        ScheduleKind::FixedInterval
+    }
+}
+
+/// The schedule used by `schedule_dynamic`: the wait before each execution is the one returned by
+/// the last execution (or the initial one, before the first execution).
+struct DynamicDelay {
+    next: Arc<Mutex<Option<Duration>>>,
+}
+
+impl Schedule for DynamicDelay {
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        *self.next.lock().unwrap()
+    }
+}
+
+/// The schedule used by `schedule_backoff`: the backoff is reset after every successful execution.
+struct ResettableBackoff {
+    backoff: Backoff,
+    succeeded: Arc<AtomicBool>,
+}
+
+impl Schedule for ResettableBackoff {
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        if self.succeeded.swap(false, Ordering::Relaxed) {
+            self.backoff.reset();
+        }
+        Some(self.backoff.next_delay())
+    }
+}
+
+/// The event loop the tasks of a `CoreExecutor` are scheduled on.
+enum Reactor {
+    /// The event loop runs on a thread owned by the executor, terminated with it. With
+    /// `CoreExecutor::run_on_current_thread`, the event loop runs on the calling thread instead,
+    /// and there is no thread to join.
+    Owned {
+        termination_sender: Mutex<Option<Sender<()>>>,
+        thread_handle: Mutex<Option<JoinHandle<()>>>,
+        /// Cleared once the event loop terminates.
+        alive: Arc<AtomicBool>,
+    },
+    /// The event loop belongs to the application, which runs it, see `CoreExecutor::from_remote`.
+    Borrowed,
+}
+
+impl Reactor {
+    fn take_termination_sender(&self) -> Option<Sender<()>> {
+        match *self {
+            Reactor::Owned { ref termination_sender, .. } => termination_sender.lock().unwrap().take(),
+            Reactor::Borrowed => None,
+        }
+    }
+
+    fn take_thread_handle(&self) -> Option<JoinHandle<()>> {
+        match *self {
+            Reactor::Owned { ref thread_handle, .. } => thread_handle.lock().unwrap().take(),
+            Reactor::Borrowed => None,
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match *self {
+            Reactor::Owned { ref alive, .. } => alive.load(Ordering::Relaxed),
+            Reactor::Borrowed => true,
+        }
+    }
+
+    /// Returns the name of the executor thread, unless the event loop runs on a thread the
+    /// executor doesn't own, or the thread was detached.
+    fn thread_name(&self) -> Option<String> {
+        match *self {
+            Reactor::Owned { ref thread_handle, .. } => thread_handle.lock().unwrap().as_ref()
+                .and_then(|thread_handle| thread_handle.thread().name().map(|name| name.to_owned())),
+            Reactor::Borrowed => None,
+        }
+    }
+}
+
+/// The lowest `CoreExecutorInner::prune_at`.
+const MIN_PRUNE_AT: usize = 64;
+
+struct CoreExecutorInner {
+    remote: Remote,
+    reactor: Reactor,
+    /// The tasks scheduled on this executor, stopped on termination.
+    tasks: Mutex<HashMap<TaskId, Weak<TaskState>>>,
+    /// The number of registered tasks from which the terminated ones are pruned on registration,
+    /// twice the number of tasks left by the last pruning, so that registering is amortized
+    /// constant time.
+    prune_at: AtomicUsize,
+    hooks: Arc<ExecutorHooks>,
+    /// Cleared when the executor thread terminates, for whatever reason.
+    running: Arc<AtomicBool>,
+    /// Set by `CoreExecutor::detach`: the executor thread is left running when dropped.
+    detached: AtomicBool,
+    /// The intervals of the tasks are raised to at least this, see `MIN_INTERVAL`.
+    min_interval: Duration,
+    /// The task reporting the metrics to the sink, see `CoreExecutor::set_metrics_sink`.
+    metrics_flusher: Mutex<Option<TaskHandle>>,
+}
+
+impl CoreExecutorInner {
+    /// Registers a task with the executor. If the executor thread has terminated, the task would
+    /// never run, so it's stopped right away.
+    fn register(&self, task_handle: &TaskHandle) {
+        {
+            let mut tasks = self.tasks.lock().unwrap();
+            if tasks.len() >= self.prune_at.load(Ordering::Relaxed) {
+                CoreExecutorInner::prune(&mut tasks);
+                self.prune_at.store((tasks.len() * 2).max(MIN_PRUNE_AT), Ordering::Relaxed);
+            }
+            tasks.insert(task_handle.inner.id, Arc::downgrade(&task_handle.inner));
+            task_handle.inner.executions().executor_hooks = Some(self.hooks.clone());
+        }
+        if let Some(observer) = self.hooks.observer() {
+            observer.on_schedule(&task_handle.inner.info());
+        }
+        {
+            let task = &task_handle.inner;
+            let executions = task.executions();
+            if let Some((level, target)) = task.log_target(&executions) {
+                log!(target: &target, level, "Task {} scheduled: {:?}, interval {:?}", task.id, executions.kind,
+                     executions.interval);
+            }
+        }
+        if !self.running.load(Ordering::Relaxed) {
+            warn!("Executor thread terminated, the task will not run");
+            task_handle.finish(TerminationReason::ExecutorDropped);
+        }
+    }
+
+    /// Removes the tasks that terminated. Stopped tasks are kept until their last execution
+    /// completes, for `shutdown`.
+    fn prune(tasks: &mut HashMap<TaskId, Weak<TaskState>>) {
+        tasks.retain(|_, task| task.upgrade().is_some_and(|task| !task.stopped() || task.running() > 0));
+    }
+
+    /// Returns the tasks with the given tag that didn't terminate yet, sorted by id.
+    fn live_tasks_tagged(&self, tag: &str) -> Vec<Arc<TaskState>> {
+        let mut tasks = self.live_tasks();
+        tasks.retain(|task| task.executions().tags.contains(tag));
+        tasks
+    }
+
+    /// Returns the tasks that didn't terminate yet, sorted by id.
+    fn live_tasks(&self) -> Vec<Arc<TaskState>> {
+        let mut tasks = self.tasks.lock().unwrap();
+        CoreExecutorInner::prune(&mut tasks);
+        let mut live_tasks = tasks.values().filter_map(|task| task.upgrade()).collect::<Vec<_>>();
+        live_tasks.sort_by_key(|task| task.id);
+        live_tasks
+    }
+}
+
+/// Clears the running and alive flags of an executor when its event loop terminates, even if it
+/// panicked.
+struct RunningGuard {
+    running: Arc<AtomicBool>,
+    alive: Arc<AtomicBool>,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            error!("Executor thread panicked, its tasks won't run anymore");
+        }
+        self.running.store(false, Ordering::Relaxed);
+        self.alive.store(false, Ordering::Relaxed);
+    }
+}
+
+impl Drop for CoreExecutorInner {
+    fn drop(&mut self) {
+        if *self.detached.get_mut() {
+            debug!("Dropping detached executor, its thread will keep running");
+            return;
+        }
+        if let Some(termination_sender) = self.reactor.take_termination_sender() {
+            let _ = termination_sender.send(());
+        }
+        if let Some(thread_handle) = self.reactor.take_thread_handle() {
+            // The thread may have died long before, which was already reported
+            if let Err(payload) = thread_handle.join() {
+                debug!("Executor thread had panicked: {}", panic_message(&*payload));
+            }
+        }
+        // The pending tasks will never run again: the core doesn't release them when dropped
+        let tasks = self.tasks.get_mut().unwrap_or_else(|e| e.into_inner());
+        for task in tasks.drain().filter_map(|(_, task)| task.upgrade()) {
+            task.stop(&mut task.executions(), TerminationReason::ExecutorDropped);
+        }
+    }
+}
+
+/// A hook run on the threads of an executor, see `ExecutorBuilder`.
+type ThreadHook = dyn Fn() + Send + Sync;
+
+/// The configuration of the threads created by an executor.
+#[derive(Clone, Default)]
+struct ThreadConfig {
+    stack_size: Option<usize>,
+    after_start: Option<Arc<ThreadHook>>,
+    before_stop: Option<Arc<ThreadHook>>,
+}
+
+impl ThreadConfig {
+    fn pool_builder(&self, threads: usize, prefix: &str) -> Builder {
+        let mut builder = Builder::new();
+        builder.pool_size(threads).name_prefix(prefix);
+        if let Some(stack_size) = self.stack_size {
+            builder.stack_size(stack_size);
+        }
+        if let Some(ref after_start) = self.after_start {
+            let after_start = after_start.clone();
+            builder.after_start(move || after_start());
+        }
+        if let Some(ref before_stop) = self.before_stop {
+            let before_stop = before_stop.clone();
+            builder.before_stop(move || before_stop());
+        }
+        builder
+    }
+}
+
+/// Creates executors with a custom configuration of their threads. `CoreExecutor::with_name` and
+/// `ThreadPoolExecutor::with_prefix` are shortcuts for the most common case.
+#[derive(Clone, Default)]
+pub struct ExecutorBuilder {
+    name: Option<String>,
+    config: ThreadConfig,
+    /// Overrides the number of logical CPUs reported by the system.
+    cpus: Option<usize>,
+    min_interval: Option<Duration>,
+}
+
+/// Returns the number of logical CPUs the `_auto` pools are sized from, or 1 if it can't be
+/// determined.
+fn available_cpus() -> usize {
+    thread::available_parallelism().map(|cpus| cpus.get()).unwrap_or(1)
+}
+
+impl ExecutorBuilder {
+    /// Creates a builder with the default configuration.
+    pub fn new() -> ExecutorBuilder {
+        ExecutorBuilder::default()
+    }
+
+    /// Sets the name of the thread of a `CoreExecutor`, "core_executor" by default. For a
+    /// `ThreadPoolExecutor`, this is the prefix of the names of the threads, "pool_thread_" by
+    /// default.
+    pub fn name(mut self, name: &str) -> ExecutorBuilder {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Sets the stack size of the threads, see `std::thread::Builder::stack_size`.
+    pub fn stack_size(mut self, stack_size: usize) -> ExecutorBuilder {
+        self.config.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Sets a function run on every thread of the executor when it starts, before any task runs
+    /// there. For a `ThreadPoolExecutor`, it runs on the threads of the pool.
+    pub fn after_start<F>(mut self, after_start: F) -> ExecutorBuilder
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.config.after_start = Some(Arc::new(after_start));
+        self
+    }
+
+    /// Sets a function run on every thread of the executor right before it terminates. For a
+    /// `ThreadPoolExecutor`, it runs on the threads of the pool.
+    pub fn before_stop<F>(mut self, before_stop: F) -> ExecutorBuilder
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.config.before_stop = Some(Arc::new(before_stop));
+        self
+    }
+
+    /// Sets the number of logical CPUs `build_pool_auto` sizes the pool from, for the
+    /// environments where the system misreports it.
+    pub fn cpus(mut self, cpus: usize) -> ExecutorBuilder {
+        self.cpus = Some(cpus);
+        self
+    }
+
+    /// Sets the minimum interval of the tasks, `MIN_INTERVAL` by default: shorter intervals are
+    /// raised to it. A zero minimum lets tasks run back to back, which will hog the executor
+    /// thread with a zero interval.
+    pub fn min_interval(mut self, min_interval: Duration) -> ExecutorBuilder {
+        self.min_interval = Some(min_interval);
+        self
+    }
+
+    /// Creates a `CoreExecutor`. Returns an error if the thread can't be spawned, or if the
+    /// event loop fails to start.
+    pub fn build(&self) -> Result<CoreExecutor, ExecutorError> {
+        CoreExecutor::start(self.name.as_deref().unwrap_or("core_executor"), &self.config, self.min_interval.unwrap_or(MIN_INTERVAL))
+    }
+
+    /// Creates a `ThreadPoolExecutor` with the given number of threads. Its scheduling thread
+    /// is named after the prefix, followed by "executor", and keeps the default configuration.
+    pub fn build_pool(&self, threads: usize) -> Result<ThreadPoolExecutor, ExecutorError> {
+        let prefix = self.name.as_deref().unwrap_or("pool_thread_");
+        let executor = CoreExecutor::start(&format!("{}executor", prefix), &ThreadConfig::default(), self.min_interval.unwrap_or(MIN_INTERVAL))?;
+        Ok(ThreadPoolExecutor::with_config(threads, prefix, executor, &self.config))
+    }
+
+    /// Like `build_pool`, but the pool has `factor` threads for each logical CPU, rounded and
+    /// capped to `max`, and at least one thread. A factor above 1 suits I/O-heavy tasks.
+    pub fn build_pool_auto(&self, factor: f64, max: usize) -> Result<ThreadPoolExecutor, ExecutorError> {
+        let cpus = self.cpus.unwrap_or_else(available_cpus);
+        let threads = (cpus as f64 * factor).round() as usize;
+        debug!("Sizing the pool to {} threads for {} CPUs", threads, cpus);
+        self.build_pool(threads.min(max).max(1))
+    }
+}
+
+/// A `CoreExecutor` is the most simple executor provided. It runs a single thread, which is
+/// responsible for both scheduling the function (registering the timer for the wakeup),
+/// and the actual execution. The executor will stop once dropped. The `CoreExecutor`
+/// can be cloned to generate a new reference to the same underlying executor.
+/// Given the single threaded nature of this executor, tasks are executed sequentially, and a long
+/// running task will cause delay in other subsequent executions.
+pub struct CoreExecutor {
+    inner: Arc<CoreExecutorInner>
+}
+
+impl Clone for CoreExecutor {
+    fn clone(&self) -> Self {
+        CoreExecutor { inner: Arc::clone(&self.inner) }
+    }
+}
+
+impl fmt::Debug for CoreExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CoreExecutor")
+            .field("thread", &self.inner.reactor.thread_name())
+            .field("running", &self.is_running())
+            .field("alive", &self.is_alive())
+            .field("tasks", &self.inner.live_tasks().len())
+            .finish()
+    }
+}
+
+impl CoreExecutor {
+    /// Creates a new `CoreExecutor`.
+    pub fn new() -> Result<CoreExecutor, ExecutorError> {
+        CoreExecutor::with_name("core_executor")
+    }
+
+    /// Creates a new `CoreExecutor` with the specified thread name. Returns an error if the thread
+    /// can't be spawned, or if the event loop fails to start. See `ExecutorBuilder` for more
+    /// options.
+    pub fn with_name(thread_name: &str) -> Result<CoreExecutor, ExecutorError> {
+        ExecutorBuilder::new().name(thread_name).build()
+    }
+
+    fn start(thread_name: &str, config: &ThreadConfig, min_interval: Duration) -> Result<CoreExecutor, ExecutorError> {
+        let (termination_tx, termination_rx) = channel();
+        let (core_tx, core_rx) = channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let alive = Arc::new(AtomicBool::new(true));
+        let running_guard = RunningGuard { running: running.clone(), alive: alive.clone() };
+        let mut builder = thread::Builder::new().name(thread_name.to_owned());
+        if let Some(stack_size) = config.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        let (after_start, before_stop) = (config.after_start.clone(), config.before_stop.clone());
+        let thread_handle = builder
+            .spawn(move || {
+                let _running_guard = running_guard;
+                debug!("Core starting");
+                let mut core = match Core::new() {
+                    Ok(core) => core,
+                    Err(e) => {
+                        let _ = core_tx.send(Err(ExecutorError::ReactorInit(e)));
+                        return;
+                    }
+                };
+                if let Some(after_start) = after_start {
+                    after_start();
+                }
+                let _ = core_tx.send(Ok(core.remote()));
+                match core.run(termination_rx) {
+                    Ok(v) => debug!("Core terminated correctly {:?}", v),
+                    Err(e) => debug!("Core terminated with error: {:?}", e),
+                }
+                if let Some(before_stop) = before_stop {
+                    before_stop();
+                }
+            })
+            .map_err(ExecutorError::ThreadSpawn)?;
+        // If the thread panicked before sending anything, the sender is dropped
+        let remote = core_rx.wait().unwrap_or_else(|_| {
+            Err(ExecutorError::ReactorInit(io::Error::other("executor thread terminated during startup")))
+        });
+        let remote = match remote {
+            Ok(remote) => remote,
+            Err(e) => {
+                error!("Failed to start executor: {}", e);
+                let _ = thread_handle.join();
+                return Err(e);
+            }
+        };
+        let reactor = Reactor::Owned {
+            termination_sender: Mutex::new(Some(termination_tx)),
+            thread_handle: Mutex::new(Some(thread_handle)),
+            alive,
+        };
+        debug!("Executor created");
+        Ok(CoreExecutor::with_reactor(remote, reactor, running, min_interval))
+    }
+
+    /// Creates a new `CoreExecutor` scheduling the tasks on an event loop run by the application,
+    /// instead of starting its own thread. The tasks run on the thread running the `Core`, and
+    /// only while it runs. Dropping or shutting down the executor stops its tasks, but leaves the
+    /// event loop untouched; since the executor can't tell when the event loop terminates, it's
+    /// considered running until then.
+    pub fn from_remote(remote: Remote) -> CoreExecutor {
+        debug!("Executor created on an existing event loop");
+        CoreExecutor::with_reactor(remote, Reactor::Borrowed, Arc::new(AtomicBool::new(true)), MIN_INTERVAL)
+    }
+
+    /// Runs an executor on the current thread, until the future returned by `setup` resolves, or
+    /// the executor is shut down. `setup` receives the executor before the event loop starts, to
+    /// schedule the first tasks; further tasks can be scheduled while it runs, through clones of
+    /// the executor. When this returns, the tasks still scheduled are stopped. Since the tasks can
+    /// be scheduled from other threads, the functions still need to be `Send`.
+    pub fn run_on_current_thread<F, T>(setup: F) -> Result<(), ExecutorError>
+        where F: FnOnce(&CoreExecutor) -> T,
+              T: Future<Item = (), Error = ()>
+    {
+        let mut core = Core::new().map_err(ExecutorError::ReactorInit)?;
+        let (termination_tx, termination_rx) = channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let alive = Arc::new(AtomicBool::new(true));
+        let reactor = Reactor::Owned {
+            termination_sender: Mutex::new(Some(termination_tx)),
+            thread_handle: Mutex::new(None),
+            alive: alive.clone(),
+        };
+        let executor = CoreExecutor::with_reactor(core.remote(), reactor, running.clone(), MIN_INTERVAL);
+        let _running_guard = RunningGuard { running, alive };
+        let termination = setup(&executor);
+        debug!("Core starting on the current thread");
+        let shutdown = termination_rx.map_err(|_| ());
+        match core.run(termination.select2(shutdown)) {
+            Ok(_) => debug!("Core terminated correctly"),
+            Err(_) => debug!("Core terminated with error"),
+        }
+        // Clones of the executor may outlive the event loop
+        executor.inner.running.store(false, Ordering::Relaxed);
+        for task in executor.inner.live_tasks() {
+            task.stop(&mut task.executions(), TerminationReason::ExecutorDropped);
+        }
+        Ok(())
+    }
+
+    fn with_reactor(remote: Remote, reactor: Reactor, running: Arc<AtomicBool>, min_interval: Duration) -> CoreExecutor {
+        let inner = CoreExecutorInner {
+            remote,
+            reactor,
+            tasks: Mutex::new(HashMap::new()),
+            prune_at: AtomicUsize::new(MIN_PRUNE_AT),
+            hooks: Arc::new(ExecutorHooks::default()),
+            running,
+            detached: AtomicBool::new(false),
+            metrics_flusher: Mutex::new(None),
+            min_interval,
+        };
+        CoreExecutor {
+            inner: Arc::new(inner)
+        }
+    }
+
+    /// Returns false once the executor is shut down, or its thread has terminated, for example
+    /// because it panicked. Tasks scheduled on a terminated executor are stopped right away, since
+    /// they would never run.
+    pub fn is_running(&self) -> bool {
+        self.inner.running.load(Ordering::Relaxed)
+    }
+
+    /// Returns false once the executor thread has terminated, for example because it panicked.
+    /// Unlike `is_running`, this stays true after a shutdown until the thread actually
+    /// terminates. An executor created by `from_remote` is always considered alive, since it
+    /// can't tell when the event loop terminates.
+    pub fn is_alive(&self) -> bool {
+        self.inner.reactor.is_alive()
+    }
+
+    /// Stops all the tasks of the executor, waits up to `grace` for the executions in progress to
+    /// complete, and then terminates the executor thread, even if other clones of the executor
+    /// are still around. If the grace period expires first, the remaining executions are
+    /// abandoned: the executor thread is detached instead of being joined, and an error is
+    /// returned. A grace period too long to be represented has no deadline.
+    pub fn shutdown(self, grace: Duration) -> Result<(), ExecutorError> {
+        let deadline = Instant::now().checked_add(grace);
+        // From now on, new tasks are stopped as soon as they're registered
+        self.inner.running.store(false, Ordering::Relaxed);
+        let tasks = self.inner.live_tasks();
+        for task in &tasks {
+            task.stop(&mut task.executions(), TerminationReason::ExecutorDropped);
+        }
+        let running = tasks.iter()
+            .map(|task| task.wait_idle(task.executions(), deadline))
+            .sum::<usize>();
+        if let Some(termination_sender) = self.inner.reactor.take_termination_sender() {
+            let _ = termination_sender.send(());
+        }
+        if running > 0 {
+            warn!("Shutdown grace period expired, abandoning {} executions", running);
+            self.inner.reactor.take_thread_handle();
+            return Err(ExecutorError::ShutdownTimedOut(running));
+        }
+        debug!("Executor shut down");
+        Ok(())
+    }
+
+    /// Returns a snapshot of the tasks scheduled on this executor that didn't terminate yet,
+    /// sorted by id.
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        self.inner.live_tasks().iter().map(|task| task.info()).collect()
+    }
+
+    /// Returns the aggregate metrics of this executor. The `recent_executions` counter is reset
+    /// by every call, so it's meant to be read by a single periodic reporter.
+    pub fn metrics(&self) -> ExecutorMetrics {
+        let live_tasks = self.inner.live_tasks().iter().filter(|task| !task.stopped()).count();
+        self.inner.hooks.metrics(live_tasks, true)
+    }
+
+    /// Returns the metrics of the executor and the state and statistics of each of its tasks,
+    /// serialized as a JSON object, with `metrics` and `tasks` keys. Durations are reported in
+    /// milliseconds, and instants as the milliseconds elapsed since. The tasks are ranked by
+    /// their total busy time, the most expensive first. Unlike `metrics`, this doesn't reset the
+    /// `recent_executions` counter.
+    #[cfg(feature = "serde")]
+    pub fn dump_json(&self) -> String {
+        let tasks = self.inner.live_tasks();
+        let mut snapshots = tasks.iter()
+            .map(|task| {
+                let executions = task.executions();
+                TaskSnapshot { info: task.info_locked(&executions), stats: executions.stats.clone() }
+            })
+            .collect::<Vec<_>>();
+        snapshots.sort_by_key(|snapshot| cmp::Reverse(snapshot.info.busy_time));
+        let snapshot = RegistrySnapshot {
+            metrics: self.inner.hooks.metrics(tasks.iter().filter(|task| !task.stopped()).count(), false),
+            tasks: snapshots,
+        };
+        // The snapshot only contains strings, numbers and booleans
+        serde_json::to_string(&snapshot).unwrap_or_default()
+    }
+
+    /// Stops all the tasks of this executor with the given name, as if `TaskHandle::stop` was
+    /// called on each of them. Returns false if no running task has that name.
+    pub fn stop_task(&self, name: &str) -> bool {
+        let mut found = false;
+        for task in self.inner.live_tasks() {
+            let mut executions = task.executions();
+            if !task.stopped() && executions.name.as_deref() == Some(name) {
+                debug!("Stopping task {} ({})", task.id, name);
+                task.stop(&mut executions, TerminationReason::Stopped);
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Returns a snapshot of the tasks with the given tag that didn't terminate yet, sorted by id.
+    pub fn tasks_tagged(&self, tag: &str) -> Vec<TaskInfo> {
+        self.inner.live_tasks_tagged(tag).iter().map(|task| task.info()).collect()
+    }
+
+    /// Stops all the tasks with the given tag, as if `TaskHandle::stop` was called on each of
+    /// them, and returns how many were still running.
+    pub fn stop_tagged(&self, tag: &str) -> usize {
+        let stopped = self.inner.live_tasks_tagged(tag).iter()
+            .filter(|task| task.stop(&mut task.executions(), TerminationReason::Stopped))
+            .count();
+        debug!("Stopped {} tasks tagged {}", stopped, tag);
+        stopped
+    }
+
+    /// Pauses all the tasks with the given tag, and returns how many were affected. The stopped
+    /// tasks, even with an execution still in progress, are not.
+    pub fn pause_tagged(&self, tag: &str) -> usize {
+        let tasks = self.inner.live_tasks_tagged(tag);
+        tasks.iter().filter(|task| !task.stopped()).map(|task| task.paused.store(true, Ordering::Relaxed)).count()
+    }
+
+    /// Resumes all the tasks with the given tag, and returns how many were affected. The stopped
+    /// tasks, even with an execution still in progress, are not.
+    pub fn resume_tagged(&self, tag: &str) -> usize {
+        let tasks = self.inner.live_tasks_tagged(tag);
+        tasks.iter().filter(|task| !task.stopped()).map(|task| task.paused.store(false, Ordering::Relaxed)).count()
+    }
+
+    /// Stops all the tasks scheduled so far on this executor, as if `TaskHandle::stop` was called
+    /// on each of them, and returns how many were still running. The executor itself keeps
+    /// running, and the tasks scheduled afterwards are not affected.
+    pub fn stop_all_tasks(&self) -> usize {
+        let tasks = self.inner.live_tasks();
+        let stopped = tasks.iter()
+            .filter(|task| task.stop(&mut task.executions(), TerminationReason::Stopped))
+            .count();
+        debug!("Stopped {} tasks", stopped);
+        stopped
+    }
+
+    /// Detaches the executor thread, which will keep running the scheduled tasks for the lifetime
+    /// of the process, even after all the references to the executor are dropped: the tasks can
+    /// only be stopped through their handles. The resources of the executor are intentionally
+    /// leaked, so this should only be used for executors that are meant to live as long as the
+    /// process.
+    pub fn detach(self) {
+        if let Some(termination_sender) = self.inner.reactor.take_termination_sender() {
+            // Dropping the sender would terminate the event loop
+            mem::forget(termination_sender);
+        }
+        self.inner.reactor.take_thread_handle();
+        self.inner.detached.store(true, Ordering::Relaxed);
+        debug!("Executor detached");
+    }
+
+    /// Returns the `Remote` of the event loop running on the executor thread, to bind other
+    /// resources to it, or to implement schedules this crate doesn't provide. Anything running on
+    /// the event loop delays the scheduled tasks, so it should never block. The `Handle` of the
+    /// event loop can be obtained through `Remote::handle`, from the executor thread only (for
+    /// example, from a scheduled function).
+    pub fn remote(&self) -> Remote {
+        self.inner.remote.clone()
+    }
+
+    /// Spawns the given future on the executor thread, where it runs alongside the scheduled
+    /// tasks: it should not block. Returns an error if the executor has terminated, instead of
+    /// dropping the future.
+    pub fn spawn<F>(&self, future: F) -> Result<(), ExecutorError>
+        where F: Future<Item = (), Error = ()> + Send + 'static
+    {
+        self.check_running()?;
+        self.inner.remote.spawn(move |_| future);
+        Ok(())
+    }
+
+    /// Runs the given function on the executor thread as soon as possible, and returns a
+    /// `Receiver` that will resolve to the value it returns. The function receives the `Handle`
+    /// of the event loop, to spawn futures or register events. Returns an error if the executor
+    /// has terminated.
+    pub fn spawn_fn<F, T>(&self, spawned_fn: F) -> Result<Receiver<T>, ExecutorError>
+        where F: FnOnce(&Handle) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        self.check_running()?;
+        let (result_tx, result_rx) = channel();
+        self.inner.remote.spawn(move |handle| {
+            let _ = result_tx.send(spawned_fn(handle));
+            Ok::<(), ()>(())
+        });
+        Ok(result_rx)
+    }
+
+    /// Starts a watchdog thread that checks the executor thread every `threshold`, by scheduling
+    /// a heartbeat on it. When a heartbeat is not observed within `threshold`, for example because
+    /// a task is blocking the thread, a warning is logged and `callback` is invoked with how long
+    /// the heartbeat has been waiting; this repeats every `threshold` until the thread recovers.
+    /// The watchdog terminates, without reporting, once the executor is shut down or dropped.
+    pub fn enable_watchdog<F>(&self, threshold: Duration, callback: F) -> Result<(), ExecutorError>
+        where F: Fn(Duration) + Send + 'static
+    {
+        let inner = Arc::downgrade(&self.inner);
+        let remote = self.inner.remote.clone();
+        let running = self.inner.running.clone();
+        thread::Builder::new()
+            .name("executor_watchdog".to_owned())
+            .spawn(move || {
+                let observed = Arc::new(AtomicBool::new(true));
+                let mut sent_at = Instant::now();
+                loop {
+                    // A single heartbeat is pending at any time
+                    if observed.swap(false, Ordering::Relaxed) {
+                        sent_at = Instant::now();
+                        let observed = observed.clone();
+                        remote.spawn(move |_| {
+                            observed.store(true, Ordering::Relaxed);
+                            Ok(())
+                        });
+                    }
+                    thread::sleep(threshold);
+                    if !running.load(Ordering::Relaxed) || inner.strong_count() == 0 {
+                        debug!("Executor terminated, stopping watchdog");
+                        return;
+                    }
+                    if !observed.load(Ordering::Relaxed) {
+                        let late = sent_at.elapsed();
+                        warn!("Executor thread stalled, heartbeat pending for {:?}", late);
+                        callback(late);
+                    }
+                }
+            })
+            .map_err(ExecutorError::ThreadSpawn)?;
+        Ok(())
+    }
+
+    fn check_running(&self) -> Result<(), ExecutorError> {
+        if self.is_running() {
+            Ok(())
+        } else {
+            Err(ExecutorError::Shutdown)
+        }
+    }
+
+    /// Sets the panic policy of the tasks running on this executor, including the ones already
+    /// scheduled, unless they define their own through `TaskHandle::set_panic_policy`.
+    pub fn set_panic_policy(&self, policy: PanicPolicy) {
+        *self.inner.hooks.panic_policy.write().unwrap() = Arc::new(policy);
+    }
+
+    /// Sets the handler invoked, with the name of the task and the panic payload, every time a
+    /// task running on this executor panics, instead of logging the panic. The handler can be
+    /// invoked from any of the executor threads. Tasks whose panic policy is
+    /// `PanicPolicy::Callback` report their panics to the callback as well, after the handler:
+    /// the callback then receives the payload, and the handler its message as a `String`.
+    pub fn set_panic_handler(&self, handler: Box<PanicHandler>) {
+        *self.inner.hooks.panic_handler.write().unwrap() = Some(Arc::from(handler));
+    }
+
+    /// Sets the observer notified of the lifecycle events of all the tasks of this executor,
+    /// including the ones already scheduled. See `ExecutorObserver`.
+    pub fn set_observer(&self, observer: Arc<dyn ExecutorObserver>) {
+        *self.inner.hooks.observer.write().unwrap() = Some(observer);
+    }
+
+    /// Sets the level the lifecycle of the tasks of this executor is logged at: when they're
+    /// scheduled, the start and the end of every execution, the overruns and when they're
+    /// stopped. The lines are logged with the target `TASK_LOG_TARGET::<name>`, so that the tasks
+    /// can be filtered individually. Defaults to debug; `LogLevelFilter::Off` disables them, for
+    /// executors running high-frequency tasks.
+    pub fn set_task_log_level(&self, level: LogLevelFilter) {
+        *self.inner.hooks.task_log_level.write().unwrap() = Some(level);
+    }
+
+    /// Sets the sink the metrics of this executor are reported to, replacing the previous one.
+    /// The duration and the lateness of every execution are recorded as histograms right away,
+    /// while the number of tasks and of queued executions are set as gauges, and the counters of
+    /// the executor and of every task as counters, by a task scheduled on this executor, named
+    /// "metrics-flush", every `every`; the sink is flushed after every report. The tasks are
+    /// labeled by name, or by id if they weren't given one. Returns the handle of the reporting
+    /// task.
+    pub fn set_metrics_sink(&self, sink: Arc<dyn MetricsSink>, every: Duration) -> TaskHandle {
+        *self.inner.hooks.metrics_sink.write().unwrap() = Some(sink.clone());
+        // The task mustn't keep its own executor alive
+        let inner = Arc::downgrade(&self.inner);
+        let flusher = self.schedule_fixed_rate_named("metrics-flush", every, every, move |_| {
+            if let Some(inner) = inner.upgrade() {
+                report_metrics(&inner, &*sink);
+            }
+        });
+        if let Some(previous) = self.inner.metrics_flusher.lock().unwrap().replace(flusher.clone()) {
+            previous.stop();
+        }
+        flusher
+    }
+
+    /// Schedule a function for running at fixed intervals. The executor will try to run the
+    /// function every `interval`, but if one execution takes longer than `interval` it will delay
+    /// all the subsequent calls.
+    pub fn schedule_fixed_interval<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(FixedInterval::new(initial, interval), scheduled_fn)
+    }
+
+    /// Schedules a `Task` at fixed intervals, like `schedule_fixed_interval`. The task is
+    /// registered with its name.
+    pub fn schedule_task(&self, initial: Duration, interval: Duration, task: Box<dyn Task>) -> TaskHandle {
+        self.schedule_task_with(FixedInterval::new(initial, interval), task)
+    }
+
+    /// Schedules a `Task` at fixed rate, like `schedule_fixed_rate`. The task is registered with
+    /// its name.
+    pub fn schedule_task_fixed_rate(&self, initial: Duration, interval: Duration, task: Box<dyn Task>) -> TaskHandle {
+        self.schedule_task_with(FixedRate::new(initial, interval), task)
+    }
+
+    /// Schedules a function at fixed rate, like `schedule_fixed_rate`, passing it a mutable
+    /// reference to `state`, owned by the executor. The final state can be recovered once the
+    /// task is stopped, see `TaskHandle::into_state_receiver`.
+    pub fn schedule_fixed_rate_with_state<T, F>(&self, initial: Duration, interval: Duration, state: T, mut scheduled_fn: F) -> TaskHandle
+        where T: Send + 'static,
+              F: FnMut(&mut T, &Handle) + Send + 'static
+    {
+        let (task_handle, mut state) = OwnedState::new(state);
+        self.schedule_with_handle(FixedRate::new(initial, interval), task_handle, move |handle, _| {
+            scheduled_fn(state.get_mut(), handle)
+        })
+    }
+
+    /// Schedules a function returning a future, which is driven to completion on the executor
+    /// thread, without blocking it: the next execution starts `interval` after the future
+    /// resolves. An error of the future is handled like a panic, according to the panic policy of
+    /// the task. Stopping the task doesn't interrupt a pending future, but no execution starts
+    /// afterwards.
+    pub fn schedule_async_interval<F, Fut>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) -> Fut + Send + 'static,
+              Fut: Future<Item = (), Error = ()> + 'static
+    {
+        self.schedule_async_with(FixedDelay { initial: Some(initial), interval }, scheduled_fn)
+    }
+
+    /// Like `schedule_async_interval`, but at fixed rate: the executions start every `interval`,
+    /// measured from the start of the previous one. An execution is never started before the
+    /// future of the previous one resolved; the delay is then recovered as with
+    /// `schedule_fixed_rate`.
+    pub fn schedule_async_fixed_rate<F, Fut>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) -> Fut + Send + 'static,
+              Fut: Future<Item = (), Error = ()> + 'static
+    {
+        self.schedule_async_with(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    fn schedule_async_with<S, F, Fut>(&self, schedule: S, mut scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Handle) -> Fut + Send + 'static,
+              Fut: Future<Item = (), Error = ()> + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let task_handle_clone = task_handle.clone();
+        self.spawn_loop(schedule, task_handle.clone(), move |handle: &Handle, tick| {
+            let execution = task_handle_clone.begin_execution()?;
+            execution.run_async(tick, |_| scheduled_fn(handle))
+        });
+        task_handle
+    }
+
+    fn schedule_task_with<S: Schedule>(&self, schedule: S, mut task: Box<dyn Task>) -> TaskHandle {
+        let task_handle = task_trait_handle(&*task);
+        self.schedule_with_handle(schedule, task_handle, move |handle, context| task.run(handle, context))
+    }
+
+    /// Schedule a function for running at fixed rate. The executor will try to run the function
+    /// every `interval`, and if a task execution takes longer than `interval`, the wait time
+    /// between task will be reduced to decrease the overall delay.
+    pub fn schedule_fixed_rate<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the durations are strings like "30s" or "1h30m", see the
+    /// `duration` module. Returns an error naming the offending token if they're not valid.
+    pub fn schedule_fixed_rate_str<F>(&self, initial: &str, interval: &str, scheduled_fn: F) -> Result<TaskHandle, ParseError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let schedule = FixedRate::new(parse_duration(initial)?, parse_duration(interval)?);
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Schedules a function as described by a configuration, typically deserialized from a
+    /// configuration file. Returns an error naming the offending field if the configuration is
+    /// not valid; the overlap policy is rejected, as executions on the executor thread never
+    /// overlap.
+    #[cfg(feature = "serde")]
+    pub fn schedule_from_config<F>(&self, config: &ScheduleConfig, mut scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        if config.overlap_policy.is_some() {
+            return Err(config_error("overlap_policy", &"only supported on a ThreadPoolExecutor"));
+        }
+        match config.validate()? {
+            ConfiguredSchedule::Interval { initial, interval, jitter, fixed_interval } =>
+                config.builder(self.task(), initial, interval, jitter, fixed_interval).spawn(scheduled_fn),
+            ConfiguredSchedule::Cron(schedule) => {
+                self.check_running()?;
+                Ok(self.schedule_with_handle(schedule, config.task_handle(), move |handle, _| scheduled_fn(handle)))
+            }
+        }
+    }
+
+    /// Returns a stream yielding a tick at fixed rate, see `IntervalStream`. The ticks are
+    /// produced on the executor thread, while the stream can be consumed anywhere.
+    pub fn interval_stream(&self, initial: Duration, interval: Duration) -> IntervalStream {
+        self.interval_stream_with(FixedRate::new(initial, interval))
+    }
+
+    /// Like `interval_stream`, but returns a stream of futures 0.3. Dropping the stream stops
+    /// the task.
+    #[cfg(feature = "compat")]
+    pub fn interval_stream_compat(&self, initial: Duration, interval: Duration) -> impl Stream03<Item = Instant> {
+        // The interval stream never fails
+        self.interval_stream(initial, interval).compat().filter_map(|tick| futures03::future::ready(tick.ok()))
+    }
+
+    /// Schedules the sending of an item, produced by `make_item`, into `sink` at fixed rate. The
+    /// schedule waits for the sink to accept each item before computing the time of the next
+    /// one, so the task never ticks faster than the sink accepts. An error from the sink stops the
+    /// task with `TerminationReason::SinkFailed`, and is then returned by `TaskHandle::error`.
+    pub fn schedule_sink<S, F>(&self, initial: Duration, interval: Duration, sink: S, mut make_item: F) -> TaskHandle
+        where S: Sink + Send + 'static,
+              S::SinkItem: 'static,
+              S::SinkError: fmt::Debug,
+              F: FnMut() -> S::SinkItem + Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let inner_handle = task_handle.clone();
+        // Taken while an item is being sent
+        let sink = Arc::new(Mutex::new(Some(sink)));
+        self.spawn_loop(FixedRate::new(initial, interval), task_handle.clone(), move |_: &Handle, tick| -> Completion {
+            let task_sink = match sink.lock().unwrap().take() {
+                Some(task_sink) => task_sink,
+                None => {
+                    debug!("Previous item still being sent, skipping this one");
+                    return None;
+                }
+            };
+            let item = inner_handle.begin_execution().and_then(|execution| execution.run(tick, |_| make_item()));
+            let item = match item {
+                Some(item) => item,
+                None => {
+                    *sink.lock().unwrap() = Some(task_sink);
+                    return None;
+                }
+            };
+            let sink = sink.clone();
+            let task_handle = inner_handle.clone();
+            let sent = task_sink.send(item).select2(inner_handle.on_stop());
+            Some(Box::new(sent.then(move |result| {
+                match result {
+                    Ok(Either::A((task_sink, _))) => *sink.lock().unwrap() = Some(task_sink),
+                    Err(Either::A((e, _))) => task_handle.fail(TerminationReason::SinkFailed, format!("{:?}", e)),
+                    // The task was stopped while the sink was full
+                    Ok(Either::B(_)) | Err(Either::B(_)) => (),
+                }
+                Ok(())
+            })))
+        });
+        task_handle
+    }
+
+    /// Returns a `TaskBuilder` to configure a recurring task, and schedule it on the executor
+    /// thread.
+    pub fn task(&self) -> TaskBuilder<'_, CoreExecutor> {
+        TaskBuilder::new(self)
+    }
+
+    /// Schedule a function for running on the wall-clock boundaries of `period`, for example at
+    /// the top of every minute, see `Aligned`.
+    pub fn schedule_aligned<F>(&self, period: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(Aligned::new(period), scheduled_fn)
+    }
+
+    /// Schedules a function at fixed rate, anchored to the instant it was scheduled: the Nth
+    /// execution targets `now + initial + N * interval`, so the ticks don't drift, see
+    /// `Anchored`. The ticks missed when the task falls behind follow `policy`.
+    pub fn schedule_fixed_rate_anchored<F>(&self, initial: Duration, interval: Duration, policy: MissedTickPolicy, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(Anchored::with_policy(initial, interval, policy), scheduled_fn)
+    }
+
+    /// Schedules the stages of the given chain at fixed rate: on every tick, they run one after
+    /// the other on the executor thread.
+    pub fn schedule_fixed_rate_chain(&self, initial: Duration, interval: Duration, chain: ScheduledChain<Handle>) -> TaskHandle {
+        self.schedule_with_chain(FixedRate::new(initial, interval), chain)
+    }
+
+    /// Like `schedule_fixed_rate_chain`, but the chain follows the given schedule.
+    pub fn schedule_with_chain<S>(&self, schedule: S, chain: ScheduledChain<Handle>) -> TaskHandle
+        where S: Schedule
+    {
+        let task_handle = TaskHandle::new();
+        let inner_handle = task_handle.clone();
+        let ScheduledChain { mut stages, policy } = chain;
+        self.spawn_loop(schedule, task_handle.clone(), move |handle: &Handle, tick| -> Completion {
+            for (index, stage) in stages.iter_mut().enumerate() {
+                let execution = inner_handle.begin_execution()?;
+                if execution.run(tick, |_| stage(handle)).is_none() && policy == ChainPolicy::AbortOnFailure {
+                    debug!("Stage {} failed, skipping the rest of the chain", index);
+                    break;
+                }
+            }
+            None
+        });
+        task_handle
+    }
+
+    /// Like `schedule_fixed_interval`, but the function also runs as soon as an item arrives on
+    /// `triggers`, for example a `futures::sync::mpsc::UnboundedReceiver<()>`, whichever comes
+    /// first. The interval then restarts from the triggered run.
+    pub fn schedule_fixed_interval_triggered<T, F>(&self, initial: Duration, interval: Duration, triggers: T, scheduled_fn: F) -> TaskHandle
+        where T: Stream + Send + 'static,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with_triggers(FixedInterval::new(initial, interval), triggers, scheduled_fn)
+    }
+
+    /// Like `schedule_with`, but the function also runs as soon as an item arrives on
+    /// `triggers`, and the schedule restarts from there, as with
+    /// `TaskHandle::trigger_now_and_reschedule`. The items arriving during an execution are
+    /// coalesced into a single follow-up run. Once the stream ends, the task keeps its regular
+    /// schedule.
+    pub fn schedule_with_triggers<S, T, F>(&self, schedule: S, triggers: T, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              T: Stream + Send + 'static,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        let task_handle = self.schedule_with(schedule, scheduled_fn);
+        let forwarder = TriggerForwarder { task_handle: task_handle.clone(), completion: task_handle.on_stop(), triggers };
+        self.inner.remote.spawn(move |_| forwarder);
+        task_handle
+    }
+
+    /// Returns a `Debouncer` running the given function on the executor thread, once `delay` has
+    /// passed since the last trigger.
+    pub fn debouncer<F>(&self, delay: Duration, debounced_fn: F) -> Debouncer
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        self.inner.register(&task_handle);
+        Debouncer {
+            inner: Arc::new(DebounceState {
+                task_handle,
+                remote: self.inner.remote.clone(),
+                delay,
+                generation: AtomicU64::new(0),
+                pending: AtomicBool::new(false),
+                timer: Mutex::new(None),
+                debounced_fn: Mutex::new(Box::new(debounced_fn)),
+            }),
+        }
+    }
+
+    /// Returns a `Throttle` running the given function on the executor thread when triggered, no
+    /// more than once per `window`, following `ThrottleMode::LeadingAndTrailing`.
+    pub fn throttler<F>(&self, window: Duration, throttled_fn: F) -> Throttle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.throttler_with_mode(window, ThrottleMode::LeadingAndTrailing, throttled_fn)
+    }
+
+    /// Like `throttler`, but with the given mode.
+    pub fn throttler_with_mode<F>(&self, window: Duration, mode: ThrottleMode, throttled_fn: F) -> Throttle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        self.inner.register(&task_handle);
+        Throttle {
+            inner: Arc::new(ThrottleState {
+                task_handle,
+                remote: self.inner.remote.clone(),
+                window,
+                mode,
+                pending: AtomicBool::new(false),
+                in_window: AtomicBool::new(false),
+                throttled_fn: Mutex::new(Box::new(throttled_fn)),
+            }),
+        }
+    }
+
+    /// Returns a channel receiving the current `Instant` at fixed rate, for the threads that
+    /// block on `recv` rather than polling futures. The ticks accumulate in the channel if they
+    /// are not received in time. Once the receiver is dropped, the task stops itself.
+    pub fn tick_channel(&self, initial: Duration, interval: Duration) -> (TaskHandle, mpsc::Receiver<Instant>) {
+        let (sender, receiver) = mpsc::channel();
+        let task_handle = self.schedule_fixed_rate_controlled(initial, interval, move |_| {
+            match sender.send(Instant::now()) {
+                Ok(()) => Control::Continue,
+                Err(_) => {
+                    debug!("Tick receiver dropped, stopping task");
+                    Control::Stop
+                }
+            }
+        });
+        (task_handle, receiver)
+    }
+
+    /// Like `interval_stream`, but the ticks follow the given schedule, for example a
+    /// `FixedInterval` to keep `interval` between two ticks regardless of their lateness.
+    pub fn interval_stream_with<S>(&self, schedule: S) -> IntervalStream
+        where S: Schedule
+    {
+        IntervalStream::new(|tick_fn| self.schedule_with(schedule, tick_fn))
+    }
+
+    /// Schedules a function at fixed rate, delivering the value returned by each execution
+    /// through the returned `ResultStream`, which buffers up to `capacity` values. When the
+    /// stream is full, the `overflow` policy applies.
+    pub fn schedule_fixed_rate_results<F, T>(&self, initial: Duration, interval: Duration, capacity: usize,
+                                             overflow: OverflowPolicy, scheduled_fn: F) -> (TaskHandle, ResultStream<T>)
+        where F: FnMut(&Handle) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        self.schedule_with_results(FixedRate::new(initial, interval), capacity, overflow, scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate_results`, but the function follows the given schedule.
+    pub fn schedule_with_results<S, F, T>(&self, schedule: S, capacity: usize, overflow: OverflowPolicy,
+                                          mut scheduled_fn: F) -> (TaskHandle, ResultStream<T>)
+        where S: Schedule,
+              F: FnMut(&Handle) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let results = ResultQueue::new(capacity, overflow);
+        let task_results = results.clone();
+        let inner_handle = task_handle.clone();
+        self.spawn_loop(schedule, task_handle.clone(), move |handle: &Handle, tick| -> Completion {
+            let execution = inner_handle.begin_execution()?;
+            let value = execution.run(tick, |_| scheduled_fn(handle))?;
+            Some(Box::new(PushResult::new(task_results.clone(), &inner_handle, value)))
+        });
+        (task_handle.clone(), ResultStream::new(task_handle, results))
+    }
+
+    /// Like `schedule_fixed_interval`, but returns an error if the executor thread has
+    /// terminated, instead of a handle to a task that will never run.
+    pub fn try_schedule_fixed_interval<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.try_schedule_with(FixedInterval::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but returns an error if the executor thread has terminated,
+    /// instead of a handle to a task that will never run.
+    pub fn try_schedule_fixed_rate<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.try_schedule_with(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_interval`, but the task is given a name, which can be used to stop
+    /// it through `stop_task` and appears in the logs.
+    pub fn schedule_fixed_interval_named<F>(&self, name: &str, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with_named(name, FixedInterval::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the task is given a name, which can be used to stop it
+    /// through `stop_task` and appears in the logs.
+    pub fn schedule_fixed_rate_named<F>(&self, name: &str, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with_named(name, FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_interval`, but the task is also added to the given set.
+    pub fn schedule_fixed_interval_in<F>(&self, task_set: &TaskSet, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let task_handle = self.schedule_fixed_interval(initial, interval, scheduled_fn);
+        task_set.add(task_handle.clone());
+        task_handle
+    }
+
+    /// Like `schedule_fixed_rate`, but the task is also added to the given set.
+    pub fn schedule_fixed_rate_in<F>(&self, task_set: &TaskSet, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let task_handle = self.schedule_fixed_rate(initial, interval, scheduled_fn);
+        task_set.add(task_handle.clone());
+        task_handle
+    }
+
+    /// Like `schedule_fixed_rate`, but `predicate` is evaluated on the executor thread before
+    /// each execution: the first time it returns false, the task stops permanently, without
+    /// running the function. Use `schedule_fixed_rate_when` to skip single executions instead.
+    pub fn schedule_fixed_rate_while<P, F>(&self, initial: Duration, interval: Duration, predicate: P, scheduled_fn: F) -> TaskHandle
+        where P: Fn() -> bool + Send + 'static,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_gated(FixedRate::new(initial, interval), predicate, true, scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but `predicate` is evaluated on the executor thread before
+    /// each execution: when it returns false, that execution is skipped, as if the task was
+    /// paused, and the task keeps running. Use `schedule_fixed_rate_while` to stop the task
+    /// instead.
+    pub fn schedule_fixed_rate_when<P, F>(&self, initial: Duration, interval: Duration, predicate: P, scheduled_fn: F) -> TaskHandle
+        where P: Fn() -> bool + Send + 'static,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_gated(FixedRate::new(initial, interval), predicate, false, scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_interval`, but the function decides whether the task should keep
+    /// running, by returning a `Control` value.
+    pub fn schedule_fixed_interval_controlled<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) -> Control + Send + 'static
+    {
+        self.schedule_with_controlled(FixedInterval::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the function decides whether the task should keep running,
+    /// by returning a `Control` value.
+    pub fn schedule_fixed_rate_controlled<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) -> Control + Send + 'static
+    {
+        self.schedule_with_controlled(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_with`, but the function decides whether the task should keep running, by
+    /// returning a `Control` value. The task will also stop once the schedule returns `None`.
+    pub fn schedule_with_controlled<S, F>(&self, schedule: S, mut scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Handle) -> Control + Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let task_handle_clone = task_handle.clone();
+        self.schedule_with_handle(schedule, task_handle, move |handle, _| {
+            if scheduled_fn(handle) == Control::Stop {
+                debug!("Task requested to stop");
+                task_handle_clone.stop();
+            }
+        })
+    }
+
+    /// Like `schedule_fixed_rate`, but the function can fail by returning an error, which is
+    /// logged. An error counts as a failure towards `TaskHandle::set_max_consecutive_failures`,
+    /// as a panic does.
+    pub fn schedule_fixed_rate_fallible<F, E>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) -> Result<(), E> + Send + 'static,
+              E: fmt::Display
+    {
+        self.schedule_with_fallible(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_with`, but the function can fail by returning an error, see
+    /// `schedule_fixed_rate_fallible`.
+    pub fn schedule_with_fallible<S, F, E>(&self, schedule: S, mut scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Handle) -> Result<(), E> + Send + 'static,
+              E: fmt::Display
+    {
+        self.schedule_with_handle(schedule, TaskHandle::new(), move |handle, context| {
+            if let Err(e) = scheduled_fn(handle) {
+                context.record_error(&e);
+            }
+        })
+    }
+
+    /// Like `schedule_fixed_interval`, but the task will stop once `deadline` is passed: the
+    /// deadline is checked on the executor thread before arming the timer for each execution.
+    /// If the deadline is already passed, the function won't run at all.
+    pub fn schedule_fixed_interval_until<F>(&self, initial: Duration, interval: Duration, deadline: Instant, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(Until::new(FixedInterval::new(initial, interval), deadline), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the task will stop once `deadline` is passed: the deadline
+    /// is checked on the executor thread before arming the timer for each execution. If the
+    /// deadline is already passed, the function won't run at all.
+    pub fn schedule_fixed_rate_until<F>(&self, initial: Duration, interval: Duration, deadline: Instant, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(Until::new(FixedRate::new(initial, interval), deadline), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_interval`, but the function also receives a `TaskContext` describing
+    /// the current execution.
+    pub fn schedule_fixed_interval_ctx<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle, &TaskContext) + Send + 'static
+    {
+        self.schedule_with_ctx(FixedInterval::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the function also receives a `TaskContext` describing the
+    /// current execution, including the delay accumulated by the schedule.
+    pub fn schedule_fixed_rate_ctx<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle, &TaskContext) + Send + 'static
+    {
+        self.schedule_with_ctx(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the task will stop after `max_runs` executions, including
+    /// the ones requested through `TaskHandle::trigger_now`.
+    pub fn schedule_fixed_rate_limited<F>(&self, initial: Duration, interval: Duration, max_runs: u64, mut scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let task_handle = TaskHandle::with_max_runs(max_runs);
+        self.schedule_with_handle(FixedRate::new(initial, interval), task_handle, move |handle, _| scheduled_fn(handle))
+    }
+
+    /// Like `schedule_fixed_rate`, but every execution will happen at a random time up to
+    /// `jitter` before or after its nominal time. This is useful to avoid many processes running
+    /// the same task in lockstep. The long-run rate of the task is not affected by the jitter.
+    pub fn schedule_fixed_rate_jittered<F>(&self, initial: Duration, interval: Duration, jitter: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(Jittered::new(FixedRate::new(initial, interval), jitter), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the first execution will happen after a random delay,
+    /// uniformly distributed between zero and `interval`. The delay is chosen once, when the task
+    /// is scheduled: scheduling many similar tasks with this method will spread their executions
+    /// evenly over the period.
+    pub fn schedule_fixed_rate_splayed<F>(&self, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(FixedRate::splayed(interval), scheduled_fn)
+    }
+
+    /// Schedule a function for running according to the given `Schedule`. The task will stop
+    /// once the schedule returns `None`.
+    pub fn schedule_with<S, F>(&self, schedule: S, mut scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with_ctx(schedule, move |handle, _| scheduled_fn(handle))
+    }
+
+    /// Like `schedule_with`, but the task is given a name, which can be used to stop it through
+    /// `stop_task` and appears in the logs.
+    pub fn schedule_with_named<S, F>(&self, name: &str, schedule: S, mut scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with_handle(schedule, TaskHandle::named(name), move |handle, _| scheduled_fn(handle))
+    }
+
+    /// Like `schedule_with`, but returns an error if the executor thread has terminated, instead
+    /// of a handle to a task that will never run.
+    pub fn try_schedule_with<S, F>(&self, schedule: S, scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where S: Schedule,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        self.check_running()?;
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_with`, but the function also receives a `TaskContext` describing the
+    /// current execution.
+    pub fn schedule_with_ctx<S, F>(&self, schedule: S, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Handle, &TaskContext) + Send + 'static
+    {
+        self.schedule_with_handle(schedule, TaskHandle::new(), scheduled_fn)
+    }
+
+    fn schedule_with_handle<S, F>(&self, schedule: S, task_handle: TaskHandle, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Handle, &TaskContext) + Send + 'static
+    {
+        let run_fn = CoreExecutor::run_fn(task_handle.clone(), scheduled_fn);
+        self.spawn_loop(schedule, task_handle.clone(), run_fn);
+        task_handle
+    }
+
+    fn schedule_gated<S, P, F>(&self, schedule: S, predicate: P, stop: bool, mut scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              P: Fn() -> bool + Send + 'static,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let run_fn = CoreExecutor::run_fn(task_handle.clone(), move |handle, _: &TaskContext| scheduled_fn(handle));
+        self.spawn_loop(schedule, task_handle.clone(), gated(predicate, stop, task_handle.clone(), run_fn));
+        task_handle
+    }
+
+    /// Wraps the function of a task, so that every execution goes through
+    /// `TaskHandle::begin_execution`.
+    fn run_fn<F>(task_handle: TaskHandle, mut scheduled_fn: F) -> impl FnMut(&Handle, Tick) -> Completion + Send + 'static
+        where F: FnMut(&Handle, &TaskContext) + Send + 'static
+    {
+        move |handle, tick| {
+            if let Some(execution) = task_handle.begin_execution() {
+                execution.run(tick, |context| scheduled_fn(handle, context));
+            }
+            None
+        }
+    }
+
+    /// Starts the scheduling loop of a task. `scheduled_fn` is responsible for running the
+    /// execution through `TaskHandle::begin_execution`.
+    fn spawn_loop<S, F>(&self, mut schedule: S, task_handle: TaskHandle, scheduled_fn: F)
+        where S: Schedule,
+              F: FnMut(&Handle, Tick) -> Completion + Send + 'static
+    {
+        let min_interval = self.inner.min_interval;
+        if let Some(interval) = schedule.interval().filter(|&interval| interval < min_interval) {
+            warn!("Interval {:?} below the minimum, raised to {:?}", interval, min_interval);
+            schedule.set_interval(min_interval);
+        }
+        if let Some(interval) = schedule.interval().filter(|&interval| interval > MAX_INTERVAL) {
+            warn!("Interval {:?} above the maximum, lowered to {:?}", interval, MAX_INTERVAL);
+            schedule.set_interval(MAX_INTERVAL);
+        }
+        {
+            let mut executions = task_handle.inner.executions();
+            executions.interval = schedule.interval();
+            executions.kind = schedule.kind();
+        }
+        self.inner.register(&task_handle);
+        let task_loop = Arc::new(Mutex::new(TaskLoop { schedule, scheduled_fn, min_interval, generation: 0 }));
+        let task_loop_clone = task_loop.clone();
+        let remote = self.inner.remote.clone();
+        let trigger = move |task_handle: &TaskHandle, reschedule: bool| {
+            let task_loop = task_loop_clone.clone();
+            let task_handle = task_handle.clone();
+            remote.spawn(move |handle| {
+                trigger_run(task_loop, reschedule, handle, task_handle);
+                Ok::<(), ()>(())
+            });
+        };
+        task_handle.inner.executions().trigger = Some(Arc::new(trigger));
+        self.inner.remote.spawn(move |handle| {
+            schedule_loop(task_loop, handle, task_handle);
+            Ok::<(), ()>(())
+        });
+    }
+
+    /// Registers a one-shot task. The delay is computed on the executor thread. `scheduled_fn`
+    /// is responsible for running the execution through `TaskHandle::begin_execution` and for
+    /// stopping the task afterwards.
+    fn spawn_once<D, F>(&self, delay: D, task_handle: TaskHandle, scheduled_fn: F)
+        where D: FnOnce() -> Duration + Send + 'static,
+              F: FnOnce(&Handle, Tick) + Send + 'static
+    {
+        task_handle.inner.executions().kind = ScheduleKind::Once;
+        self.inner.register(&task_handle);
+        self.inner.remote.spawn(move |handle| {
+            run_once(scheduled_fn, delay(), handle, task_handle);
+            Ok::<(), ()>(())
+        });
+    }
+
+    /// Schedule a function for running after each of the delays produced by the given iterator:
+    /// the first execution will happen after the first delay, and each following execution will
+    /// wait for the next delay after the previous one completed. Once the iterator is exhausted,
+    /// the task will stop.
+    pub fn schedule_iter<I, F>(&self, delays: I, scheduled_fn: F) -> TaskHandle
+        where I: Iterator<Item = Duration> + Send + 'static,
+              F: FnMut(&Handle) + Send + 'static
+    {
+        self.schedule_with(DelaySequence::new(delays), scheduled_fn)
+    }
+
+    /// Schedule a function that decides when it should run next. The first execution will happen
+    /// after `initial`, and each execution returns how long to wait before the following one, or
+    /// `None` to stop the task. A zero wait still goes through the event loop, so other tasks
+    /// will get a chance to run.
+    pub fn schedule_dynamic<F>(&self, initial: Duration, mut scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) -> Option<Duration> + Send + 'static
+    {
+        let next = Arc::new(Mutex::new(Some(initial)));
+        let next_clone = Arc::clone(&next);
+        let schedule = DynamicDelay { next };
+        self.schedule_with(schedule, move |handle| {
+            *next_clone.lock().unwrap() = scheduled_fn(handle);
+        })
+    }
+
+    /// Schedule a function for running with an exponential backoff. The function returns whether
+    /// the execution was successful: every failure makes the following wait longer, while a
+    /// success resets the backoff to its initial value.
+    pub fn schedule_backoff<F>(&self, backoff: Backoff, mut scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Handle) -> bool + Send + 'static
+    {
+        let succeeded = Arc::new(AtomicBool::new(false));
+        let succeeded_clone = Arc::clone(&succeeded);
+        let schedule = ResettableBackoff { backoff, succeeded };
+        self.schedule_with(schedule, move |handle| {
+            if scheduled_fn(handle) {
+                succeeded_clone.store(true, Ordering::Relaxed);
+            }
+        })
+    }
+
+    /// Schedule a function for running at the wall-clock times described by the given cron
+    /// expression (see the `schedule` module for the supported syntax). Times are in UTC. The
+    /// expression is validated immediately, and an error is returned if it's invalid.
+    pub fn schedule_cron<F>(&self, expression: &str, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let schedule = CronSchedule::parse(expression)?;
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Schedule a function for running every day at the given UTC time; see `schedule_daily` and
+    /// `schedule_daily_in_tz` for a local time. After each execution the next run is computed
+    /// again from the system clock, so the schedule stays anchored to the time of day even if the
+    /// clock is adjusted or the process is suspended. Returns an error if the time is not valid.
+    pub fn schedule_daily_utc<F>(&self, hour: u32, minute: u32, second: u32, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let schedule = CronSchedule::daily(hour, minute, second)?;
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_daily_utc`, but the time is the local time of the system, whose timezone is
+    /// looked up again before every wait. See `CronSchedule::in_timezone` for the behavior around
+    /// DST transitions.
+    #[cfg(feature = "chrono")]
+    pub fn schedule_daily<F>(&self, hour: u32, minute: u32, second: u32, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let schedule = CronSchedule::daily(hour, minute, second)?.in_local_timezone();
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_cron`, but the expression is matched against the local time of the given
+    /// timezone. See `CronSchedule::in_timezone` for the behavior around DST transitions.
+    #[cfg(feature = "chrono")]
+    pub fn schedule_cron_in_tz<F>(&self, expression: &str, timezone: Tz, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let schedule = CronSchedule::parse(expression)?.in_timezone(timezone);
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_daily_utc`, but the time is the local time of the given timezone. See
+    /// `CronSchedule::in_timezone` for the behavior around DST transitions.
+    #[cfg(feature = "chrono")]
+    pub fn schedule_daily_in_tz<F>(&self, timezone: Tz, hour: u32, minute: u32, second: u32, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: FnMut(&Handle) + Send + 'static
+    {
+        let schedule = CronSchedule::daily(hour, minute, second)?.in_timezone(timezone);
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Schedule a function for running once, after `delay`. If the returned handle is stopped
+    /// before the delay expires, the function will be dropped without being executed. The handle
+    /// is marked as stopped once the function has run.
+    pub fn schedule_once<F>(&self, delay: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnOnce(&Handle) + Send + 'static
+    {
+        self.schedule_once_after(move || delay, scheduled_fn)
+    }
+
+    fn schedule_once_after<D, F>(&self, delay: D, scheduled_fn: F) -> TaskHandle
+        where D: FnOnce() -> Duration + Send + 'static,
+              F: FnOnce(&Handle) + Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let task_handle_clone = task_handle.clone();
+        self.spawn_once(delay, task_handle.clone(), move |handle, tick| {
+            if let Some(execution) = task_handle_clone.begin_execution() {
+                execution.run(tick, |_| scheduled_fn(handle));
+            }
+            task_handle_clone.finish(TerminationReason::Completed);
+        });
+        task_handle
+    }
+
+    /// Like `schedule_once`, but the function can be taken back with `CancelableOnce::cancel` as
+    /// long as it didn't run, instead of being dropped on the executor thread.
+    pub fn schedule_once_cancelable<F>(&self, delay: Duration, scheduled_fn: F) -> CancelableOnce<F>
+        where F: FnOnce(&Handle) + Send + 'static
+    {
+        let slot = Arc::new(Mutex::new(Some(scheduled_fn)));
+        let slot_clone = Arc::clone(&slot);
+        let task_handle = self.schedule_once(delay, move |handle| {
+            let scheduled_fn = slot_clone.lock().unwrap().take();
+            if let Some(scheduled_fn) = scheduled_fn {
+                scheduled_fn(handle);
+            }
+        });
+        CancelableOnce::new(task_handle, slot)
+    }
+
+    /// Schedule a function for running once, after `delay`, and returns a `Receiver` that will
+    /// resolve to the value returned by the function. If the task is stopped before running,
+    /// the sender will be dropped and the receiver will resolve to `Canceled`.
+    pub fn schedule_once_result<F, T>(&self, delay: Duration, scheduled_fn: F) -> (TaskHandle, Receiver<T>)
+        where F: FnOnce(&Handle) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        let (result_tx, result_rx) = channel();
+        let task_handle = self.schedule_once(delay, move |handle| {
+            let _ = result_tx.send(scheduled_fn(handle));
+        });
+        (task_handle, result_rx)
+    }
+
+    /// Like `schedule_once_result`, but the result is a `std::future::Future`, which can be
+    /// awaited.
+    #[cfg(feature = "compat")]
+    pub fn schedule_once_result_compat<F, T>(&self, delay: Duration, scheduled_fn: F) -> (TaskHandle, impl StdFuture<Output = Result<T, Canceled>>)
+        where F: FnOnce(&Handle) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        let (task_handle, result_rx) = self.schedule_once_result(delay, scheduled_fn);
+        (task_handle, result_rx.compat())
+    }
+
+    /// Schedule a job that is retried, according to `policy`, until it succeeds: the first attempt
+    /// runs right away, and the returned `Receiver` resolves to the result of the first successful
+    /// attempt, or to the error of the last one. If the task is stopped before that, pending
+    /// retries are aborted and the receiver resolves to `Canceled`, which also happens if the last
+    /// attempt panics.
+    pub fn schedule_retry<F, T, E>(&self, policy: RetryPolicy, mut scheduled_fn: F) -> (TaskHandle, Receiver<Result<T, E>>)
+        where F: FnMut(&Handle) -> Result<T, E> + Send + 'static,
+              T: Send + 'static,
+              E: Send + 'static
+    {
+        let (result_tx, result_rx) = channel();
+        let result_tx = Arc::new(Mutex::new(Some(result_tx)));
+        let result_tx_clone = result_tx.clone();
+        let max_attempts = u64::from(policy.max_attempts());
+        let task_handle = TaskHandle::new();
+        let task_handle_clone = task_handle.clone();
+        let on_stop = task_handle.on_stop();
+        self.schedule_with_handle(policy, task_handle.clone(), move |handle, context| {
+            let result = scheduled_fn(handle);
+            let result_failed = result.is_err();
+            if result.is_ok() || context.run() + 1 >= max_attempts {
+                if let Some(result_tx) = result_tx_clone.lock().unwrap().take() {
+                    let _ = result_tx.send(result);
+                }
+                task_handle_clone.finish(TerminationReason::Completed);
+            } else {
+                debug!("Attempt {} failed, retrying", context.run() + 1);
+            }
+            if result_failed {
+                context.mark_failed();
+            }
+        });
+        // Drop the sender as soon as the task is stopped, without waiting for the pending retry
+        self.inner.remote.spawn(move |_| {
+            on_stop.then(move |_| {
+                result_tx.lock().unwrap().take();
+                Ok::<(), ()>(())
+            })
+        });
+        (task_handle, result_rx)
+    }
+
+    /// Schedule a function for running once, at the given instant. The remaining wait is
+    /// computed on the executor thread when the task is registered; if `when` is already in the
+    /// past, the function will run as soon as possible. Other than that, it behaves like
+    /// `schedule_once`.
+    pub fn schedule_once_at<F>(&self, when: Instant, scheduled_fn: F) -> TaskHandle
+        where F: FnOnce(&Handle) + Send + 'static
+    {
+        self.schedule_once_after(move || when.saturating_duration_since(Instant::now()), scheduled_fn)
+    }
+}
+
+
+/// Runs the futures on the executor thread. Futures can't be spawned once the executor has
+/// terminated.
+impl<F> Executor<F> for CoreExecutor
+    where F: Future<Item = (), Error = ()> + Send + 'static
+{
+    fn execute(&self, future: F) -> Result<(), ExecuteError<F>> {
+        if !self.is_running() {
+            return Err(ExecuteError::new(ExecuteErrorKind::Shutdown, future));
+        }
+        self.inner.remote.execute(future)
+    }
+}
+
+/// A `ThreadPoolExecutor` will use one thread for the task scheduling and a thread pool for
+/// task execution, allowing multiple tasks to run in parallel.
+#[derive(Clone)]
+pub struct ThreadPoolExecutor {
+    executor: CoreExecutor,
+    pool: SharedPool,
+    affinity_pools: Arc<AffinityPools>,
+}
+
+impl fmt::Debug for ThreadPoolExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThreadPoolExecutor")
+            .field("pool_size", &self.pool_size())
+            .field("queued", &self.executor.inner.hooks.queued.load(Ordering::Relaxed))
+            .field("in_flight", &self.in_flight())
+            .field("executor", &self.executor)
+            .finish()
+    }
+}
+
+impl ThreadPoolExecutor {
+    /// Creates a new `ThreadPoolExecutor` with the specified number of threads. Threads will
+    /// be named "pool_thread_0", "pool_thread_1" and so on.
+    pub fn new(threads: usize) -> Result<ThreadPoolExecutor, ExecutorError> {
+        ThreadPoolExecutor::with_prefix(threads, "pool_thread_")
+    }
+
+    /// Creates a new `ThreadPoolExecutor` with one thread for each logical CPU. The number of
+    /// threads is returned by `pool_size`.
+    pub fn new_auto() -> Result<ThreadPoolExecutor, ExecutorError> {
+        ExecutorBuilder::new().build_pool_auto(1.0, usize::MAX)
+    }
+
+    /// Like `new_auto`, with the given prefix for the thread names.
+    pub fn with_prefix_auto(prefix: &str) -> Result<ThreadPoolExecutor, ExecutorError> {
+        ExecutorBuilder::new().name(prefix).build_pool_auto(1.0, usize::MAX)
+    }
+
+    /// Like `new_auto`, but with `factor` threads for each logical CPU, up to `max`, see
+    /// `ExecutorBuilder::build_pool_auto`.
+    pub fn new_auto_scaled(factor: f64, max: usize) -> Result<ThreadPoolExecutor, ExecutorError> {
+        ExecutorBuilder::new().build_pool_auto(factor, max)
+    }
+
+    /// Creates a new `ThreadPoolExecutor` with the specified number of threads and prefix for
+    /// the thread names. See `ExecutorBuilder` for more options.
+    pub fn with_prefix(threads: usize, prefix: &str) -> Result<ThreadPoolExecutor, ExecutorError> {
+        ExecutorBuilder::new().name(prefix).build_pool(threads)
+    }
+
+    /// Creates a new `ThreadPoolExecutor` with the specified number of threads, prefix and
+    /// using the given `CoreExecutor` for scheduling.
+    pub fn with_executor(threads: usize, prefix: &str, executor: CoreExecutor) -> ThreadPoolExecutor {
+        ThreadPoolExecutor::with_config(threads, prefix, executor, &ThreadConfig::default())
+    }
+
+    fn with_config(threads: usize, prefix: &str, executor: CoreExecutor, config: &ThreadConfig) -> ThreadPoolExecutor {
+        let pool = config.pool_builder(threads, prefix).create();
+        let mut pool_executor = ThreadPoolExecutor::with_pool(pool, executor);
+        pool_executor.pool = SharedPool::new(pool_executor.pool.current(), threads);
+        let affinity_pools = AffinityPools { threads, prefix: prefix.to_owned(), config: config.clone(), pools: Mutex::new(Vec::new()) };
+        pool_executor.affinity_pools = Arc::new(affinity_pools);
+        pool_executor
+    }
+
+    /// Creates a new `ThreadPoolExecutor` submitting the executions to the given pool, and using
+    /// the given `CoreExecutor` for scheduling. The pool can be shared with the rest of the
+    /// application. Since the size of the pool is unknown, the tasks with an affinity (see
+    /// `TaskHandle::set_affinity`) run on any of its threads.
+    pub fn with_pool(pool: CpuPool, executor: CoreExecutor) -> ThreadPoolExecutor {
+        let affinity_pools = AffinityPools { threads: 0, prefix: String::new(), config: ThreadConfig::default(), pools: Mutex::new(Vec::new()) };
+        ThreadPoolExecutor { pool: SharedPool::new(pool, 0), executor, affinity_pools: Arc::new(affinity_pools) }
+    }
+
+    /// Replaces the pool with a new one of the given number of threads (at least one), shared
+    /// by all the clones of the executor. The executions already submitted to the previous pool
+    /// complete there, and its threads terminate once they're done. The threads dedicated to
+    /// the tasks with an affinity are not affected.
+    pub fn resize(&self, threads: usize) {
+        let threads = threads.max(1);
+        debug!("Resizing the pool to {} threads", threads);
+        let pool = self.affinity_pools.config.pool_builder(threads, &self.affinity_pools.prefix).create();
+        self.pool.replace(pool, threads);
+    }
+
+    /// Returns the number of threads of the pool, or 0 if the executor was created with
+    /// `with_pool`, and not resized since.
+    pub fn pool_size(&self) -> usize {
+        self.pool.size()
+    }
+
+    /// Schedules the given function to be executed every `interval`, measured from the end of the
+    /// previous execution: the next execution is scheduled only once the previous one completed
+    /// on the pool, so that executions never overlap. If the task has an execution timeout, the
+    /// next execution is scheduled when it expires instead. The function will be executed on one
+    /// of the threads in the thread pool. The task follows `OverlapPolicy::Queue`.
+    pub fn schedule_fixed_interval<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let task_handle = TaskHandle::new();
+        task_handle.set_overlap_policy(OverlapPolicy::Queue);
+        self.schedule_with_handle(FixedDelay { initial: Some(initial), interval }, task_handle, move |remote, _| scheduled_fn(remote))
+    }
+
+    /// Schedules a `Task` on the pool at fixed intervals, like `schedule_fixed_interval`. The
+    /// task is registered with its name; its executions are serialized, as with
+    /// `schedule_with_mut`.
+    pub fn schedule_task(&self, initial: Duration, interval: Duration, task: Box<dyn Task<Remote>>) -> TaskHandle {
+        self.schedule_task_with(FixedDelay { initial: Some(initial), interval }, task)
+    }
+
+    /// Schedules a `Task` on the pool at fixed rate, like `schedule_fixed_rate`. The task is
+    /// registered with its name; its executions are serialized, as with `schedule_with_mut`.
+    pub fn schedule_task_fixed_rate(&self, initial: Duration, interval: Duration, task: Box<dyn Task<Remote>>) -> TaskHandle {
+        self.schedule_task_with(FixedRate::new(initial, interval), task)
+    }
+
+    /// Schedules a function on the pool at fixed rate, passing it a mutable reference to `state`,
+    /// see `CoreExecutor::schedule_fixed_rate_with_state`. The executions are serialized, as with
+    /// `schedule_with_mut`, so they never access the state concurrently.
+    pub fn schedule_fixed_rate_with_state<T, F>(&self, initial: Duration, interval: Duration, state: T, mut scheduled_fn: F) -> TaskHandle
+        where T: Send + 'static,
+              F: FnMut(&mut T, &Remote) + Send + 'static
+    {
+        let (task_handle, mut state) = OwnedState::new(state);
+        self.schedule_mut_with_handle(FixedRate::new(initial, interval), task_handle, move |remote, _| {
+            scheduled_fn(state.get_mut(), remote)
+        })
+    }
+
+    fn schedule_task_with<S: Schedule>(&self, schedule: S, mut task: Box<dyn Task<Remote>>) -> TaskHandle {
+        let task_handle = task_trait_handle(&*task);
+        self.schedule_mut_with_handle(schedule, task_handle, move |remote, context| task.run(remote, context))
+    }
+
+    /// Schedules the given function to be executed every `interval`. The function will be
+    /// scheduled on one of the threads in the thread pool, even if the previous execution is
+    /// still running, unless the overlap policy of the task says otherwise. With
+    /// `OverlapPolicy::Queue`, the next execution is scheduled once the previous one completed on
+    /// the pool: if it took longer than `interval`, the following waits are reduced to recover
+    /// the delay, as with `CoreExecutor::schedule_fixed_rate`.
+    pub fn schedule_fixed_rate<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the durations are strings like "30s" or "1h30m", see
+    /// `CoreExecutor::schedule_fixed_rate_str`.
+    pub fn schedule_fixed_rate_str<F>(&self, initial: &str, interval: &str, scheduled_fn: F) -> Result<TaskHandle, ParseError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let schedule = FixedRate::new(parse_duration(initial)?, parse_duration(interval)?);
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Schedules a function on the pool as described by a configuration, see
+    /// `CoreExecutor::schedule_from_config`. The overlap policy of the configuration is applied.
+    #[cfg(feature = "serde")]
+    pub fn schedule_from_config<F>(&self, config: &ScheduleConfig, scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        match config.validate()? {
+            ConfiguredSchedule::Interval { initial, interval, jitter, fixed_interval } => {
+                let builder = config.builder(self.task(), initial, interval, jitter, fixed_interval);
+                match config.overlap_policy {
+                    Some(policy) => builder.overlap_policy(policy).spawn(scheduled_fn),
+                    None => builder.spawn(scheduled_fn),
+                }
+            }
+            ConfiguredSchedule::Cron(schedule) => {
+                self.executor.check_running()?;
+                Ok(self.schedule_with_handle(schedule, config.task_handle(), move |remote, _| scheduled_fn(remote)))
+            }
+        }
+    }
+
+    /// Like `schedule_fixed_rate`, but the function can mutate its state: executions are
+    /// serialized, following `OverlapPolicy::Queue`, so they never run concurrently.
+    pub fn schedule_fixed_rate_mut<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnMut(&Remote) + Send + 'static
+    {
+        self.schedule_with_mut(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_with`, but the function can mutate its state: the task follows
+    /// `OverlapPolicy::Queue`, so executions never run concurrently. Should the overlap policy be
+    /// changed, the executions would still be serialized, blocking the pool threads meanwhile.
+    pub fn schedule_with_mut<S, F>(&self, schedule: S, mut scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Remote) + Send + 'static
+    {
+        self.schedule_mut_with_handle(schedule, TaskHandle::new(), move |remote, _| scheduled_fn(remote))
+    }
+
+    fn schedule_mut_with_handle<S, F>(&self, schedule: S, task_handle: TaskHandle, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: FnMut(&Remote, &TaskContext) + Send + 'static
+    {
+        task_handle.set_overlap_policy(OverlapPolicy::Queue);
+        let scheduled_fn = Mutex::new(scheduled_fn);
+        self.schedule_with_handle(schedule, task_handle, move |remote, context| {
+            // A panic in a previous execution doesn't invalidate the function
+            let mut scheduled_fn = scheduled_fn.lock().unwrap_or_else(|e| e.into_inner());
+            (*scheduled_fn)(remote, context)
+        })
+    }
+
+    /// Schedules the given function to be executed on the wall-clock boundaries of `period`, see
+    /// `CoreExecutor::schedule_aligned`. The function will be executed on one of the threads in
+    /// the thread pool.
+    pub fn schedule_aligned<F>(&self, period: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with(Aligned::new(period), scheduled_fn)
+    }
+
+    /// Schedules a function at fixed rate, anchored to the instant it was scheduled, see
+    /// `CoreExecutor::schedule_fixed_rate_anchored`.
+    pub fn schedule_fixed_rate_anchored<F>(&self, initial: Duration, interval: Duration, policy: MissedTickPolicy, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with(Anchored::with_policy(initial, interval, policy), scheduled_fn)
+    }
+
+    /// Returns a `TaskBuilder` to configure a recurring task, and schedule it on the pool.
+    pub fn task(&self) -> TaskBuilder<'_, ThreadPoolExecutor> {
+        TaskBuilder::new(self)
+    }
+
+    /// Schedules the stages of the given chain at fixed rate, see
+    /// `CoreExecutor::schedule_fixed_rate_chain`. Every stage runs on the pool, once the previous
+    /// one completed; the next tick waits for the whole chain to complete.
+    pub fn schedule_fixed_rate_chain(&self, initial: Duration, interval: Duration, chain: ScheduledChain<Remote>) -> TaskHandle {
+        self.schedule_with_chain(FixedRate::new(initial, interval), chain)
+    }
+
+    /// Like `schedule_fixed_rate_chain`, but the chain follows the given schedule.
+    pub fn schedule_with_chain<S>(&self, schedule: S, chain: ScheduledChain<Remote>) -> TaskHandle
+        where S: Schedule
+    {
+        let task_handle = TaskHandle::new();
+        task_handle.set_overlap_policy(OverlapPolicy::Queue);
+        let chain = Arc::new(PoolChain {
+            stages: chain.stages.into_iter().map(Mutex::new).collect(),
+            policy: chain.policy,
+            spawner: self.spawner(),
+            task_handle: task_handle.clone(),
+        });
+        self.executor.spawn_loop(schedule, task_handle.clone(), move |handle: &Handle, tick| -> Completion {
+            Some(PoolChain::run_from(chain.clone(), 0, handle.clone(), tick))
+        });
+        task_handle
+    }
+
+    /// Schedules a function at fixed rate on the pool, delivering the value returned by each
+    /// execution through the returned `ResultStream`, see
+    /// `CoreExecutor::schedule_fixed_rate_results`. The executions are serialized, the schedule
+    /// waiting for each value to be added to the stream.
+    pub fn schedule_fixed_rate_results<F, T>(&self, initial: Duration, interval: Duration, capacity: usize,
+                                             overflow: OverflowPolicy, scheduled_fn: F) -> (TaskHandle, ResultStream<T>)
+        where F: FnMut(&Remote) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        self.schedule_with_results(FixedRate::new(initial, interval), capacity, overflow, scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate_results`, but the function follows the given schedule.
+    pub fn schedule_with_results<S, F, T>(&self, schedule: S, capacity: usize, overflow: OverflowPolicy,
+                                          scheduled_fn: F) -> (TaskHandle, ResultStream<T>)
+        where S: Schedule,
+              F: FnMut(&Remote) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        let task_handle = TaskHandle::new();
+        task_handle.set_overlap_policy(OverlapPolicy::Queue);
+        let results = ResultQueue::new(capacity, overflow);
+        let task_results = results.clone();
+        let inner_handle = task_handle.clone();
+        let spawner = self.spawner();
+        let scheduled_fn = Arc::new(Mutex::new(scheduled_fn));
+        self.executor.spawn_loop(schedule, task_handle.clone(), move |handle: &Handle, tick| -> Completion {
+            let execution = inner_handle.begin_execution()?;
+            let value = Arc::new(Mutex::new(None));
+            let job_value = value.clone();
+            let job_fn = scheduled_fn.clone();
+            let remote = handle.remote().clone();
+            let job = move || {
+                let result = execution.run(tick, |_| {
+                    // A panic in a previous execution doesn't invalidate the function
+                    let mut scheduled_fn = job_fn.lock().unwrap_or_else(|e| e.into_inner());
+                    (*scheduled_fn)(&remote)
+                });
+                *job_value.lock().unwrap() = result;
+            };
+            let results = task_results.clone();
+            let task_handle = inner_handle.clone();
+            Some(Box::new(spawner.submit(handle, &inner_handle, job).and_then(move |()| {
+                match value.lock().unwrap().take() {
+                    Some(value) => Either::A(PushResult::new(results, &task_handle, value)),
+                    None => Either::B(future::ok(())),
+                }
+            })))
+        });
+        (task_handle.clone(), ResultStream::new(task_handle, results))
+    }
+
+    /// Like `schedule_fixed_rate`, but the task is also added to the given set.
+    pub fn schedule_fixed_rate_in<F>(&self, task_set: &TaskSet, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let task_handle = self.schedule_fixed_rate(initial, interval, scheduled_fn);
+        task_set.add(task_handle.clone());
+        task_handle
+    }
+
+    /// Like `schedule_fixed_rate`, but the task is given a name, which can be used to stop it
+    /// through `stop_task` and appears in the logs.
+    pub fn schedule_fixed_rate_named<F>(&self, name: &str, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with_named(name, FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but returns an error if the scheduling thread has terminated,
+    /// instead of a handle to a task that will never run.
+    pub fn try_schedule_fixed_rate<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.try_schedule_with(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but `predicate` is evaluated by the scheduling thread before
+    /// each execution: the first time it returns false, the task stops permanently, without
+    /// running the function. Use `schedule_fixed_rate_when` to skip single executions instead.
+    pub fn schedule_fixed_rate_while<P, F>(&self, initial: Duration, interval: Duration, predicate: P, scheduled_fn: F) -> TaskHandle
+        where P: Fn() -> bool + Send + 'static,
+              F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_gated(FixedRate::new(initial, interval), predicate, true, scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but `predicate` is evaluated by the scheduling thread before
+    /// each execution: when it returns false, that execution is skipped and the task keeps
+    /// running. Use `schedule_fixed_rate_while` to stop the task instead.
+    pub fn schedule_fixed_rate_when<P, F>(&self, initial: Duration, interval: Duration, predicate: P, scheduled_fn: F) -> TaskHandle
+        where P: Fn() -> bool + Send + 'static,
+              F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_gated(FixedRate::new(initial, interval), predicate, false, scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the function decides whether the task should keep running,
+    /// by returning a `Control` value. Executions already submitted to the pool when one of them
+    /// returns `Control::Stop` will still run.
+    pub fn schedule_fixed_rate_controlled<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) -> Control + Send + Sync + 'static
+    {
+        self.schedule_with_controlled(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_with`, but the function decides whether the task should keep running, by
+    /// returning a `Control` value. The task will also stop once the schedule returns `None`.
+    pub fn schedule_with_controlled<S, F>(&self, schedule: S, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: Fn(&Remote) -> Control + Send + Sync + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let task_handle_clone = task_handle.clone();
+        self.schedule_with_handle(schedule, task_handle, move |remote, _| {
+            if scheduled_fn(remote) == Control::Stop {
+                debug!("Task requested to stop");
+                task_handle_clone.stop();
+            }
+        })
+    }
+
+    /// Like `schedule_fixed_rate`, but the function can fail by returning an error, see
+    /// `CoreExecutor::schedule_fixed_rate_fallible`.
+    pub fn schedule_fixed_rate_fallible<F, E>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) -> Result<(), E> + Send + Sync + 'static,
+              E: fmt::Display
+    {
+        self.schedule_with_fallible(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_with`, but the function can fail by returning an error, see
+    /// `CoreExecutor::schedule_fixed_rate_fallible`.
+    pub fn schedule_with_fallible<S, F, E>(&self, schedule: S, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: Fn(&Remote) -> Result<(), E> + Send + Sync + 'static,
+              E: fmt::Display
+    {
+        self.schedule_with_handle(schedule, TaskHandle::new(), move |remote, context| {
+            if let Err(e) = scheduled_fn(remote) {
+                context.record_error(&e);
+            }
+        })
+    }
+
+    /// Like `schedule_fixed_rate`, but the task will stop once `deadline` is passed. The deadline
+    /// is checked by the scheduling thread, before submitting each execution to the pool.
+    pub fn schedule_fixed_rate_until<F>(&self, initial: Duration, interval: Duration, deadline: Instant, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with(Until::new(FixedRate::new(initial, interval), deadline), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the function also receives a `TaskContext` describing the
+    /// current execution.
+    pub fn schedule_fixed_rate_ctx<F>(&self, initial: Duration, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote, &TaskContext) + Send + Sync + 'static
+    {
+        self.schedule_with_ctx(FixedRate::new(initial, interval), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the task will stop after `max_runs` executions, including
+    /// the ones requested through `TaskHandle::trigger_now`.
+    pub fn schedule_fixed_rate_limited<F>(&self, initial: Duration, interval: Duration, max_runs: u64, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let task_handle = TaskHandle::with_max_runs(max_runs);
+        self.schedule_with_handle(FixedRate::new(initial, interval), task_handle, move |remote, _| scheduled_fn(remote))
+    }
+
+    /// Like `schedule_fixed_rate`, but every execution will happen at a random time up to
+    /// `jitter` before or after its nominal time.
+    pub fn schedule_fixed_rate_jittered<F>(&self, initial: Duration, interval: Duration, jitter: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with(Jittered::new(FixedRate::new(initial, interval), jitter), scheduled_fn)
+    }
+
+    /// Like `schedule_fixed_rate`, but the first execution will happen after a random delay,
+    /// uniformly distributed between zero and `interval`.
+    pub fn schedule_fixed_rate_splayed<F>(&self, interval: Duration, scheduled_fn: F) -> TaskHandle
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with(FixedRate::splayed(interval), scheduled_fn)
+    }
+
+    /// Schedules the given function to be executed according to the given `Schedule`. The
+    /// function will be scheduled on one of the threads in the thread pool.
+    pub fn schedule_with<S, F>(&self, schedule: S, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with_ctx(schedule, move |remote, _| scheduled_fn(remote))
+    }
+
+    /// Like `schedule_with`, but the task is given a name, which can be used to stop it through
+    /// `stop_task` and appears in the logs.
+    pub fn schedule_with_named<S, F>(&self, name: &str, schedule: S, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with_handle(schedule, TaskHandle::named(name), move |remote, _| scheduled_fn(remote))
+    }
+
+    /// Like `schedule_with`, but returns an error if the scheduling thread has terminated,
+    /// instead of a handle to a task that will never run.
+    pub fn try_schedule_with<S, F>(&self, schedule: S, scheduled_fn: F) -> Result<TaskHandle, ExecutorError>
+        where S: Schedule,
+              F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.executor.check_running()?;
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_with`, but the function also receives a `TaskContext` describing the
+    /// current execution.
+    pub fn schedule_with_ctx<S, F>(&self, schedule: S, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: Fn(&Remote, &TaskContext) + Send + Sync + 'static
+    {
+        self.schedule_with_handle(schedule, TaskHandle::new(), scheduled_fn)
+    }
+
+    fn schedule_with_handle<S, F>(&self, schedule: S, task_handle: TaskHandle, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              F: Fn(&Remote, &TaskContext) + Send + Sync + 'static
+    {
+        let run_fn = self.run_fn(task_handle.clone(), scheduled_fn);
+        self.executor.spawn_loop(schedule, task_handle.clone(), run_fn);
+        task_handle
+    }
+
+    fn schedule_gated<S, P, F>(&self, schedule: S, predicate: P, stop: bool, scheduled_fn: F) -> TaskHandle
+        where S: Schedule,
+              P: Fn() -> bool + Send + 'static,
+              F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let task_handle = TaskHandle::new();
+        let run_fn = self.run_fn(task_handle.clone(), move |remote: &Remote, _: &TaskContext| scheduled_fn(remote));
+        self.executor.spawn_loop(schedule, task_handle.clone(), gated(predicate, stop, task_handle.clone(), run_fn));
+        task_handle
+    }
+
+    fn spawner(&self) -> PoolSpawner {
+        PoolSpawner {
+            pool: self.pool.clone(),
+            affinity_pools: self.affinity_pools.clone(),
+            hooks: self.executor.inner.hooks.clone(),
+        }
+    }
+
+    /// Wraps the function of a task, so that every execution is submitted to the pool through
+    /// `TaskHandle::begin_execution`, according to the overlap policy of the task. With
+    /// `OverlapPolicy::Queue`, the schedule waits for each execution to complete on the pool, so
+    /// that it measures how long the execution took.
+    fn run_fn<F>(&self, task_handle: TaskHandle, scheduled_fn: F) -> impl FnMut(&Handle, Tick) -> Completion + Send + 'static
+        where F: Fn(&Remote, &TaskContext) + Send + Sync + 'static
+    {
+        let spawner = self.spawner();
+        let arc_fn = Arc::new(scheduled_fn);
+        move |handle, tick| {
+            let policy = task_handle.overlap_policy();
+            let (limit, queue) = match policy {
+                OverlapPolicy::Allow => (None, false),
+                OverlapPolicy::Skip => (Some(1), false),
+                OverlapPolicy::Queue => (Some(1), true),
+                OverlapPolicy::Concurrent(limit) => (Some(limit.max(1)), task_handle.inner.executions().queue_at_limit),
+            };
+            // Executions only start on this thread, so no other one can start meanwhile
+            if let Some(limit) = limit.filter(|_| !queue) {
+                if task_handle.skip_at_limit(limit) {
+                    debug!("{} executions still running, skipping this one", limit);
+                    return None;
+                }
+            }
+            let execution = task_handle.begin_execution()?;
+            let arc_fn_clone = arc_fn.clone();
+            let remote = handle.remote().clone();
+            let job = move || {
+                execution.run(tick, |context| arc_fn_clone(&remote, context));
+            };
+            if policy == OverlapPolicy::Queue {
+                return Some(Box::new(spawner.submit(handle, &task_handle, job)));
+            }
+            spawner.spawn(handle, &task_handle, job);
+            match limit {
+                Some(limit) if queue => Some(Box::new(ExecutionSlot { inner: task_handle.inner.clone(), limit })),
+                _ => None,
+            }
+        }
+    }
+
+    /// Schedules the given function to be executed after each of the delays produced by the
+    /// given iterator. The function will be scheduled on one of the threads in the thread pool.
+    pub fn schedule_iter<I, F>(&self, delays: I, scheduled_fn: F) -> TaskHandle
+        where I: Iterator<Item = Duration> + Send + 'static,
+              F: Fn(&Remote) + Send + Sync + 'static
+    {
+        self.schedule_with(DelaySequence::new(delays), scheduled_fn)
+    }
+
+    /// Schedules the given function to be executed at the wall-clock times described by the
+    /// given cron expression. The function will be executed on one of the threads in the thread
+    /// pool.
+    pub fn schedule_cron<F>(&self, expression: &str, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let schedule = CronSchedule::parse(expression)?;
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Schedules the given function to be executed every day at the given UTC time. The function
+    /// will be executed on one of the threads in the thread pool.
+    pub fn schedule_daily_utc<F>(&self, hour: u32, minute: u32, second: u32, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let schedule = CronSchedule::daily(hour, minute, second)?;
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_daily_utc`, but the time is the local time of the system.
+    #[cfg(feature = "chrono")]
+    pub fn schedule_daily<F>(&self, hour: u32, minute: u32, second: u32, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let schedule = CronSchedule::daily(hour, minute, second)?.in_local_timezone();
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_cron`, but the expression is matched against the local time of the given
+    /// timezone.
+    #[cfg(feature = "chrono")]
+    pub fn schedule_cron_in_tz<F>(&self, expression: &str, timezone: Tz, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let schedule = CronSchedule::parse(expression)?.in_timezone(timezone);
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Like `schedule_daily_utc`, but the time is the local time of the given timezone.
+    #[cfg(feature = "chrono")]
+    pub fn schedule_daily_in_tz<F>(&self, timezone: Tz, hour: u32, minute: u32, second: u32, scheduled_fn: F) -> Result<TaskHandle, CronError>
+        where F: Fn(&Remote) + Send + Sync + 'static
+    {
+        let schedule = CronSchedule::daily(hour, minute, second)?.in_timezone(timezone);
+        Ok(self.schedule_with(schedule, scheduled_fn))
+    }
+
+    /// Schedules the given function to be executed once, after `delay`. The function will be
+    /// executed on one of the threads in the thread pool.
+    pub fn schedule_once<F>(&self, delay: Duration, scheduled_fn: F) -> TaskHandle
+        where F: FnOnce(&Remote) + Send + 'static
+    {
+        self.schedule_once_after(move || delay, scheduled_fn)
+    }
+
+    fn schedule_once_after<D, F>(&self, delay: D, scheduled_fn: F) -> TaskHandle
+        where D: FnOnce() -> Duration + Send + 'static,
+              F: FnOnce(&Remote) + Send + 'static
+    {
+        let spawner = self.spawner();
+        let task_handle = TaskHandle::new();
+        let task_handle_clone = task_handle.clone();
+        self.executor.spawn_once(
+            delay,
+            task_handle.clone(),
+            move |handle, tick| {
+                let execution = task_handle_clone.begin_execution();
+                task_handle_clone.finish(TerminationReason::Completed);
+                let execution = match execution {
+                    Some(execution) => execution,
+                    None => return,
+                };
+                let remote = handle.remote().clone();
+                spawner.spawn(handle, &task_handle_clone, move || {
+                    execution.run(tick, |_| scheduled_fn(&remote));
+                });
+            }
+        );
+        task_handle
+    }
+
+    /// Like `schedule_once`, but the function can be taken back with `CancelableOnce::cancel` as
+    /// long as it didn't start on the thread pool.
+    pub fn schedule_once_cancelable<F>(&self, delay: Duration, scheduled_fn: F) -> CancelableOnce<F>
+        where F: FnOnce(&Remote) + Send + 'static
+    {
+        let slot = Arc::new(Mutex::new(Some(scheduled_fn)));
+        let slot_clone = Arc::clone(&slot);
+        let task_handle = self.schedule_once(delay, move |remote| {
+            let scheduled_fn = slot_clone.lock().unwrap().take();
+            if let Some(scheduled_fn) = scheduled_fn {
+                scheduled_fn(remote);
+            }
+        });
+        CancelableOnce::new(task_handle, slot)
+    }
+
+    /// Schedules the given function to be executed once, after `delay`, and returns a `Receiver`
+    /// that will resolve to the value returned by the function. The function will be executed on
+    /// one of the threads in the thread pool.
+    pub fn schedule_once_result<F, T>(&self, delay: Duration, scheduled_fn: F) -> (TaskHandle, Receiver<T>)
+        where F: FnOnce(&Remote) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        let (result_tx, result_rx) = channel();
+        let task_handle = self.schedule_once(delay, move |remote| {
+            let _ = result_tx.send(scheduled_fn(remote));
+        });
+        (task_handle, result_rx)
+    }
+
+    /// Like `schedule_once_result`, but the result is a `std::future::Future`, which can be
+    /// awaited.
+    #[cfg(feature = "compat")]
+    pub fn schedule_once_result_compat<F, T>(&self, delay: Duration, scheduled_fn: F) -> (TaskHandle, impl StdFuture<Output = Result<T, Canceled>>)
+        where F: FnOnce(&Remote) -> T + Send + 'static,
+              T: Send + 'static
+    {
+        let (task_handle, result_rx) = self.schedule_once_result(delay, scheduled_fn);
+        (task_handle, result_rx.compat())
+    }
+
+    /// Schedules the given function to be executed once, at the given instant. The function will
+    /// be executed on one of the threads in the thread pool.
+    pub fn schedule_once_at<F>(&self, when: Instant, scheduled_fn: F) -> TaskHandle
+        where F: FnOnce(&Remote) + Send + 'static
+    {
+        self.schedule_once_after(move || when.saturating_duration_since(Instant::now()), scheduled_fn)
+    }
+
+    /// Sets the panic policy of the tasks running on this executor, including the ones already
+    /// scheduled, unless they define their own through `TaskHandle::set_panic_policy`.
+    pub fn set_panic_policy(&self, policy: PanicPolicy) {
+        self.executor.set_panic_policy(policy);
+    }
+
+    /// Stops all the tasks of the executor, waits up to `grace` for the executions in progress or
+    /// queued on the pool to complete, and then terminates the scheduling thread. See
+    /// `CoreExecutor::shutdown`.
+    pub fn shutdown(self, grace: Duration) -> Result<(), ExecutorError> {
+        self.executor.shutdown(grace)
+    }
+
+    /// Returns a snapshot of the tasks scheduled on this executor that didn't terminate yet,
+    /// sorted by id.
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        self.executor.tasks()
+    }
+
+    /// Returns the aggregate metrics of this executor, including the executions waiting for a
+    /// free thread in the pool, see `CoreExecutor::metrics`.
+    pub fn metrics(&self) -> ExecutorMetrics {
+        self.executor.metrics()
+    }
+
+    /// Returns the number of executions submitted to the pool that didn't complete yet, both
+    /// the running ones and the queued ones. It keeps growing when the tasks take longer than
+    /// their intervals and the pool can't keep up.
+    pub fn in_flight(&self) -> usize {
+        self.executor.inner.hooks.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Sets the handler invoked, with the number of executions in flight, every time that number
+    /// climbs to `threshold`, see `in_flight`; a warning is logged as well. The handler runs on
+    /// the scheduling thread, so it should return quickly. Replaces the previous handler.
+    pub fn on_saturation<F>(&self, threshold: usize, handler: F)
+        where F: Fn(usize) + Send + Sync + 'static
+    {
+        let saturation = Saturation { threshold, handler: Arc::new(handler) };
+        *self.executor.inner.hooks.saturation.write().unwrap() = Some(saturation);
+    }
+
+    /// Returns the state of the executor and its tasks as JSON, see `CoreExecutor::dump_json`.
+    #[cfg(feature = "serde")]
+    pub fn dump_json(&self) -> String {
+        self.executor.dump_json()
+    }
+
+    /// Stops all the tasks of this executor with the given name. Returns false if no running
+    /// task has that name.
+    pub fn stop_task(&self, name: &str) -> bool {
+        self.executor.stop_task(name)
+    }
+
+    /// Returns a snapshot of the tasks with the given tag that didn't terminate yet, sorted by id.
+    pub fn tasks_tagged(&self, tag: &str) -> Vec<TaskInfo> {
+        self.executor.tasks_tagged(tag)
+    }
+
+    /// Stops all the tasks with the given tag, and returns how many were still running.
+    pub fn stop_tagged(&self, tag: &str) -> usize {
+        self.executor.stop_tagged(tag)
+    }
+
+    /// Pauses all the tasks with the given tag, and returns how many were affected.
+    pub fn pause_tagged(&self, tag: &str) -> usize {
+        self.executor.pause_tagged(tag)
+    }
+
+    /// Resumes all the tasks with the given tag, and returns how many were affected.
+    pub fn resume_tagged(&self, tag: &str) -> usize {
+        self.executor.resume_tagged(tag)
+    }
+
+    /// Stops all the tasks scheduled so far on this executor, and returns how many were still
+    /// running, see `CoreExecutor::stop_all_tasks`.
+    pub fn stop_all_tasks(&self) -> usize {
+        self.executor.stop_all_tasks()
+    }
+
+    /// Detaches the scheduling thread, which will keep running the scheduled tasks for the
+    /// lifetime of the process, see `CoreExecutor::detach`. The thread pool is kept alive by the
+    /// tasks themselves, and released once all of them are stopped.
+    pub fn detach(self) {
+        self.executor.detach();
+    }
+
+    /// Returns the `Remote` of the event loop running on the scheduling thread, see
+    /// `CoreExecutor::remote`.
+    pub fn remote(&self) -> Remote {
+        self.executor.remote()
+    }
+
+    /// Starts a watchdog thread reporting when the scheduling thread is stalled, see
+    /// `CoreExecutor::enable_watchdog`.
+    pub fn enable_watchdog<F>(&self, threshold: Duration, callback: F) -> Result<(), ExecutorError>
+        where F: Fn(Duration) + Send + 'static
+    {
+        self.executor.enable_watchdog(threshold, callback)
+    }
+
+    /// Returns false once the scheduling thread has terminated, see `CoreExecutor::is_running`.
+    pub fn is_running(&self) -> bool {
+        self.executor.is_running()
+    }
+
+    /// Returns false once the scheduling thread has terminated, see `CoreExecutor::is_alive`.
+    pub fn is_alive(&self) -> bool {
+        self.executor.is_alive()
+    }
+
+    /// Sets the handler invoked every time a task running on this executor panics, see
+    /// `CoreExecutor::set_panic_handler`.
+    pub fn set_panic_handler(&self, handler: Box<PanicHandler>) {
+        self.executor.set_panic_handler(handler);
+    }
+
+    /// Sets the observer notified of the lifecycle events of all the tasks of this executor. The
+    /// executions are reported from the pool threads running them.
+    pub fn set_observer(&self, observer: Arc<dyn ExecutorObserver>) {
+        self.executor.set_observer(observer);
+    }
+
+    /// Sets the level the lifecycle of the tasks of this executor is logged at, see
+    /// `CoreExecutor::set_task_log_level`.
+    pub fn set_task_log_level(&self, level: LogLevelFilter) {
+        self.executor.set_task_log_level(level);
+    }
+
+    /// Sets the sink the metrics of this executor are reported to, see
+    /// `CoreExecutor::set_metrics_sink`. The report runs on the scheduling thread.
+    pub fn set_metrics_sink(&self, sink: Arc<dyn MetricsSink>, every: Duration) -> TaskHandle {
+        self.executor.set_metrics_sink(sink, every)
+    }
+
+    /// Runs the given function right away on one of the threads in the thread pool, and returns
+    /// a future resolving to the value it returns. The future can be waited on, or composed with
+    /// other futures. If the function panics, the panic is propagated when the future is polled.
+    pub fn run_now<F, T>(&self, job: F) -> CpuFuture<T, ()>
+        where F: FnOnce() -> T + Send + 'static,
+              T: Send + 'static
+    {
+        self.pool.current().spawn_fn(move || Ok(job()))
+    }
+
+    /// Like `run_now`, but the function runs after `delay`, as a one-shot task. If the function
+    /// panics, or the task is stopped first, the future resolves to `Canceled`.
+    pub fn run_after<F, T>(&self, delay: Duration, job: F) -> Receiver<T>
+        where F: FnOnce() -> T + Send + 'static,
+              T: Send + 'static
+    {
+        self.schedule_once_result(delay, move |_remote| job()).1
+    }
+
+    /// Returns the thread pool used internally.
+    pub(crate) fn pool(&self) -> &SharedPool {
+        &self.pool
+    }
+}
+
+/// Runs the futures on the thread pool. Futures can't be spawned once the scheduling thread has
+/// terminated.
+impl<F> Executor<F> for ThreadPoolExecutor
+    where F: Future<Item = (), Error = ()> + Send + 'static
+{
+    fn execute(&self, future: F) -> Result<(), ExecuteError<F>> {
+        if !self.is_running() {
+            return Err(ExecuteError::new(ExecuteErrorKind::Shutdown, future));
+        }
+        self.pool.current().execute(future)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::io;
+    use std::sync::{mpsc, Arc, Mutex, RwLock};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use futures::{Future, Stream};
+    use futures::future::{self, ExecuteErrorKind, Executor};
+    use futures::sync::mpsc as futures_mpsc;
+    use futures::sync::oneshot::{self, Canceled, Receiver};
+    use futures_cpupool::Builder;
+    use tokio_core::reactor::{Core, Handle, Remote, Timeout};
+
+    use duration::{ParseError, MAX_INTERVAL};
+    use schedule::{Backoff, CronError, DelaySequence, FixedInterval, MissedTickPolicy, RetryPolicy, Schedule, ScheduleKind};
+    use log::{self, LogLevel, LogLevelFilter, LogMetadata, LogRecord};
+
+    #[cfg(feature = "serde")]
+    use super::ScheduleConfig;
+    use super::{ChainPolicy, ClockSource, Control, CoreExecutor, ExecutorBuilder, ExecutorError,
+                ExecutorObserver, Histogram, MIN_INTERVAL, MetricsSink, OverflowPolicy,
+                OverlapPolicy, PanicPolicy, PrometheusTextSink, ScheduledChain, TASK_LOG_TARGET,
+                Task, TaskContext, TaskInfo, TaskSet, TaskTimer, TerminationReason,
+                ThreadPoolExecutor, ThrottleMode, fail_next_timers};
+
+    #[test]
+    fn fixed_interval_test() {
+        let timings = Arc::new(RwLock::new(Vec::new()));
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let timings_clone = Arc::clone(&timings);
+            executor.schedule_fixed_rate(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
+                    timings_clone.write().unwrap().push(Instant::now());
+                }
+            );
+            thread::sleep(Duration::from_millis(5500));
+        }
+
+        let timings = timings.read().unwrap();
+        assert_eq!(timings.len(), 6);
+        for i in 1..6 {
+            let execution_interval = timings[i] - timings[i-1];
+            assert!(execution_interval < Duration::from_millis(1020));
+            assert!(execution_interval > Duration::from_millis(980));
+        }
+    }
+
+    #[test]
+    fn fixed_interval_slow_task_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            executor.schedule_fixed_interval(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
+                    // TODO: use atomic int when available
+                    let counter = {
+                        let mut counter = counter_clone.write().unwrap();
+                        (*counter) += 1;
+                        *counter
+                    };
+                    if counter == 1 {
+                        thread::sleep(Duration::from_secs(3));
+                    }
+                }
+            );
+            thread::sleep(Duration::from_millis(5500));
+        }
+        assert_eq!(*counter.read().unwrap(), 4);
+    }
+
+    #[test]
+    fn fixed_rate_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            executor.schedule_fixed_rate(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            thread::sleep(Duration::from_millis(5500));
+        }
+        assert_eq!(*counter.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn fixed_rate_slow_task_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            executor.schedule_fixed_rate(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
                     // TODO: use atomic int when available
                     let counter = {
                         let mut counter = counter_clone.write().unwrap();
@@ -378,98 +6232,3394 @@ mod tests {
                     }
                 }
             );
-            thread::sleep(Duration::from_millis(5500));
+            thread::sleep(Duration::from_millis(5500));
+        }
+        assert_eq!(*counter.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn fixed_rate_slow_task_test_pool() {
+        let starts = Arc::new(Mutex::new(Vec::new()));
+        let starts_clone = Arc::clone(&starts);
+        {
+            let executor = ThreadPoolExecutor::new(20).unwrap();
+            executor.task()
+                .every(Duration::from_secs(1))
+                .overlap_policy(OverlapPolicy::Queue)
+                .spawn(move |_remote| {
+                    let first = {
+                        let mut starts = starts_clone.lock().unwrap();
+                        starts.push(Instant::now());
+                        starts.len() == 1
+                    };
+                    if first {
+                        thread::sleep(Duration::from_secs(3));
+                    }
+                })
+                .unwrap();
+            thread::sleep(Duration::from_millis(5500));
+        }
+        let starts = starts.lock().unwrap();
+        assert_eq!(starts.len(), 6);
+        // The slow execution delays the next ones, which then catch up with the rate
+        let expected = [0, 3000, 3000, 3000, 4000, 5000];
+        for (start, &expected) in starts.iter().zip(&expected) {
+            let offset = *start - starts[0];
+            assert!(offset >= Duration::from_millis(expected) && offset < Duration::from_millis(expected + 100),
+                    "{:?} instead of {}ms", offset, expected);
+        }
+    }
+
+    #[test]
+    fn fixed_rate_stop_test() {
+        let counter1 = Arc::new(RwLock::new(0));
+        let counter2 = Arc::new(RwLock::new(0));
+        let counter1_clone = Arc::clone(&counter1);
+        let counter2_clone = Arc::clone(&counter2);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let t1 = executor.schedule_fixed_rate(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
+                    let mut counter = counter1_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            executor.schedule_fixed_rate(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
+                    let mut counter = counter2_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            thread::sleep(Duration::from_millis(5500));
+            t1.stop();
+            thread::sleep(Duration::from_millis(5000));
+        }
+        assert_eq!(*counter1.read().unwrap(), 6);
+        assert_eq!(*counter2.read().unwrap(), 11);
+    }
+
+    #[test]
+    fn fixed_interval_stop_test() {
+        let counter1 = Arc::new(RwLock::new(0));
+        let counter2 = Arc::new(RwLock::new(0));
+        let counter1_clone = Arc::clone(&counter1);
+        let counter2_clone = Arc::clone(&counter2);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let t1 = executor.schedule_fixed_interval(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
+                    let mut counter = counter1_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            executor.schedule_fixed_rate(
+                Duration::from_secs(0),
+                Duration::from_secs(1),
+                move |_handle| {
+                    let mut counter = counter2_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            thread::sleep(Duration::from_millis(5500));
+            t1.stop();
+            thread::sleep(Duration::from_millis(5000));
+        }
+        assert_eq!(*counter1.read().unwrap(), 6);
+        assert_eq!(*counter2.read().unwrap(), 11);
+    }
+
+    #[test]
+    fn schedule_once_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let counter_pool_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+            let t1 = executor.schedule_once(
+                Duration::from_millis(100),
+                move |_handle| {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            let t2 = pool_executor.schedule_once(
+                Duration::from_millis(100),
+                move |_remote| {
+                    let mut counter = counter_pool_clone.write().unwrap();
+                    (*counter) += 10;
+                }
+            );
+            assert!(!t1.stopped());
+            assert!(!t2.stopped());
+            thread::sleep(Duration::from_millis(500));
+            assert!(t1.stopped());
+            assert!(t2.stopped());
+        }
+        assert_eq!(*counter.read().unwrap(), 11);
+    }
+
+    #[test]
+    fn schedule_once_stop_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let t1 = executor.schedule_once(
+                Duration::from_millis(300),
+                move |_handle| {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            thread::sleep(Duration::from_millis(100));
+            t1.stop();
+            thread::sleep(Duration::from_millis(400));
+        }
+        assert_eq!(*counter.read().unwrap(), 0);
+    }
+
+    #[test]
+    fn schedule_once_at_past_test() {
+        let fired_at = Arc::new(RwLock::new(None));
+        let fired_at_clone = Arc::clone(&fired_at);
+        let start_time = Instant::now();
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let t1 = executor.schedule_once_at(
+                start_time - Duration::from_secs(10),
+                move |_handle| {
+                    *fired_at_clone.write().unwrap() = Some(Instant::now());
+                }
+            );
+            thread::sleep(Duration::from_millis(200));
+            assert!(t1.stopped());
+        }
+        let fired_at = fired_at.read().unwrap().expect("Task didn't run");
+        assert!(fired_at - start_time < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn schedule_once_at_stop_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let t1 = executor.schedule_once_at(
+                Instant::now() + Duration::from_millis(300),
+                move |_handle| {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            thread::sleep(Duration::from_millis(100));
+            t1.stop();
+            thread::sleep(Duration::from_millis(400));
+        }
+        assert_eq!(*counter.read().unwrap(), 0);
+    }
+
+    #[test]
+    fn schedule_once_result_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let (t1, result1) = executor.schedule_once_result(
+            Duration::from_millis(100),
+            |_handle| 42
+        );
+        let (t2, result2) = pool_executor.schedule_once_result(
+            Duration::from_millis(100),
+            |_remote| thread::current().name().map(|name| name.to_owned())
+        );
+        assert_eq!(result1.wait(), Ok(42));
+        assert_eq!(result2.wait().unwrap(), Some("pool_thread_0".to_owned()));
+        // The result is sent before the execution returns, and the task stops after it
+        t1.on_stop().wait().unwrap();
+        t2.on_stop().wait().unwrap();
+        assert!(t1.stopped());
+        assert!(t2.stopped());
+    }
+
+    #[test]
+    fn schedule_once_result_stop_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (t1, result1) = executor.schedule_once_result(
+            Duration::from_millis(200),
+            |_handle| 42
+        );
+        t1.stop();
+        assert_eq!(result1.wait(), Err(Canceled));
+    }
+
+    #[test]
+    fn schedule_cron_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            executor.schedule_cron(
+                "* * * * * *",
+                move |_handle| {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            ).unwrap();
+            thread::sleep(Duration::from_millis(2500));
+        }
+        let counter = *counter.read().unwrap();
+        assert!((2..=3).contains(&counter));
+    }
+
+    #[test]
+    fn schedule_cron_invalid_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        assert!(executor.schedule_cron("* * *", |_handle| ()).is_err());
+        assert!(pool_executor.schedule_cron("0 25 * * *", |_remote| ()).is_err());
+    }
+
+    #[test]
+    fn schedule_daily_utc_test() {
+        // The firing times are tested against an injected clock in the schedule module; here the
+        // task only has to wait for the next occurrence of the time of day
+        let executor = CoreExecutor::new().unwrap();
+        let in_an_hour = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 3600;
+        let second_of_day = (in_an_hour % 86_400) as u32;
+        let task = executor.schedule_daily_utc(second_of_day / 3600, (second_of_day % 3600) / 60, second_of_day % 60, |_| ())
+            .unwrap();
+        while task.next_run_in().is_none() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let wait = task.next_run_in().unwrap();
+        assert!(wait > Duration::from_secs(3590) && wait <= Duration::from_secs(3600), "{:?}", wait);
+        assert_eq!(task.stats().executions, 0);
+        assert!(executor.schedule_daily_utc(25, 0, 0, |_handle| ()).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn schedule_daily_test() {
+        use chrono::Timelike;
+
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let in_an_hour = chrono::Local::now() + chrono::Duration::hours(1);
+        let (hour, minute, second) = (in_an_hour.hour(), in_an_hour.minute(), in_an_hour.second());
+        let tasks = [
+            executor.schedule_daily(hour, minute, second, |_| ()).unwrap(),
+            pool_executor.schedule_daily(hour, minute, second, |_| ()).unwrap(),
+        ];
+        for task in &tasks {
+            while task.next_run_in().is_none() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let wait = task.next_run_in().unwrap();
+            assert!(wait > Duration::from_secs(3590) && wait <= Duration::from_secs(3600), "{:?}", wait);
+        }
+        assert!(pool_executor.schedule_daily(0, 60, 0, |_remote| ()).is_err());
+    }
+
+    struct Times(u32);
+
+    impl Schedule for Times {
+        fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+            if self.0 == 0 {
+                return None;
+            }
+            self.0 -= 1;
+            Some(Duration::from_millis(100))
+        }
+    }
+
+    #[test]
+    fn schedule_with_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let counter_pool_clone = Arc::clone(&counter);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+            let t1 = executor.schedule_with(
+                Times(3),
+                move |_handle| {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            let t2 = pool_executor.schedule_with(
+                Times(2),
+                move |_remote| {
+                    let mut counter = counter_pool_clone.write().unwrap();
+                    (*counter) += 10;
+                }
+            );
+            thread::sleep(Duration::from_millis(600));
+            assert!(t1.stopped());
+            assert!(t2.stopped());
+        }
+        assert_eq!(*counter.read().unwrap(), 23);
+    }
+
+    #[test]
+    fn schedule_iter_test() {
+        let timings = Arc::new(RwLock::new(Vec::new()));
+        let timings_clone = Arc::clone(&timings);
+        let start_time = Instant::now();
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let delays = vec![50, 100, 200].into_iter().map(Duration::from_millis);
+            let t1 = executor.schedule_iter(
+                delays,
+                move |_handle| {
+                    timings_clone.write().unwrap().push(Instant::now());
+                }
+            );
+            thread::sleep(Duration::from_millis(300));
+            assert!(!t1.stopped());
+            thread::sleep(Duration::from_millis(300));
+            assert!(t1.stopped());
+        }
+        let timings = timings.read().unwrap();
+        assert_eq!(timings.len(), 3);
+        let expected = [50, 150, 350];
+        for (timing, expected) in timings.iter().zip(expected.iter()) {
+            let elapsed = *timing - start_time;
+            assert!(elapsed > Duration::from_millis(*expected - 10));
+            assert!(elapsed < Duration::from_millis(*expected + 40));
+        }
+    }
+
+    #[test]
+    fn schedule_dynamic_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let other_counter = Arc::new(RwLock::new(0));
+        let other_counter_clone = Arc::clone(&other_counter);
+        let start_time = Instant::now();
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let t1 = executor.schedule_dynamic(
+                Duration::from_millis(0),
+                move |_handle| {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                    if start_time.elapsed() < Duration::from_millis(300) {
+                        Some(Duration::from_millis(0))
+                    } else {
+                        None
+                    }
+                }
+            );
+            executor.schedule_fixed_rate(
+                Duration::from_millis(0),
+                Duration::from_millis(50),
+                move |_handle| {
+                    let mut counter = other_counter_clone.write().unwrap();
+                    (*counter) += 1;
+                }
+            );
+            thread::sleep(Duration::from_millis(500));
+            assert!(t1.stopped());
+        }
+        assert!(*counter.read().unwrap() > 1);
+        // The zero waits didn't starve the other task
+        assert!(*other_counter.read().unwrap() >= 8);
+    }
+
+    #[test]
+    fn schedule_backoff_test() {
+        let timings = Arc::new(RwLock::new(Vec::new()));
+        let timings_clone = Arc::clone(&timings);
+        {
+            let executor = CoreExecutor::new().unwrap();
+            let backoff = Backoff::exponential(Duration::from_millis(20), 2.0, Duration::from_secs(1));
+            executor.schedule_backoff(
+                backoff,
+                move |_handle| {
+                    let mut timings = timings_clone.write().unwrap();
+                    timings.push(Instant::now());
+                    // The third execution succeeds
+                    timings.len() == 3
+                }
+            );
+            thread::sleep(Duration::from_millis(260));
+        }
+        let timings = timings.read().unwrap();
+        assert_eq!(timings.len(), 5);
+        let expected = [40, 80, 20, 40];
+        for i in 1..5 {
+            let execution_interval = timings[i] - timings[i-1];
+            assert!(execution_interval >= Duration::from_millis(expected[i-1]));
+            assert!(execution_interval < Duration::from_millis(expected[i-1] + 15));
+        }
+    }
+
+    #[test]
+    fn fixed_rate_jittered_test() {
+        let timings = Arc::new(RwLock::new(Vec::new()));
+        let timings_clone = Arc::clone(&timings);
+        let start_time = Instant::now();
+        {
+            let executor = CoreExecutor::new().unwrap();
+            executor.schedule_fixed_rate_jittered(
+                Duration::from_millis(50),
+                Duration::from_millis(100),
+                Duration::from_millis(30),
+                move |_handle| {
+                    timings_clone.write().unwrap().push(Instant::now());
+                }
+            );
+            thread::sleep(Duration::from_millis(1000));
+        }
+        let timings = timings.read().unwrap();
+        assert_eq!(timings.len(), 10);
+        for (n, timing) in timings.iter().enumerate() {
+            let nominal = Duration::from_millis(50 + 100 * n as u64);
+            let elapsed = *timing - start_time;
+            assert!(elapsed + Duration::from_millis(30) >= nominal);
+            assert!(elapsed <= nominal + Duration::from_millis(45));
+        }
+    }
+
+    #[test]
+    fn fixed_rate_splayed_test() {
+        let timings = Arc::new(RwLock::new(Vec::new()));
+        let start_time = Instant::now();
+        {
+            let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+            for _ in 0..40 {
+                let timings_clone = Arc::clone(&timings);
+                pool_executor.schedule_fixed_rate_splayed(
+                    Duration::from_millis(500),
+                    move |_remote| {
+                        timings_clone.write().unwrap().push(Instant::now());
+                    }
+                );
+            }
+            // Dropped well before the bound below, as the tasks due right before can still fire
+            thread::sleep(Duration::from_millis(200));
+        }
+        let timings = timings.read().unwrap();
+        // Only the tasks with shorter delays fired, at different times
+        assert!(timings.len() > 5 && timings.len() < 40);
+        let first = timings.iter().min().unwrap();
+        let last = timings.iter().max().unwrap();
+        assert!(*last - *first > Duration::from_millis(100));
+        assert!(*last - start_time < Duration::from_millis(250));
+    }
+
+    #[test]
+    fn pause_resume_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let executor = CoreExecutor::new().unwrap();
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+            }
+        );
+        thread::sleep(Duration::from_millis(250));
+        t1.pause();
+        assert!(t1.paused());
+        assert_eq!(*counter.read().unwrap(), 3);
+        thread::sleep(Duration::from_millis(500));
+        // No executions while paused
+        assert_eq!(*counter.read().unwrap(), 3);
+        t1.resume();
+        thread::sleep(Duration::from_millis(500));
+        // and no catching up after resuming
+        assert_eq!(*counter.read().unwrap(), 8);
+        assert!(!t1.stopped());
+    }
+
+    #[test]
+    fn stop_and_wait_test() {
+        let finished = Arc::new(RwLock::new(false));
+        let finished_clone = Arc::clone(&finished);
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move |_handle| {
+                thread::sleep(Duration::from_millis(300));
+                *finished_clone.write().unwrap() = true;
+            }
+        );
+        let t2 = pool_executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_secs(1),
+            move |_remote| thread::sleep(Duration::from_millis(300))
+        );
+        thread::sleep(Duration::from_millis(100));
+        assert!(!t1.stop_and_wait(Some(Duration::from_millis(50))));
+        assert!(!*finished.read().unwrap());
+        assert!(t1.stop_and_wait(None));
+        assert!(*finished.read().unwrap());
+        assert!(t2.stop_and_wait(Some(Duration::from_millis(500))));
+        // Nothing left to wait for
+        assert!(t1.stop_and_wait(Some(Duration::from_millis(0))));
+        assert!(t1.stop_and_wait(Some(Duration::MAX)));
+
+        // From the function of the task, which can't wait for its own execution
+        let (tx, rx) = mpsc::channel();
+        let t3 = executor.schedule_fixed_rate_ctx(Duration::from_millis(0), Duration::from_millis(10), move |_handle, context| {
+            tx.send(context.task_handle().stop_and_wait(None)).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(false));
+        assert!(t3.stop_and_wait(None));
+        assert_eq!(t3.stats().executions, 1);
+    }
+
+    #[test]
+    fn on_stop_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        // Stopped externally
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            |_handle| thread::sleep(Duration::from_millis(200))
+        );
+        thread::sleep(Duration::from_millis(100));
+        let start_time = Instant::now();
+        t1.stop();
+        t1.on_stop().wait().unwrap();
+        assert!(start_time.elapsed() >= Duration::from_millis(50));
+        // Stopped by itself
+        let t2 = pool_executor.schedule_once(Duration::from_millis(100), |_remote| ());
+        t2.on_stop().wait().unwrap();
+        assert!(t2.stopped());
+        // Executor dropped
+        let t3 = executor.schedule_fixed_interval(
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            |_handle| ()
+        );
+        let t4 = pool_executor.schedule_once(Duration::from_secs(10), |_remote| ());
+        drop(executor);
+        drop(pool_executor);
+        t3.on_stop().wait().unwrap();
+        t4.on_stop().wait().unwrap();
+    }
+
+    #[test]
+    fn stats_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let start_time = Instant::now();
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(500),
+            |_handle| thread::sleep(Duration::from_millis(100))
+        );
+        let t2 = pool_executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(500),
+            |_remote| thread::sleep(Duration::from_millis(100))
+        );
+        let stats = t1.stats();
+        assert_eq!(stats.executions, 0);
+        assert!(stats.last_end.is_none());
+        thread::sleep(Duration::from_millis(1250));
+        let stats = t1.stats();
+        assert_eq!(stats.executions, 3);
+        assert_eq!(stats.panics, 0);
+        assert!(stats.last_start.unwrap() >= start_time + Duration::from_millis(1000));
+        assert!(stats.last_end.unwrap() >= stats.last_start.unwrap());
+        assert!(stats.last_duration.unwrap() >= Duration::from_millis(100));
+        let stats = t2.stats();
+        assert_eq!(stats.executions, 3);
+        assert!(stats.last_duration.unwrap() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn next_run_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let start_time = Instant::now();
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(100),
+            Duration::from_millis(500),
+            |_handle| thread::sleep(Duration::from_millis(200))
+        );
+        let t2 = executor.schedule_once(Duration::from_secs(10), |_handle| ());
+        thread::sleep(Duration::from_millis(50));
+        let next_run = t1.next_run_at().unwrap();
+        assert!(next_run >= start_time + Duration::from_millis(100));
+        assert!(next_run <= start_time + Duration::from_millis(150));
+        assert!(t2.next_run_in().unwrap() > Duration::from_secs(9));
+        // Currently executing
+        thread::sleep(Duration::from_millis(100));
+        assert!(t1.next_run_at().is_none());
+        // The fixed rate compensates the execution time
+        thread::sleep(Duration::from_millis(200));
+        let next_run = t1.next_run_at().unwrap();
+        assert!(next_run >= start_time + Duration::from_millis(600));
+        assert!(next_run <= start_time + Duration::from_millis(650));
+        t2.stop();
+        assert!(t2.next_run_in().is_none());
+    }
+
+    #[test]
+    fn trigger_now_test() {
+        let runs = Arc::new(RwLock::new(Vec::new()));
+        let runs_clone = Arc::clone(&runs);
+        let pool_runs_clone = Arc::clone(&runs);
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let start_time = Instant::now();
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(500),
+            Duration::from_millis(500),
+            move |_handle| runs_clone.write().unwrap().push(start_time.elapsed())
+        );
+        let t2 = pool_executor.schedule_fixed_rate(
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            move |_remote| pool_runs_clone.write().unwrap().push(Duration::from_secs(0))
+        );
+        thread::sleep(Duration::from_millis(200));
+        t1.trigger_now();
+        t2.trigger_now();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(runs.read().unwrap().len(), 2);
+        // The phase is kept
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(runs.read().unwrap().len(), 3);
+        // The schedule restarts from the triggered run
+        t1.trigger_now_and_reschedule();
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(runs.read().unwrap().len(), 4);
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(runs.read().unwrap().len(), 5);
+        let runs = runs.read().unwrap();
+        assert!(runs[3] >= Duration::from_millis(600) && runs[3] < Duration::from_millis(700));
+        assert!(runs[4] >= Duration::from_millis(1100) && runs[4] < Duration::from_millis(1200));
+        // Ignored once stopped
+        t1.stop();
+        t1.trigger_now();
+    }
+
+    #[test]
+    fn set_interval_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let executor = CoreExecutor::new().unwrap();
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(400),
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+            }
+        );
+        let t2 = executor.schedule_once(Duration::from_secs(10), |_handle| ());
+        assert_eq!(t1.interval(), Some(Duration::from_millis(400)));
+        thread::sleep(Duration::from_millis(100));
+        // The pending timer still uses the old interval
+        t1.set_interval(Duration::from_millis(100));
+        assert_eq!(t1.interval(), Some(Duration::from_millis(100)));
+        thread::sleep(Duration::from_millis(250));
+        assert_eq!(*counter.read().unwrap(), 1);
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(*counter.read().unwrap(), 4);
+        t2.set_interval(Duration::from_millis(100));
+        assert_eq!(t2.interval(), None);
+    }
+
+    #[test]
+    fn postpone_test() {
+        let runs = Arc::new(RwLock::new(Vec::new()));
+        let runs_clone = Arc::clone(&runs);
+        let once_runs_clone = Arc::clone(&runs);
+        let executor = CoreExecutor::new().unwrap();
+        let start_time = Instant::now();
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(200),
+            Duration::from_millis(200),
+            move |_handle| runs_clone.write().unwrap().push(start_time.elapsed())
+        );
+        let t2 = executor.schedule_once(
+            Duration::from_millis(100),
+            move |_handle| once_runs_clone.write().unwrap().push(Duration::from_secs(0))
+        );
+        thread::sleep(Duration::from_millis(50));
+        t1.postpone(Duration::from_millis(100));
+        t1.postpone(Duration::from_millis(200));
+        t2.postpone(Duration::from_millis(1000));
+        let next_run = t1.next_run_at().unwrap();
+        assert!(next_run >= start_time + Duration::from_millis(500));
+        thread::sleep(Duration::from_millis(800));
+        let runs = runs.read().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert!(runs[0] >= Duration::from_millis(500) && runs[0] < Duration::from_millis(600));
+        assert!(runs[1] >= Duration::from_millis(700) && runs[1] < Duration::from_millis(800));
+        assert!(!t2.stopped());
+
+        // postponements too long to be represented are clamped
+        let t3 = executor.schedule_fixed_rate(Duration::from_millis(50), Duration::from_secs(1), |_handle| ());
+        t3.postpone(Duration::MAX);
+        t3.postpone(Duration::MAX);
+        thread::sleep(Duration::from_millis(100));
+        assert!(t3.next_run_in().unwrap() > Duration::from_secs(86_400));
+        assert_eq!(t3.stats().executions, 0);
+        assert!(executor.is_alive());
+    }
+
+    #[test]
+    fn fixed_rate_limited_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let counter_pool_clone = Arc::clone(&counter);
+        let counter_stop_clone = Arc::clone(&counter);
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let t1 = executor.schedule_fixed_rate_limited(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            5,
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+            }
+        );
+        let t2 = pool_executor.schedule_fixed_rate_limited(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            3,
+            move |_remote| {
+                let mut counter = counter_pool_clone.write().unwrap();
+                (*counter) += 10;
+            }
+        );
+        let t3 = executor.schedule_fixed_rate_limited(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            100,
+            move |_handle| {
+                let mut counter = counter_stop_clone.write().unwrap();
+                (*counter) += 100;
+            }
+        );
+        t1.trigger_now();
+        thread::sleep(Duration::from_millis(150));
+        t3.stop();
+        thread::sleep(Duration::from_millis(600));
+        assert!(t1.stopped());
+        assert!(t2.stopped());
+        assert_eq!(*counter.read().unwrap(), 5 + 30 + 200);
+    }
+
+    #[test]
+    fn fixed_rate_until_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let counter_pool_clone = Arc::clone(&counter);
+        let counter_past_clone = Arc::clone(&counter);
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let deadline = Instant::now() + Duration::from_millis(350);
+        let t1 = executor.schedule_fixed_rate_until(
+            Duration::from_millis(0),
+            Duration::from_millis(200),
+            deadline,
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+            }
+        );
+        let t2 = pool_executor.schedule_fixed_rate_until(
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            deadline,
+            move |_remote| {
+                let mut counter = counter_pool_clone.write().unwrap();
+                (*counter) += 10;
+            }
+        );
+        let t3 = executor.schedule_fixed_interval_until(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            Instant::now() - Duration::from_millis(1),
+            move |_handle| {
+                let mut counter = counter_past_clone.write().unwrap();
+                (*counter) += 100;
+            }
+        );
+        thread::sleep(Duration::from_millis(50));
+        assert!(t3.stopped());
+        thread::sleep(Duration::from_millis(200));
+        assert!(t1.stopped());
+        assert!(!t2.stopped());
+        thread::sleep(Duration::from_millis(200));
+        assert!(t2.stopped());
+        assert_eq!(*counter.read().unwrap(), 2 + 20);
+    }
+
+    #[test]
+    fn controlled_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let counter_pool_clone = Arc::clone(&counter);
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let t1 = executor.schedule_fixed_rate_controlled(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+                if *counter % 10 == 3 { Control::Stop } else { Control::Continue }
+            }
+        );
+        let t2 = pool_executor.schedule_fixed_rate_controlled(
+            Duration::from_millis(50),
+            Duration::from_millis(100),
+            move |_remote| {
+                let mut counter = counter_pool_clone.write().unwrap();
+                (*counter) += 10;
+                if *counter >= 20 { Control::Stop } else { Control::Continue }
+            }
+        );
+        thread::sleep(Duration::from_millis(600));
+        assert!(t1.stopped());
+        assert!(t2.stopped());
+        assert_eq!(*counter.read().unwrap(), 23);
+    }
+
+    #[test]
+    fn fixed_rate_while_when_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let enabled = Arc::new(AtomicBool::new(true));
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let counter_clone = Arc::clone(&counter);
+        let enabled_clone = Arc::clone(&enabled);
+        let t1 = executor.schedule_fixed_rate_while(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move || enabled_clone.load(Ordering::Relaxed),
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+            }
+        );
+        let counter_clone = Arc::clone(&counter);
+        let enabled_clone = Arc::clone(&enabled);
+        let t2 = pool_executor.schedule_fixed_rate_when(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move || enabled_clone.load(Ordering::Relaxed),
+            move |_remote| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 10;
+            }
+        );
+        thread::sleep(Duration::from_millis(150));
+        enabled.store(false, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(200));
+        assert!(t1.stopped());
+        assert!(!t2.stopped());
+        assert_eq!(*counter.read().unwrap(), 22);
+        enabled.store(true, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(200));
+        assert!(t1.stopped());
+        assert_eq!(*counter.read().unwrap(), 42);
+    }
+
+    #[test]
+    fn schedule_ctx_test() {
+        let contexts = Arc::new(RwLock::new(Vec::new()));
+        let contexts_clone = Arc::clone(&contexts);
+        let pool_runs = Arc::new(RwLock::new(Vec::new()));
+        let pool_runs_clone = Arc::clone(&pool_runs);
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let start_time = Instant::now();
+        executor.schedule_fixed_rate_ctx(
+            Duration::from_millis(100),
+            Duration::from_millis(100),
+            move |_handle, context: &TaskContext| {
+                contexts_clone.write().unwrap().push(context.clone());
+                if context.run() == 0 {
+                    thread::sleep(Duration::from_millis(250));
+                }
+            }
+        );
+        pool_executor.schedule_fixed_rate_ctx(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move |_remote, context| pool_runs_clone.write().unwrap().push(context.run())
+        );
+        thread::sleep(Duration::from_millis(450));
+        let contexts = contexts.read().unwrap();
+        assert!(contexts.len() >= 3);
+        for (n, context) in contexts.iter().enumerate() {
+            assert_eq!(context.run(), n as u64);
+            assert!(context.started_at() >= context.scheduled_at());
+        }
+        let first = &contexts[0];
+        assert!(first.scheduled_at() >= start_time + Duration::from_millis(100));
+        assert!(first.lateness() < Duration::from_millis(50));
+        assert_eq!(first.delay(), Duration::from_millis(0));
+        // The slow first execution delayed the rate by 150ms
+        let second = &contexts[1];
+        assert!(second.delay() >= Duration::from_millis(150));
+        assert!(second.delay() < Duration::from_millis(200));
+        assert_eq!(*pool_runs.read().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn context_task_handle_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let t1 = executor.schedule_fixed_rate_ctx(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move |_handle, context| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+                if context.task_handle().stats().executions == 2 {
+                    context.task_handle().stop();
+                }
+            }
+        );
+        let bailed_out = Arc::new(RwLock::new(false));
+        let bailed_out_clone = Arc::clone(&bailed_out);
+        let t2 = pool_executor.schedule_fixed_rate_ctx(
+            Duration::from_millis(0),
+            Duration::from_secs(10),
+            move |_remote, context| {
+                while !context.task_handle().stopped() {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                *bailed_out_clone.write().unwrap() = true;
+            }
+        );
+        thread::sleep(Duration::from_millis(400));
+        assert!(t1.stopped());
+        assert_eq!(*counter.read().unwrap(), 3);
+        assert!(t2.stop_and_wait(Some(Duration::from_millis(100))));
+        assert!(*bailed_out.read().unwrap());
+    }
+
+    #[test]
+    fn panic_policy_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let schedule_failing = |increment| {
+            let counter_clone = Arc::clone(&counter);
+            move |_: &Remote| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += increment;
+                if *counter == increment {
+                    drop(counter);
+                    panic!("first run failed");
+                }
+            }
+        };
+        // Ignored by default
+        let counter_clone = Arc::clone(&counter);
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+                if *counter == 1 {
+                    drop(counter);
+                    panic!("first run failed");
+                }
+            }
+        );
+        thread::sleep(Duration::from_millis(250));
+        t1.stop();
+        assert_eq!(*counter.read().unwrap(), 3);
+        assert_eq!(t1.stats().panics, 1);
+        assert_eq!(t1.stats().executions, 3);
+        // Per executor
+        *counter.write().unwrap() = 0;
+        pool_executor.set_panic_policy(PanicPolicy::StopTask);
+        let t2 = pool_executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(100),
+            schedule_failing(10)
+        );
+        thread::sleep(Duration::from_millis(250));
+        assert!(t2.stopped());
+        assert_eq!(*counter.read().unwrap(), 10);
+        // Per task, overriding the executor
+        *counter.write().unwrap() = 0;
+        let panics = Arc::new(RwLock::new(Vec::new()));
+        let panics_clone = Arc::clone(&panics);
+        let t3 = pool_executor.schedule_fixed_rate(
+            Duration::from_millis(100),
+            Duration::from_millis(100),
+            schedule_failing(100)
+        );
+        t3.set_panic_policy(PanicPolicy::Callback(Box::new(move |payload| {
+            let message = payload.downcast_ref::<&str>().unwrap().to_string();
+            panics_clone.write().unwrap().push(message);
+        })));
+        thread::sleep(Duration::from_millis(350));
+        t3.stop();
+        assert_eq!(*counter.read().unwrap(), 300);
+        assert_eq!(*panics.read().unwrap(), vec!["first run failed".to_owned()]);
+    }
+
+    #[test]
+    fn panic_handler_test() {
+        let panics = Arc::new(RwLock::new(Vec::new()));
+        let handler = |panics: Arc<RwLock<Vec<(String, String)>>>| {
+            Box::new(move |name: &str, payload: Box<dyn Any + Send>| {
+                let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap();
+                panics.write().unwrap().push((name.to_owned(), message));
+            })
+        };
+        let executor = CoreExecutor::new().unwrap();
+        executor.set_panic_handler(handler(Arc::clone(&panics)));
+        let t1 = executor.schedule_once(Duration::from_millis(50), |_handle| panic!("core failed"));
+        t1.set_name("core-task");
+        let t2 = executor.schedule_once(Duration::from_millis(0), |_handle| panic!("unnamed failed"));
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        pool_executor.set_panic_handler(handler(Arc::clone(&panics)));
+        pool_executor.set_panic_policy(PanicPolicy::StopTask);
+        let t3 = pool_executor.schedule_fixed_rate(
+            Duration::from_millis(100),
+            Duration::from_millis(100),
+            |_remote| panic!("pool failed")
+        );
+        t3.set_name("pool-task");
+        // a task with its own callback reports its panics to the handler too
+        let callbacks = Arc::new(AtomicUsize::new(0));
+        let callbacks_clone = Arc::clone(&callbacks);
+        let t4 = executor.schedule_once(Duration::from_millis(150), |_handle| panic!("callback failed"));
+        t4.set_name("callback-task");
+        t4.set_panic_policy(PanicPolicy::Callback(Box::new(move |payload| {
+            assert_eq!(payload.downcast_ref::<&str>(), Some(&"callback failed"));
+            callbacks_clone.fetch_add(1, Ordering::Relaxed);
+        })));
+        thread::sleep(Duration::from_millis(350));
+        assert!(t1.stopped() && t2.stopped() && t3.stopped() && t4.stopped());
+        assert_eq!(callbacks.load(Ordering::Relaxed), 1);
+        assert_eq!(t3.terminated_reason(), Some(TerminationReason::Panicked));
+        let expected = vec![
+            ("<unnamed>".to_owned(), "unnamed failed".to_owned()),
+            ("core-task".to_owned(), "core failed".to_owned()),
+            ("pool-task".to_owned(), "pool failed".to_owned()),
+            ("callback-task".to_owned(), "callback failed".to_owned()),
+        ];
+        assert_eq!(*panics.read().unwrap(), expected);
+    }
+
+    #[test]
+    fn max_consecutive_failures_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        // Fails on runs 0, 1, 3, 4, 5
+        let t1 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(50),
+            move |_handle| {
+                let run = {
+                    let mut counter = counter_clone.write().unwrap();
+                    (*counter) += 1;
+                    *counter
+                };
+                if run != 3 {
+                    panic!("failed run");
+                }
+            }
+        );
+        t1.set_max_consecutive_failures(3);
+        let wait_for = |condition: &dyn Fn() -> bool| {
+            let start = Instant::now();
+            while !condition() {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                thread::sleep(Duration::from_millis(1));
+            }
+        };
+        wait_for(&|| t1.failure_count() == 2);
+        assert!(t1.terminated_reason().is_none());
+        // Reset by the successful run
+        wait_for(&|| t1.failure_count() == 0);
+        assert_eq!(*counter.read().unwrap(), 3);
+        t1.on_stop().wait().unwrap();
+        assert!(t1.stopped());
+        assert_eq!(t1.failure_count(), 3);
+        assert_eq!(t1.terminated_reason(), Some(TerminationReason::TooManyFailures(3)));
+        assert_eq!(*counter.read().unwrap(), 6);
+
+        let t2 = executor.schedule_once(Duration::from_millis(0), |_handle| ());
+        let t3 = executor.schedule_fixed_rate(
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            |_handle| ()
+        );
+        t3.stop();
+        let t4 = executor.schedule_fixed_rate(
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            |_handle| ()
+        );
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(t2.terminated_reason(), Some(TerminationReason::Completed));
+        assert_eq!(t3.terminated_reason(), Some(TerminationReason::Stopped));
+        drop(executor);
+        assert_eq!(t4.terminated_reason(), Some(TerminationReason::ExecutorDropped));
+
+        // Errors count as failures too: runs 1, 3, 4 and 5 fail
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let fallible = |runs: Arc<AtomicUsize>| move || match runs.fetch_add(1, Ordering::SeqCst) + 1 {
+            2 => Ok(()),
+            run => Err(format!("run {} failed", run)),
+        };
+        let (core_runs, pool_runs) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let (core_fn, pool_fn) = (fallible(core_runs.clone()), fallible(pool_runs.clone()));
+        let t5 = executor.schedule_fixed_rate_fallible(Duration::from_millis(20), Duration::from_millis(10), move |_| core_fn());
+        let t6 = pool_executor.schedule_fixed_rate_fallible(Duration::from_millis(20), Duration::from_millis(10), move |_| pool_fn());
+        // Overlapping executions on the pool could complete after the one stopping the task
+        t6.set_overlap_policy(OverlapPolicy::Queue);
+        for task in [&t5, &t6] {
+            task.set_max_consecutive_failures(3);
+        }
+        for (task, runs) in [(t5, core_runs), (t6, pool_runs)] {
+            task.on_stop().wait().unwrap();
+            assert_eq!(task.terminated_reason(), Some(TerminationReason::TooManyFailures(3)));
+            assert_eq!(runs.load(Ordering::SeqCst), 5);
+            assert_eq!(task.stats().panics, 0);
+        }
+    }
+
+    #[test]
+    fn schedule_retry_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let start_time = Instant::now();
+        let mut attempts = 0;
+        let (t1, result) = executor.schedule_retry(
+            RetryPolicy::exponential(Duration::from_millis(50), 2.0, Duration::from_secs(1), 5),
+            move |_handle| {
+                attempts += 1;
+                if attempts < 3 { Err(attempts) } else { Ok(attempts) }
+            }
+        );
+        assert_eq!(result.wait(), Ok(Ok(3)));
+        assert!(start_time.elapsed() >= Duration::from_millis(150));
+        t1.on_stop().wait().unwrap();
+        assert_eq!(t1.stats().executions, 3);
+
+        let (_, result) = executor.schedule_retry(
+            RetryPolicy::fixed(Duration::from_millis(10), 3),
+            |_handle| Err::<(), _>("failed")
+        );
+        assert_eq!(result.wait(), Ok(Err("failed")));
+
+        let start_time = Instant::now();
+        let (t3, result) = executor.schedule_retry(
+            RetryPolicy::fixed(Duration::from_secs(10), 3),
+            |_handle| Err::<(), _>("failed")
+        );
+        thread::sleep(Duration::from_millis(50));
+        t3.stop();
+        assert_eq!(result.wait(), Err(Canceled));
+        assert_eq!(t3.stats().executions, 1);
+        assert!(start_time.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn terminated_executor_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        assert!(executor.is_running());
+        assert!(executor.is_alive());
+        let t1 = executor.try_schedule_fixed_rate(
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            |_handle| ()
+        ).unwrap();
+        // Kills the executor thread
+        executor.inner.remote.spawn(|_| future::lazy(|| -> Result<(), ()> { panic!("core killed") }));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!executor.is_running());
+        assert!(!pool_executor.is_running());
+        assert!(!executor.is_alive());
+        assert!(!pool_executor.is_alive());
+        assert!(!t1.stopped());
+        let result = executor.try_schedule_fixed_interval(Duration::from_millis(0), Duration::from_millis(10), |_handle| ());
+        assert!(matches!(result, Err(ExecutorError::Shutdown)));
+        let result = pool_executor.try_schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(10), |_remote| ());
+        assert!(matches!(result, Err(ExecutorError::Shutdown)));
+        let t2 = executor.schedule_once(Duration::from_millis(0), |_handle| ());
+        assert!(t2.stopped());
+        assert_eq!(t2.terminated_reason(), Some(TerminationReason::ExecutorDropped));
+        // Dropping the executor joins the dead thread without panicking
+        drop(pool_executor);
+        drop(executor);
+        assert_eq!(t1.terminated_reason(), Some(TerminationReason::ExecutorDropped));
+
+        // After a shutdown, the thread stays alive until it terminates
+        let executor = CoreExecutor::new().unwrap();
+        let clone = executor.clone();
+        executor.shutdown(Duration::from_secs(1)).unwrap();
+        assert!(!clone.is_running());
+        thread::sleep(Duration::from_millis(50));
+        assert!(!clone.is_alive());
+    }
+
+    #[test]
+    fn timer_failure_test() {
+        let executor = CoreExecutor::new().unwrap();
+        executor.inner.remote.spawn(|_| {
+            fail_next_timers(1);
+            Ok::<(), ()>(())
+        });
+        let t1 = executor.schedule_once(Duration::from_millis(0), |_handle| ());
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let t2 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(10),
+            move |_handle| {
+                let mut counter = counter_clone.write().unwrap();
+                (*counter) += 1;
+                if *counter == 2 {
+                    // The timer for the third run will fail
+                    fail_next_timers(1);
+                }
+            }
+        );
+        let t3 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(10),
+            |_handle| ()
+        );
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(t1.terminated_reason(), Some(TerminationReason::TimerFailed));
+        assert_eq!(t1.stats().executions, 0);
+        assert_eq!(t2.terminated_reason(), Some(TerminationReason::TimerFailed));
+        assert_eq!(*counter.read().unwrap(), 2);
+        assert!(!t3.stopped());
+        assert!(t3.stats().executions > 5);
+    }
+
+    #[test]
+    fn executor_error_test() {
+        fn create() -> Result<CoreExecutor, io::Error> {
+            Ok(CoreExecutor::new()?)
+        }
+        assert!(create().is_ok());
+        let error = io::Error::from(ExecutorError::ReactorInit(io::Error::other("no reactor")));
+        assert_eq!(error.to_string(), "no reactor");
+        let error = io::Error::from(ExecutorError::Shutdown);
+        assert_eq!(error.to_string(), "the executor thread has terminated");
+        let error = ExecutorError::from(CronError::FieldCount(3));
+        assert_eq!(error.to_string(), "invalid schedule: cron expression should have 5 or 6 fields, found 3");
+    }
+
+    #[test]
+    fn shutdown_test() {
+        fn schedule_slow(executor: &CoreExecutor, done: &Arc<AtomicBool>) {
+            let done_clone = Arc::clone(done);
+            executor.schedule_fixed_rate(
+                Duration::from_millis(0),
+                Duration::from_secs(10),
+                move |_handle| {
+                    thread::sleep(Duration::from_millis(500));
+                    done_clone.store(true, Ordering::Relaxed);
+                }
+            );
         }
-        assert_eq!(*counter.read().unwrap(), 6);
+        let done = Arc::new(AtomicBool::new(false));
+        let executor = CoreExecutor::new().unwrap();
+        schedule_slow(&executor, &done);
+        thread::sleep(Duration::from_millis(50));
+        assert!(executor.shutdown(Duration::from_secs(1)).is_ok());
+        assert!(done.load(Ordering::Relaxed));
+
+        let done = Arc::new(AtomicBool::new(false));
+        let executor = CoreExecutor::new().unwrap();
+        schedule_slow(&executor, &done);
+        thread::sleep(Duration::from_millis(50));
+        let start_time = Instant::now();
+        assert!(matches!(executor.shutdown(Duration::from_millis(100)), Err(ExecutorError::ShutdownTimedOut(1))));
+        assert!(start_time.elapsed() < Duration::from_millis(300));
+        assert!(!done.load(Ordering::Relaxed));
+
+        let done = Arc::new(AtomicBool::new(false));
+        let done_clone = Arc::clone(&done);
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let t1 = pool_executor.schedule_once(Duration::from_millis(0), move |_remote| {
+            thread::sleep(Duration::from_millis(500));
+            done_clone.store(true, Ordering::Relaxed);
+        });
+        let pool_clone = pool_executor.clone();
+        thread::sleep(Duration::from_millis(50));
+        assert!(pool_executor.shutdown(Duration::from_secs(1)).is_ok());
+        assert!(done.load(Ordering::Relaxed));
+        assert!(!pool_clone.is_running());
+        assert_eq!(t1.stats().executions, 1);
+        let t2 = pool_clone.schedule_once(Duration::from_millis(0), |_remote| ());
+        assert_eq!(t2.terminated_reason(), Some(TerminationReason::ExecutorDropped));
+
+        let done = Arc::new(AtomicBool::new(false));
+        let done_clone = Arc::clone(&done);
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        pool_executor.schedule_once(Duration::from_millis(0), move |_remote| {
+            thread::sleep(Duration::from_millis(500));
+            done_clone.store(true, Ordering::Relaxed);
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(matches!(pool_executor.shutdown(Duration::from_millis(100)), Err(ExecutorError::ShutdownTimedOut(1))));
+        assert!(!done.load(Ordering::Relaxed));
+
+        // a grace period too long to be represented has no deadline
+        let done = Arc::new(AtomicBool::new(false));
+        let executor = CoreExecutor::new().unwrap();
+        schedule_slow(&executor, &done);
+        thread::sleep(Duration::from_millis(50));
+        assert!(executor.shutdown(Duration::MAX).is_ok());
+        assert!(done.load(Ordering::Relaxed));
+        assert!(ThreadPoolExecutor::new(1).unwrap().shutdown(Duration::MAX).is_ok());
+    }
+
+    #[test]
+    fn detach_test() {
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let t1 = {
+            let executor = CoreExecutor::new().unwrap();
+            let t1 = executor.schedule_fixed_rate(
+                Duration::from_millis(0),
+                Duration::from_millis(20),
+                move |_handle| { (*counter_clone.write().unwrap()) += 1; }
+            );
+            executor.detach();
+            t1
+        };
+        let counter_clone = Arc::clone(&counter);
+        let t2 = {
+            let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+            let t2 = pool_executor.schedule_fixed_rate(
+                Duration::from_millis(0),
+                Duration::from_millis(20),
+                move |_remote| { (*counter_clone.write().unwrap()) += 100; }
+            );
+            pool_executor.detach();
+            t2
+        };
+        thread::sleep(Duration::from_millis(110));
+        assert!(!t1.stopped() && !t2.stopped());
+        let count = *counter.read().unwrap();
+        assert!(count > 400);
+        assert!(count % 100 > 4);
+        t1.stop();
+        t2.stop();
+    }
+
+    #[test]
+    fn stop_all_tasks_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        let t1 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        let t2 = pool_executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_remote| ());
+        let t3 = executor.schedule_once(Duration::from_secs(10), |_handle| ());
+        let t4 = executor.schedule_once(Duration::from_millis(0), |_handle| ());
+        let t5 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        t5.stop();
+        thread::sleep(Duration::from_millis(50));
+        assert!(t4.stopped());
+        assert_eq!(pool_executor.stop_all_tasks(), 3);
+        assert!(t1.stopped() && t2.stopped() && t3.stopped());
+        assert_eq!(t1.terminated_reason(), Some(TerminationReason::Stopped));
+        assert_eq!(t4.terminated_reason(), Some(TerminationReason::Completed));
+
+        let counter = Arc::new(RwLock::new(0));
+        let counter_clone = Arc::clone(&counter);
+        let t6 = executor.schedule_fixed_rate(
+            Duration::from_millis(0),
+            Duration::from_millis(20),
+            move |_handle| { (*counter_clone.write().unwrap()) += 1; }
+        );
+        thread::sleep(Duration::from_millis(50));
+        assert!(!t6.stopped());
+        assert!(*counter.read().unwrap() > 1);
+        assert_eq!(executor.stop_all_tasks(), 1);
+        assert_eq!(executor.stop_all_tasks(), 0);
+    }
+
+    #[test]
+    fn tasks_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        let t1 = executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(20), |_handle| ());
+        t1.set_name("fixed-rate");
+        let t2 = pool_executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_remote| ());
+        let t3 = executor.schedule_once(Duration::from_secs(10), |_handle| ());
+        let t4 = executor.schedule_cron("0 0 * * *", |_handle| ()).unwrap();
+        t4.pause();
+        let t5 = executor.schedule_once(Duration::from_millis(0), |_handle| ());
+        thread::sleep(Duration::from_millis(50));
+        let tasks = pool_executor.tasks();
+        assert_eq!(tasks.iter().map(|task| task.id).collect::<Vec<_>>(), vec![t1.id(), t2.id(), t3.id(), t4.id()]);
+        assert!(!tasks.iter().any(|task| task.id == t5.id()));
+        assert_eq!(tasks[0].name, Some("fixed-rate".to_owned()));
+        assert_eq!(tasks[0].kind, ScheduleKind::FixedRate);
+        assert_eq!(tasks[0].interval, Some(Duration::from_millis(20)));
+        assert!(tasks[0].runs >= 2);
+        assert_eq!(tasks[1].kind, ScheduleKind::FixedRate);
+        assert_eq!(tasks[1].runs, 0);
+        assert_eq!(tasks[2].kind, ScheduleKind::Once);
+        assert_eq!(tasks[2].interval, None);
+        assert_eq!(tasks[3].kind, ScheduleKind::Cron);
+        assert!(tasks[3].paused);
+        assert!(tasks.iter().all(|task| !task.stopped));
+        t1.stop();
+        assert_eq!(executor.tasks().len(), 3);
+    }
+
+    #[test]
+    fn named_tasks_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        let t1 = executor.schedule_fixed_rate_named("flush", Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        let t2 = pool_executor.schedule_fixed_rate_named("flush", Duration::from_secs(10), Duration::from_secs(10), |_remote| ());
+        let t3 = executor.schedule_fixed_interval_named("cleanup", Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        let t4 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        assert_eq!(t1.name(), Some("flush".to_owned()));
+        assert_eq!(t4.name(), None);
+        assert!(!executor.stop_task("missing"));
+        assert!(pool_executor.stop_task("flush"));
+        assert!(t1.stopped() && t2.stopped());
+        assert!(!t3.stopped() && !t4.stopped());
+        assert!(!executor.stop_task("flush"));
+        assert!(executor.stop_task("cleanup"));
+        assert!(t3.stopped());
+        assert!(!t4.stopped());
+    }
+
+    #[test]
+    fn tagged_tasks_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        let t1 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        t1.add_tag("tenant:acme");
+        t1.add_tag("low-priority");
+        let t2 = pool_executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_remote| ());
+        t2.add_tag("tenant:acme");
+        let t3 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        t3.add_tag("low-priority");
+        assert_eq!(t1.tags(), vec!["low-priority".to_owned(), "tenant:acme".to_owned()]);
+        let tagged = executor.tasks_tagged("tenant:acme");
+        assert_eq!(tagged.iter().map(|task| task.id).collect::<Vec<_>>(), vec![t1.id(), t2.id()]);
+        assert_eq!(tagged[1].tags, vec!["tenant:acme".to_owned()]);
+        assert_eq!(executor.pause_tagged("low-priority"), 2);
+        assert!(t1.paused() && t3.paused() && !t2.paused());
+        assert_eq!(pool_executor.resume_tagged("low-priority"), 2);
+        assert!(!t1.paused() && !t3.paused());
+        assert_eq!(pool_executor.stop_tagged("tenant:acme"), 2);
+        assert!(t1.stopped() && t2.stopped() && !t3.stopped());
+        assert_eq!(executor.stop_tagged("tenant:acme"), 0);
+        assert!(executor.tasks_tagged("tenant:acme").is_empty());
+        t3.remove_tag("low-priority");
+        assert_eq!(executor.stop_tagged("low-priority"), 0);
+        assert!(!t3.stopped());
+
+        // A stopped task is not paused, even while its last execution is in progress
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let (started_tx, release_rx) = (Mutex::new(started_tx), Mutex::new(release_rx));
+        let t4 = pool_executor.task()
+            .every(Duration::from_secs(10))
+            .tag("slow")
+            .spawn(move |_remote| {
+                started_tx.lock().unwrap().send(()).unwrap();
+                let _ = release_rx.lock().unwrap().recv();
+            })
+            .unwrap();
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        t4.stop();
+        assert_eq!(executor.tasks_tagged("slow").len(), 1);
+        assert_eq!(executor.pause_tagged("slow"), 0);
+        assert!(!t4.paused());
+        release_tx.send(()).unwrap();
+        assert!(t4.stop_and_wait(None));
+    }
+
+    #[test]
+    fn task_set_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let task_set = TaskSet::new();
+        let t1 = executor.schedule_fixed_rate_in(&task_set, Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        let t2 = pool_executor.schedule_fixed_rate_in(
+            &task_set,
+            Duration::from_millis(0),
+            Duration::from_secs(10),
+            |_remote| thread::sleep(Duration::from_millis(200))
+        );
+        let t3 = executor.schedule_fixed_interval(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        task_set.add(t3.clone());
+        let t4 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        thread::sleep(Duration::from_millis(50));
+        assert!(!task_set.stopped());
+        t1.stop();
+        assert!(!task_set.stopped());
+        assert!(!task_set.stop_and_wait(Some(Duration::from_millis(10))));
+        assert!(task_set.stopped());
+        assert!(task_set.stop_and_wait(None));
+        assert!(task_set.stop_and_wait(Some(Duration::MAX)));
+        assert_eq!(t2.stats().executions, 1);
+        assert!(t3.stopped());
+        assert!(!t4.stopped());
+
+        // Dropping the set stops the tasks only if requested
+        let t5 = {
+            let task_set = TaskSet::new();
+            executor.schedule_fixed_rate_in(&task_set, Duration::from_secs(10), Duration::from_secs(10), |_handle| ())
+        };
+        let t6 = {
+            let task_set = TaskSet::new();
+            task_set.stop_on_drop(true);
+            pool_executor.schedule_fixed_rate_in(&task_set, Duration::from_secs(10), Duration::from_secs(10), |_remote| ())
+        };
+        assert!(!t5.stopped());
+        assert!(t6.stopped());
+    }
+
+    #[test]
+    fn child_tasks_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        let children = Arc::new(RwLock::new(Vec::new()));
+        let counter = Arc::new(RwLock::new(0));
+        let children_clone = Arc::clone(&children);
+        let counter_clone = Arc::clone(&counter);
+        let executor_clone = executor.clone();
+        let parent = executor.schedule_fixed_rate_ctx(
+            Duration::from_millis(0),
+            Duration::from_millis(20),
+            move |_handle, context| {
+                let counter_clone = Arc::clone(&counter_clone);
+                let child = executor_clone.schedule_fixed_rate(
+                    Duration::from_millis(0),
+                    Duration::from_millis(10),
+                    move |_handle| { (*counter_clone.write().unwrap()) += 1; }
+                );
+                assert!(context.task_handle().add_child(&child));
+                children_clone.write().unwrap().push(child);
+            }
+        );
+        thread::sleep(Duration::from_millis(110));
+        let first_child = children.read().unwrap()[0].clone();
+        first_child.stop();
+        assert!(!parent.stopped());
+        let grandchild = pool_executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_remote| ());
+        let second_child = children.read().unwrap()[1].clone();
+        assert!(second_child.add_child(&grandchild));
+        // No cycles, and a single parent
+        assert!(!grandchild.add_child(&parent));
+        assert!(!parent.add_child(&grandchild));
+        parent.stop();
+        thread::sleep(Duration::from_millis(50));
+        let count = *counter.read().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(*counter.read().unwrap(), count);
+        let children = children.read().unwrap();
+        assert!(children.len() >= 5);
+        assert!(children.iter().all(|child| child.stopped()));
+        assert_eq!(first_child.terminated_reason(), Some(TerminationReason::Stopped));
+        assert_eq!(second_child.terminated_reason(), Some(TerminationReason::ParentStopped));
+        assert_eq!(grandchild.terminated_reason(), Some(TerminationReason::ParentStopped));
+        // Children added to a stopped parent are stopped right away
+        let late_child = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        assert!(parent.add_child(&late_child));
+        assert!(late_child.stopped());
+    }
+
+    #[test]
+    fn scoped_task_handle_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let t1 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        let t2 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        {
+            let scoped = t1.clone().into_scoped();
+            let scoped_clone = scoped.clone();
+            drop(scoped);
+            assert!(!scoped_clone.stopped());
+            assert!(!t1.stopped());
+        }
+        assert!(t1.stopped());
+        assert_eq!(t1.terminated_reason(), Some(TerminationReason::Stopped));
+        drop(t2.clone());
+        assert!(!t2.stopped());
+    }
+
+    #[test]
+    fn stop_transition_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let t1 = executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ());
+        assert_eq!(t1.stopped_at(), None);
+        let handles = (0..4).map(|_| t1.clone()).collect::<Vec<_>>();
+        let threads = handles.into_iter()
+            .map(|handle| thread::spawn(move || handle.stop()))
+            .collect::<Vec<_>>();
+        let results = threads.into_iter().map(|thread| thread.join().unwrap()).collect::<Vec<_>>();
+        assert_eq!(results.iter().filter(|&&result| result).count(), 1);
+        let stopped_at = t1.stopped_at().unwrap();
+        assert!(stopped_at <= Instant::now());
+        assert!(!t1.stop());
+        assert_eq!(t1.stopped_at(), Some(stopped_at));
+
+        let t2 = executor.schedule_once(Duration::from_millis(0), |_handle| ());
+        thread::sleep(Duration::from_millis(50));
+        assert!(t2.stopped_at().is_some());
+        assert!(!t2.stop());
+        assert_eq!(t2.terminated_reason(), Some(TerminationReason::Completed));
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl RecordingObserver {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    impl ExecutorObserver for RecordingObserver {
+        fn on_schedule(&self, task: &TaskInfo) {
+            self.record(format!("schedule {}", task.name.as_deref().unwrap_or("")));
+        }
+
+        fn on_run_start(&self, context: &TaskContext) {
+            self.record(format!("start {} on {}", context.run(), thread::current().name().unwrap_or("")));
+        }
+
+        fn on_run_end(&self, context: &TaskContext, _took: Duration) {
+            self.record(format!("end {} on {}", context.run(), thread::current().name().unwrap_or("")));
+        }
+
+        fn on_panic(&self, _context: &TaskContext, message: &str) {
+            self.record(format!("panic {}", message));
+        }
+
+        fn on_stop(&self, task: &TaskInfo, reason: TerminationReason) {
+            self.record(format!("stop {} {:?}", task.name.as_deref().unwrap_or(""), reason));
+        }
+    }
+
+    #[test]
+    fn observer_test() {
+        let observer = Arc::new(RecordingObserver::default());
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        pool_executor.set_observer(observer.clone());
+        let task = pool_executor.schedule_fixed_rate_named("task", Duration::from_millis(0), Duration::from_secs(10), |_handle| {
+            panic!("boom");
+        });
+        thread::sleep(Duration::from_millis(100));
+        task.stop();
+        let events = observer.events.lock().unwrap().clone();
+        assert_eq!(events, vec![
+            "schedule task".to_owned(),
+            "start 0 on pool_thread_0".to_owned(),
+            "panic boom".to_owned(),
+            "end 0 on pool_thread_0".to_owned(),
+            "stop task Stopped".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn metrics_test() {
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let task = pool_executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(10), |_remote| ());
+        let _failing = pool_executor.schedule_once(Duration::from_millis(0), |_remote| panic!("failing"));
+        thread::sleep(Duration::from_millis(100));
+        let metrics = pool_executor.metrics();
+        assert_eq!(metrics.live_tasks, 1);
+        assert!(metrics.executions >= 5, "{:?}", metrics);
+        assert_eq!(metrics.recent_executions, metrics.executions);
+        assert_eq!(metrics.panics, 1);
+        assert_eq!(metrics.queued, 0);
+        task.stop();
+        thread::sleep(Duration::from_millis(50));
+        let metrics = pool_executor.metrics();
+        assert_eq!(metrics.live_tasks, 0);
+        assert!(metrics.recent_executions <= 1, "{:?}", metrics);
+
+        let _blocking = pool_executor.schedule_once(Duration::from_millis(0), |_remote| thread::sleep(Duration::from_millis(200)));
+        thread::sleep(Duration::from_millis(50));
+        let _queued = pool_executor.schedule_once(Duration::from_millis(0), |_remote| ());
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(pool_executor.metrics().queued, 1);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(pool_executor.metrics().queued, 0);
+    }
+
+    #[test]
+    fn duration_histogram_test() {
+        assert_eq!(Histogram::default().percentile(50.0), None);
+        assert_eq!(Histogram::default().max(), None);
+
+        let executor = CoreExecutor::new().unwrap();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        let task = executor.schedule_fixed_interval(Duration::from_millis(0), Duration::from_millis(0), move |_handle| {
+            let sleep = if runs_clone.fetch_add(1, Ordering::Relaxed) % 10 == 9 { 30 } else { 0 };
+            thread::sleep(Duration::from_millis(sleep));
+        });
+        while task.stats().executions < 20 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        task.stop_and_wait(None);
+        let histogram = task.duration_histogram();
+        assert_eq!(histogram.count(), task.stats().executions);
+        assert!(histogram.percentile(50.0).unwrap() <= Duration::from_millis(1));
+        assert!(histogram.percentile(100.0).unwrap() >= Duration::from_millis(30));
+        assert!(histogram.percentile(100.0).unwrap() <= histogram.max().unwrap());
+        assert!(histogram.max().unwrap() < Duration::from_millis(64));
+    }
+
+    #[test]
+    fn overrun_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let runs = AtomicUsize::new(0);
+        let task = executor.schedule_fixed_interval(Duration::from_millis(0), Duration::from_millis(100), move |_handle| {
+            if runs.fetch_add(1, Ordering::Relaxed) == 0 {
+                thread::sleep(Duration::from_millis(150));
+            }
+        });
+        let overruns = Arc::new(Mutex::new(Vec::new()));
+        let overruns_clone = overruns.clone();
+        task.on_overrun(move |overrun| overruns_clone.lock().unwrap().push(overrun));
+        thread::sleep(Duration::from_millis(350));
+        task.stop();
+        let overruns = overruns.lock().unwrap();
+        assert_eq!(overruns.len(), 1);
+        assert_eq!(overruns[0].task, task.id());
+        assert_eq!(overruns[0].run, 0);
+        assert_eq!(overruns[0].interval, Duration::from_millis(100));
+        assert!(overruns[0].took >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn watchdog_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let stalls = Arc::new(Mutex::new(Vec::new()));
+        let stalls_clone = stalls.clone();
+        executor.enable_watchdog(Duration::from_millis(50), move |late| stalls_clone.lock().unwrap().push(late)).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert!(stalls.lock().unwrap().is_empty());
+
+        executor.schedule_once(Duration::from_millis(0), |_handle| thread::sleep(Duration::from_millis(300)));
+        thread::sleep(Duration::from_millis(500));
+        let reported = stalls.lock().unwrap().clone();
+        assert!(!reported.is_empty());
+        assert!(reported.iter().all(|&late| late >= Duration::from_millis(50)));
+        assert!(reported.windows(2).all(|lates| lates[0] < lates[1]));
+
+        executor.shutdown(Duration::from_secs(1)).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(stalls.lock().unwrap().len(), reported.len());
+    }
+
+    #[test]
+    fn execution_timeout_test() {
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        let task = pool_executor.schedule_fixed_rate(Duration::from_millis(50), Duration::from_millis(100), move |_remote| {
+            if runs_clone.fetch_add(1, Ordering::Relaxed) == 0 {
+                thread::sleep(Duration::from_millis(150));
+            }
+        });
+        task.set_execution_timeout(Duration::from_millis(50));
+        thread::sleep(Duration::from_millis(320));
+        task.stop_and_wait(None);
+        let stats = task.stats();
+        assert_eq!(stats.timeouts, 1);
+        assert_eq!(stats.executions, 3);
+        assert_eq!(runs.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn pool_fixed_interval_test() {
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let starts = Arc::new(Mutex::new(Vec::new()));
+        let starts_clone = starts.clone();
+        let task = pool_executor.schedule_fixed_interval(Duration::from_millis(0), Duration::from_millis(100), move |_remote| {
+            starts_clone.lock().unwrap().push(Instant::now());
+            thread::sleep(Duration::from_millis(300));
+        });
+        assert_eq!(task.overlap_policy(), OverlapPolicy::Queue);
+        thread::sleep(Duration::from_millis(1000));
+        task.stop_and_wait(None);
+        let starts = starts.lock().unwrap();
+        assert_eq!(starts.len(), 3);
+        for pair in starts.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap >= Duration::from_millis(400) && gap < Duration::from_millis(500), "{:?}", gap);
+        }
+        assert_eq!(task.stats().executions, 3);
+    }
+
+    #[test]
+    fn overlap_policy_test() {
+        let pool_executor = ThreadPoolExecutor::new(4).unwrap();
+        let starts = Arc::new(Mutex::new(Vec::new()));
+        let starts_clone = starts.clone();
+        let skipping = pool_executor.schedule_fixed_rate(Duration::from_millis(100), Duration::from_millis(100), move |_remote| {
+            starts_clone.lock().unwrap().push(Instant::now());
+            thread::sleep(Duration::from_millis(250));
+        });
+        assert_eq!(skipping.overlap_policy(), OverlapPolicy::Allow);
+        skipping.set_overlap_policy(OverlapPolicy::Skip);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        let allowing = pool_executor.schedule_fixed_rate(Duration::from_millis(100), Duration::from_millis(100), move |_remote| {
+            runs_clone.fetch_add(1, Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(250));
+        });
+        allowing.set_overlap_policy(OverlapPolicy::Allow);
+        thread::sleep(Duration::from_millis(950));
+        skipping.stop_and_wait(None);
+        allowing.stop_and_wait(None);
+        // Ticks at 100, 200, ..., 900ms: the skipping task runs at 100, 400 and 700ms
+        let starts = starts.lock().unwrap();
+        assert_eq!(starts.len(), 3);
+        for pair in starts.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap >= Duration::from_millis(250) && gap < Duration::from_millis(350), "{:?}", gap);
+        }
+        assert_eq!(skipping.stats().skipped, 6);
+        assert_eq!(allowing.stats().skipped, 0);
+        assert_eq!(runs.load(Ordering::Relaxed), 9);
+    }
+
+    #[test]
+    fn concurrency_limit_test() {
+        let pool_executor = ThreadPoolExecutor::new(4).unwrap();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let schedule = |queue: bool| {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            let task = pool_executor.schedule_fixed_rate(Duration::from_millis(100), Duration::from_millis(100), move |_remote| {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(250));
+                running.fetch_sub(1, Ordering::SeqCst);
+            });
+            task.set_overlap_policy(OverlapPolicy::Concurrent(2));
+            task.set_queue_at_limit(queue);
+            task
+        };
+
+        // Ticks at 100, 200, ..., 900ms: runs at 100, 200, 400, 500, 700 and 800ms
+        let skipping = schedule(false);
+        thread::sleep(Duration::from_millis(950));
+        skipping.stop_and_wait(None);
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        assert_eq!(skipping.stats().executions, 6);
+        assert_eq!(skipping.stats().skipped, 3);
+
+        // Runs at 100 and 200ms, then as soon as one completes: 350, 450, 600, 700 and 850ms, the
+        // next one being due after the task is stopped at 900ms
+        max_running.store(0, Ordering::SeqCst);
+        let queueing = schedule(true);
+        thread::sleep(Duration::from_millis(900));
+        queueing.stop_and_wait(None);
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        assert_eq!(queueing.stats().executions, 7);
+        assert_eq!(queueing.stats().skipped, 0);
+    }
+
+    #[test]
+    fn pool_fn_mut_test() {
+        let pool_executor = ThreadPoolExecutor::new(4).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let mut buffer = Vec::new();
+        let task = pool_executor.schedule_fixed_rate_mut(Duration::from_millis(0), Duration::from_millis(10), move |_remote| {
+            buffer.push(buffer.len());
+            sender.send(buffer.clone()).unwrap();
+        });
+        assert_eq!(task.overlap_policy(), OverlapPolicy::Queue);
+        assert_eq!(receiver.recv().unwrap(), vec![0]);
+        assert_eq!(receiver.recv().unwrap(), vec![0, 1]);
+        assert_eq!(receiver.recv().unwrap(), vec![0, 1, 2]);
+        task.stop();
+
+        let sequence_task = pool_executor.schedule_with_mut(DelaySequence::new(vec![Duration::from_millis(0); 3].into_iter()), |_remote| ());
+        assert_eq!(sequence_task.overlap_policy(), OverlapPolicy::Queue);
+    }
+
+    #[test]
+    fn affinity_test() {
+        let pool_executor = ThreadPoolExecutor::new(4).unwrap();
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let tasks = (0..3).map(|index| {
+            let names = names.clone();
+            let task = pool_executor.schedule_fixed_rate(Duration::from_millis(20), Duration::from_millis(5), move |_remote| {
+                names.lock().unwrap().push((index, thread::current().name().unwrap().to_owned()));
+            });
+            task.set_overlap_policy(OverlapPolicy::Allow);
+            task.set_affinity(if index == 2 { 5 } else { 1 });
+            task
+        }).collect::<Vec<_>>();
+        thread::sleep(Duration::from_millis(100));
+        for task in &tasks {
+            task.stop_and_wait(None);
+        }
+        let names = names.lock().unwrap();
+        assert!(names.len() > 10);
+        // Keys 1 and 5 are both bound to the second of the four threads
+        assert!(names.iter().all(|(_, name)| name == "pool_thread_affine_1_0"), "{:?}", names);
+    }
+
+    #[test]
+    fn with_pool_test() {
+        let pool = Builder::new().pool_size(1).name_prefix("app_pool_").create();
+        let pool_executor = ThreadPoolExecutor::with_pool(pool.clone(), CoreExecutor::new().unwrap());
+        let (_task, result) = pool_executor.schedule_once_result(Duration::from_millis(0), |_remote| {
+            thread::current().name().map(|name| name.to_owned())
+        });
+        assert_eq!(result.wait().unwrap(), Some("app_pool_0".to_owned()));
+        let task = pool_executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_secs(10), |_remote| {
+            assert_eq!(thread::current().name(), Some("app_pool_0"));
+        });
+        task.set_affinity(3);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(task.stats().executions, 1);
+        assert_eq!(task.stats().panics, 0);
+        // The pool is still usable by the application
+        assert_eq!(pool.spawn_fn(|| Ok::<_, ()>(42)).wait(), Ok(42));
+    }
+
+    #[test]
+    fn run_now_test() {
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let result = pool_executor.run_now(|| thread::current().name().map(|name| name.to_owned()));
+        assert_eq!(result.wait(), Ok(Some("pool_thread_0".to_owned())));
+        let chained = pool_executor.run_now(|| 20).map(|value| value * 2);
+        assert_eq!(chained.wait(), Ok(40));
+
+        let start = Instant::now();
+        let delayed = pool_executor.run_after(Duration::from_millis(50), || 42);
+        assert_eq!(delayed.wait(), Ok(42));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        let failed = pool_executor.run_after(Duration::from_millis(0), || -> u32 { panic!("failed") });
+        assert_eq!(failed.wait(), Err(Canceled));
+    }
+
+    #[test]
+    fn futures_executor_test() {
+        fn spawn_through<E: Executor<Box<dyn Future<Item = (), Error = ()> + Send>>>(executor: &E) -> Receiver<Option<String>> {
+            let (sender, receiver) = oneshot::channel();
+            let future = future::lazy(move || {
+                let _ = sender.send(thread::current().name().map(|name| name.to_owned()));
+                Ok(())
+            });
+            executor.execute(Box::new(future)).unwrap();
+            receiver
+        }
+
+        let executor = CoreExecutor::with_name("futures_executor").unwrap();
+        assert_eq!(spawn_through(&executor).wait(), Ok(Some("futures_executor".to_owned())));
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        assert_eq!(spawn_through(&pool_executor).wait(), Ok(Some("pool_thread_0".to_owned())));
+
+        executor.clone().shutdown(Duration::from_secs(1)).unwrap();
+        let error = executor.execute(future::ok(())).unwrap_err();
+        assert_eq!(error.kind(), ExecuteErrorKind::Shutdown);
+        let error = pool_executor.execute(future::ok(())).unwrap_err();
+        assert_eq!(error.kind(), ExecuteErrorKind::Shutdown);
+    }
+
+    #[test]
+    fn spawn_test() {
+        let executor = CoreExecutor::with_name("spawn_executor").unwrap();
+        let (sender, receiver) = oneshot::channel();
+        executor.spawn(future::lazy(move || {
+            let _ = sender.send(thread::current().name().map(|name| name.to_owned()));
+            Ok(())
+        })).unwrap();
+        assert_eq!(receiver.wait(), Ok(Some("spawn_executor".to_owned())));
+        let result = executor.spawn_fn(|handle| {
+            let (sender, receiver) = oneshot::channel();
+            handle.spawn(future::lazy(move || sender.send(42).map_err(|_| ())));
+            receiver
+        }).unwrap();
+        assert_eq!(result.wait().unwrap().wait(), Ok(42));
+
+        executor.clone().shutdown(Duration::from_secs(1)).unwrap();
+        assert!(matches!(executor.spawn(future::ok(())), Err(ExecutorError::Shutdown)));
+        assert!(matches!(executor.spawn_fn(|_handle| ()), Err(ExecutorError::Shutdown)));
+    }
+
+    #[test]
+    fn remote_test() {
+        let executor = CoreExecutor::with_name("remote_executor").unwrap();
+        let pool_executor = ThreadPoolExecutor::with_executor(1, "pool_thread_", executor.clone());
+        assert!(executor.remote().handle().is_none());
+        let (sender, receiver) = oneshot::channel();
+        pool_executor.remote().spawn(move |handle| {
+            let remote = handle.remote().clone();
+            let _ = sender.send((thread::current().name().map(|name| name.to_owned()), remote.handle().is_some()));
+            Ok::<(), ()>(())
+        });
+        assert_eq!(receiver.wait(), Ok((Some("remote_executor".to_owned()), true)));
+        assert_eq!(executor.remote().id(), pool_executor.remote().id());
+    }
+
+    #[test]
+    fn from_remote_test() {
+        let mut core = Core::new().unwrap();
+        let executor = CoreExecutor::from_remote(core.remote());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        // The tasks run on the thread of the event loop
+        let core_thread = thread::current().id();
+        let task = executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(20), move |_handle| {
+            assert_eq!(thread::current().id(), core_thread);
+            runs_clone.fetch_add(1, Ordering::Relaxed);
+        });
+        // Nothing runs until the application runs its event loop
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(runs.load(Ordering::Relaxed), 0);
+        let timeout = Timeout::new(Duration::from_millis(90), &core.handle()).unwrap();
+        core.run(timeout).unwrap();
+        let ran = runs.load(Ordering::Relaxed);
+        assert!(ran == 4 || ran == 5, "{} runs", ran);
+        assert!(executor.is_running());
+
+        drop(executor);
+        assert_eq!(task.terminated_reason(), Some(TerminationReason::ExecutorDropped));
+        let timeout = Timeout::new(Duration::from_millis(50), &core.handle()).unwrap();
+        core.run(timeout).unwrap();
+        assert_eq!(runs.load(Ordering::Relaxed), ran);
+    }
+
+    #[test]
+    fn run_on_current_thread_test() {
+        let thread_name = thread::current().name().map(|name| name.to_owned());
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticks_clone = ticks.clone();
+        let mut slow_task = None;
+        CoreExecutor::run_on_current_thread(|executor| {
+            let (sender, receiver) = oneshot::channel();
+            let mut sender = Some(sender);
+            executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(10), move |_handle| {
+                assert_eq!(thread::current().name().map(|name| name.to_owned()), thread_name);
+                if ticks_clone.fetch_add(1, Ordering::Relaxed) == 2 {
+                    let _ = sender.take().unwrap().send(());
+                }
+            });
+            slow_task = Some(executor.schedule_fixed_rate(Duration::from_secs(10), Duration::from_secs(10), |_handle| ()));
+            receiver.map_err(|_| ())
+        }).unwrap();
+        assert_eq!(ticks.load(Ordering::Relaxed), 3);
+        assert_eq!(slow_task.unwrap().terminated_reason(), Some(TerminationReason::ExecutorDropped));
+
+        let start = Instant::now();
+        CoreExecutor::run_on_current_thread(|executor| {
+            let executor = executor.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                executor.shutdown(Duration::from_secs(1)).unwrap();
+            });
+            future::empty()
+        }).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn interval_stream_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let stream = executor.interval_stream(Duration::from_millis(0), Duration::from_millis(100));
+        let task_handle = stream.handle().clone();
+        let start = Instant::now();
+        let ticks = stream.take(3).wait().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(ticks.len(), 3);
+        for (i, tick) in ticks.iter().enumerate() {
+            let offset = tick.duration_since(start).as_millis() as u64;
+            assert!(offset >= i as u64 * 100 && offset < i as u64 * 100 + 50, "tick {} at {}ms", i, offset);
+        }
+        // dropping the stream stopped the task
+        assert!(task_handle.stopped());
+
+        let mut stream = executor.interval_stream_with(FixedInterval::new(Duration::from_millis(0), Duration::from_millis(50)))
+            .wait();
+        stream.next().unwrap().unwrap();
+        thread::sleep(Duration::from_millis(275));
+        // the missed ticks are coalesced into one
+        stream.next().unwrap().unwrap();
+        assert!(stream.get_ref().missed() >= 3);
+        stream.get_ref().handle().stop();
+        let rest = stream.collect::<Result<Vec<_>, _>>().unwrap();
+        assert!(rest.len() <= 1);
+    }
+
+    #[test]
+    fn schedule_results_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let mut counter = 0;
+        let (task_handle, results) = executor.schedule_fixed_rate_results(
+            Duration::from_millis(0), Duration::from_millis(20), 2, OverflowPolicy::DropOldest, move |_| {
+                counter += 1;
+                counter
+            });
+        thread::sleep(Duration::from_millis(150));
+        task_handle.stop();
+        assert!(results.dropped() >= 4);
+        let values = results.wait().collect::<Result<Vec<_>, _>>().unwrap();
+        // only the latest values were kept
+        assert_eq!(values.len(), 2);
+        assert!(values[0] > 4 && values[1] == values[0] + 1);
+
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let mut counter = 0;
+        let (task_handle, results) = pool_executor.schedule_fixed_rate_results(
+            Duration::from_millis(0), Duration::from_millis(10), 1, OverflowPolicy::Block, move |_| {
+                counter += 1;
+                (counter, thread::current().name().unwrap().to_owned())
+            });
+        let mut results = results.wait();
+        for i in 1..6 {
+            // the schedule waits for the consumer, so no value is lost
+            let (value, thread_name) = results.next().unwrap().unwrap();
+            assert_eq!(value, i);
+            assert!(thread_name.starts_with("pool_thread_"));
+            thread::sleep(Duration::from_millis(30));
+        }
+        assert_eq!(results.get_ref().dropped(), 0);
+        task_handle.stop();
+        assert!(results.count() <= 1);
+    }
+
+    #[test]
+    fn schedule_sink_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (sender, receiver) = futures_mpsc::channel(0);
+        let mut counter = 0;
+        let task_handle = executor.schedule_sink(Duration::from_millis(0), Duration::from_millis(10), sender, move || {
+            counter += 1;
+            counter
+        });
+        let mut receiver = receiver.wait();
+        for i in 1..6 {
+            // the sink holds at most one item per sender, so none is lost while the consumer is slow
+            assert_eq!(receiver.next().unwrap(), Ok(i));
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(task_handle.stats().executions < 9);
+        assert!(!task_handle.stopped());
+        drop(receiver);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(task_handle.terminated_reason(), Some(TerminationReason::SinkFailed));
+        assert!(task_handle.error().is_some());
+    }
+
+    #[test]
+    fn tick_channel_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (task_handle, receiver) = executor.tick_channel(Duration::from_millis(0), Duration::from_millis(50));
+        let start = Instant::now();
+        for i in 0..3 {
+            let offset = receiver.recv().unwrap().duration_since(start).as_millis() as u64;
+            assert!(offset >= i * 50 && offset < i * 50 + 30, "tick {} at {}ms", i, offset);
+        }
+        assert!(!task_handle.stopped());
+        drop(receiver);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(task_handle.terminated_reason(), Some(TerminationReason::Stopped));
+    }
+
+    #[test]
+    fn debouncer_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counter_clone = counter.clone();
+        let debouncer = executor.debouncer(Duration::from_millis(50), move |_| {
+            counter_clone.fetch_add(1, Ordering::SeqCst);
+        });
+        let clone = debouncer.clone();
+        thread::spawn(move || {
+            for _ in 0..10 {
+                clone.trigger();
+                thread::sleep(Duration::from_millis(10));
+            }
+        }).join().unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        // spaced triggers run separately
+        for _ in 0..3 {
+            debouncer.trigger();
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+
+        debouncer.trigger();
+        debouncer.flush();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(counter.load(Ordering::SeqCst), 5);
+        debouncer.trigger();
+        assert!(debouncer.cancel());
+        assert!(!debouncer.cancel());
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(counter.load(Ordering::SeqCst), 5);
+        assert_eq!(debouncer.handle().stats().executions, 5);
+    }
+
+    #[test]
+    fn debouncer_panic_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut runs = 0;
+        let debouncer = executor.debouncer(Duration::from_millis(10), move |_| {
+            runs += 1;
+            tx.send(()).unwrap();
+            if runs == 1 {
+                panic!("first run");
+            }
+        });
+        for _ in 0..3 {
+            debouncer.trigger();
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+        assert_eq!(debouncer.handle().stats().panics, 1);
+        assert!(!debouncer.handle().stopped());
+    }
+
+    #[test]
+    fn throttler_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let counters = [ThrottleMode::Leading, ThrottleMode::Trailing, ThrottleMode::LeadingAndTrailing].iter().map(|&mode| {
+            let counter = Arc::new(AtomicUsize::new(0));
+            let counter_clone = counter.clone();
+            let throttle = executor.throttler_with_mode(Duration::from_millis(100), mode, move |_| {
+                counter_clone.fetch_add(1, Ordering::SeqCst);
+            });
+            (throttle, counter)
+        }).collect::<Vec<_>>();
+        let counts = || counters.iter().map(|(_, counter)| counter.load(Ordering::SeqCst)).collect::<Vec<_>>();
+        for _ in 0..5 {
+            for (throttle, _) in &counters {
+                throttle.trigger();
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(counts(), vec![1, 0, 1]);
+        thread::sleep(Duration::from_millis(100));
+        // the triggers during the window resulted in a single trailing execution
+        assert_eq!(counts(), vec![1, 1, 2]);
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(counts(), vec![1, 1, 2]);
+    }
+
+    #[test]
+    fn throttler_panic_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut runs = 0;
+        let throttle = executor.throttler(Duration::from_millis(10), move |_| {
+            runs += 1;
+            tx.send(()).unwrap();
+            if runs == 1 {
+                panic!("first run");
+            }
+        });
+        for _ in 0..3 {
+            throttle.trigger();
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+        assert_eq!(throttle.handle().stats().panics, 1);
+        assert!(!throttle.handle().stopped());
+    }
+
+    #[test]
+    fn schedule_triggered_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (trigger_tx, trigger_rx) = futures_mpsc::unbounded();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        let task_handle = executor.schedule_fixed_interval_triggered(Duration::from_millis(300), Duration::from_millis(300), trigger_rx, move |_| {
+            runs_clone.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(100));
+        });
+        trigger_tx.unbounded_send(()).unwrap();
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        // triggers arriving during an execution are coalesced
+        for _ in 0..3 {
+            trigger_tx.unbounded_send(()).unwrap();
+        }
+        thread::sleep(Duration::from_millis(370));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        // the interval restarted after the follow-up run, which completed at 200ms
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        task_handle.stop();
+    }
+
+    #[test]
+    fn schedule_chain_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let stages = Arc::new(Mutex::new(Vec::new()));
+        let (first, second, third) = (stages.clone(), stages.clone(), stages.clone());
+        let chain = ScheduledChain::new()
+            .then(move |_: &Handle| first.lock().unwrap().push("collect"))
+            .then(move |_: &Handle| {
+                let mut stages = second.lock().unwrap();
+                stages.push("check");
+                if stages.len() > 3 {
+                    drop(stages);
+                    panic!("check failed");
+                }
+            })
+            .then(move |_: &Handle| third.lock().unwrap().push("flush"));
+        let task_handle = executor.schedule_fixed_rate_chain(Duration::from_millis(0), Duration::from_millis(100), chain);
+        thread::sleep(Duration::from_millis(150));
+        task_handle.stop();
+        // the failure of the second stage skipped the third one on the second tick
+        assert_eq!(*stages.lock().unwrap(), vec!["collect", "check", "flush", "collect", "check"]);
+        assert_eq!(task_handle.stats().executions, 5);
+
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let stages = Arc::new(Mutex::new(Vec::new()));
+        let (first, second) = (stages.clone(), stages.clone());
+        let chain = ScheduledChain::new()
+            .then(move |_: &Remote| {
+                thread::sleep(Duration::from_millis(50));
+                first.lock().unwrap().push(thread::current().name().unwrap().to_owned());
+                panic!("collect failed");
+            })
+            .then(move |_: &Remote| second.lock().unwrap().push(thread::current().name().unwrap().to_owned()))
+            .policy(ChainPolicy::ContinueOnFailure);
+        let task_handle = pool_executor.schedule_fixed_rate_chain(Duration::from_millis(0), Duration::from_millis(200), chain);
+        thread::sleep(Duration::from_millis(100));
+        task_handle.stop();
+        // the second stage ran after the first one completed, despite its failure
+        let stages = stages.lock().unwrap();
+        assert_eq!(stages.len(), 2);
+        assert!(stages.iter().all(|name| name.starts_with("pool_thread_")));
+        assert_eq!(task_handle.stats().panics, 1);
+    }
+
+    #[test]
+    fn task_builder_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counter_clone = counter.clone();
+        let task_handle = executor.task()
+            .every(Duration::from_millis(20))
+            .named("limited")
+            .tag("builder")
+            .max_runs(3)
+            .on_panic(PanicPolicy::StopTask)
+            .spawn(move |_| {
+                counter_clone.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+        assert_eq!(task_handle.name(), Some("limited".to_owned()));
+        assert_eq!(task_handle.tags(), vec!["builder".to_owned()]);
+        assert_eq!(task_handle.terminated_reason(), Some(TerminationReason::Completed));
+
+        match executor.task().every(Duration::from_millis(0)).spawn(|_| ()) {
+            Err(ExecutorError::InvalidSchedule(_)) => (),
+            _ => panic!("a zero interval should be rejected"),
+        }
+        match executor.task().initial(Duration::from_millis(10)).spawn(|_| ()) {
+            Err(ExecutorError::InvalidSchedule(_)) => (),
+            _ => panic!("an interval is required"),
+        }
+
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let task_handle = pool_executor.task()
+            .initial(Duration::from_secs(10))
+            .fixed_interval(Duration::from_millis(0))
+            .jitter(Duration::from_millis(10))
+            .overlap_policy(OverlapPolicy::Skip)
+            .execution_timeout(Duration::from_secs(1))
+            .spawn(|_| ())
+            .unwrap();
+        assert_eq!(task_handle.overlap_policy(), OverlapPolicy::Skip);
+        assert_eq!(task_handle.inner.executions().kind, ScheduleKind::FixedInterval);
+        task_handle.stop();
     }
 
     #[test]
-    fn fixed_rate_slow_task_test_pool() {
-        let counter = Arc::new(RwLock::new(0));
-        let counter_clone = Arc::clone(&counter);
+    fn executor_builder_test() {
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let (started_clone, stopped_clone) = (started.clone(), stopped.clone());
+        let builder = ExecutorBuilder::new()
+            .name("custom_")
+            .stack_size(256 * 1024)
+            .after_start(move || started_clone.lock().unwrap().push(thread::current().name().unwrap().to_owned()))
+            .before_stop(move || {
+                stopped_clone.fetch_add(1, Ordering::SeqCst);
+            });
+        let executor = builder.build().unwrap();
+        assert_eq!(*started.lock().unwrap(), vec!["custom_".to_owned()]);
+        drop(executor);
+        assert_eq!(stopped.load(Ordering::SeqCst), 1);
+
+        let pool_executor = builder.build_pool(2).unwrap();
+        let (tx, rx) = mpsc::channel();
+        pool_executor.schedule_once(Duration::from_millis(0), move |_| {
+            tx.send(thread::current().name().unwrap().to_owned()).unwrap();
+        });
+        assert!(rx.recv_timeout(Duration::from_secs(1)).unwrap().starts_with("custom_"));
+        thread::sleep(Duration::from_millis(50));
         {
-            let executor = ThreadPoolExecutor::new(20).unwrap();
-            executor.schedule_fixed_rate(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_remote| {
-                    // TODO: use atomic int when available
-                    let counter = {
-                        let mut counter = counter_clone.write().unwrap();
-                        (*counter) += 1;
-                        *counter
-                    };
-                    if counter == 1 {
-                        thread::sleep(Duration::from_secs(3));
+            let mut started = started.lock().unwrap();
+            started.sort();
+            assert_eq!(*started, vec!["custom_".to_owned(), "custom_0".to_owned(), "custom_1".to_owned()]);
+        }
+        drop(pool_executor);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(stopped.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn resize_pool_test() {
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        assert_eq!(pool_executor.pool_size(), 1);
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counter_clone = counter.clone();
+        let start = Instant::now();
+        let task_handle = pool_executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(20), move |_| {
+            counter_clone.fetch_add(1, Ordering::SeqCst);
+        });
+        thread::sleep(Duration::from_millis(110));
+        pool_executor.resize(3);
+        assert_eq!(pool_executor.pool_size(), 3);
+        // three jobs waiting for each other can only complete with three threads
+        let barrier = Arc::new(std::sync::Barrier::new(3));
+        let jobs = (0..3).map(|_| {
+            let barrier = barrier.clone();
+            pool_executor.run_now(move || {
+                barrier.wait();
+            })
+        }).collect::<Vec<_>>();
+        future::join_all(jobs).wait().unwrap();
+        thread::sleep(Duration::from_millis(100));
+        pool_executor.resize(0);
+        assert_eq!(pool_executor.pool_size(), 1);
+        thread::sleep(Duration::from_millis(100));
+        task_handle.stop();
+        // no tick was lost across the resizes
+        let expected = start.elapsed().as_millis() as usize / 20 + 1;
+        let runs = counter.load(Ordering::SeqCst);
+        assert!(runs + 1 >= expected && runs <= expected, "{} runs, {} expected", runs, expected);
+    }
+
+    #[test]
+    fn auto_pool_size_test() {
+        let cpus = thread::available_parallelism().unwrap().get();
+        assert_eq!(ThreadPoolExecutor::new_auto().unwrap().pool_size(), cpus);
+        assert_eq!(ThreadPoolExecutor::new_auto_scaled(2.0, 1).unwrap().pool_size(), 1);
+        assert_eq!(ExecutorBuilder::new().cpus(4).build_pool_auto(0.5, 8).unwrap().pool_size(), 2);
+        assert_eq!(ExecutorBuilder::new().cpus(4).build_pool_auto(4.0, 8).unwrap().pool_size(), 8);
+        assert_eq!(ExecutorBuilder::new().cpus(4).build_pool_auto(0.0, 8).unwrap().pool_size(), 1);
+    }
+
+    #[test]
+    fn schedule_aligned_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task_handle = executor.schedule_aligned(Duration::from_millis(200), move |_| {
+            tx.send(SystemTime::now()).unwrap();
+        });
+        for _ in 0..2 {
+            let tick = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+            let past_boundary = tick.duration_since(UNIX_EPOCH).unwrap().as_millis() % 200;
+            assert!(past_boundary < 30, "tick {}ms past the boundary", past_boundary);
+        }
+        task_handle.stop();
+    }
+
+    #[test]
+    fn wall_clock_timer_test() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        // the system clock passed the deadline, as after a suspension
+        let start = Instant::now();
+        let timer = TaskTimer::wall_clock(Duration::from_secs(10), SystemTime::now() - Duration::from_secs(1),
+                                          Duration::from_millis(50), &handle).unwrap();
+        core.run(timer).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+        // the system clock went backwards: the monotonic deadline still applies
+        let start = Instant::now();
+        let timer = TaskTimer::wall_clock(Duration::from_millis(120), SystemTime::now() + Duration::from_secs(3600),
+                                          Duration::from_millis(50), &handle).unwrap();
+        core.run(timer).unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(120) && elapsed < Duration::from_millis(300));
+
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task_handle = executor.task()
+            .every(Duration::from_millis(30))
+            .clock_source(ClockSource::Wall)
+            .spawn(move |_| {
+                let _ = tx.send(());
+            })
+            .unwrap();
+        assert_eq!(task_handle.clock_source(), ClockSource::Wall);
+        for _ in 0..3 {
+            rx.recv_timeout(Duration::from_millis(200)).unwrap();
+        }
+        task_handle.stop();
+    }
+
+    #[test]
+    fn schedule_fixed_rate_anchored_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        let mut runs = 0u64;
+        let task_handle = executor.schedule_fixed_rate_anchored(Duration::from_millis(0), Duration::from_millis(10), MissedTickPolicy::Burst, move |_| {
+            tx.send(Instant::now()).unwrap();
+            // executions of varying length must not shift the phase of the ticks
+            thread::sleep(Duration::from_millis(runs % 7));
+            runs += 1;
+        });
+        // A drift would accumulate the 3ms the executions take on average, so it would exceed the
+        // bound long before the last tick, while a busy machine only delays some of the ticks
+        for n in 0..100 {
+            let tick = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+            let target = start + Duration::from_millis(10 * n);
+            let lateness = tick.saturating_duration_since(target);
+            assert!(lateness < Duration::from_millis(50), "tick {} is {:?} late", n, lateness);
+        }
+        task_handle.stop();
+        assert_eq!(task_handle.stats().skipped_ticks, 0);
+
+        let (tx, rx) = mpsc::channel();
+        let task_handle = executor.schedule_fixed_rate_anchored(Duration::from_millis(0), Duration::from_millis(10), MissedTickPolicy::Skip, move |_| {
+            tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(45));
+        });
+        for _ in 0..3 {
+            rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        task_handle.stop();
+        assert!(task_handle.stats().skipped_ticks >= 6);
+    }
+
+    #[test]
+    fn missed_tick_policy_test() {
+        let executor = ThreadPoolExecutor::new(3).unwrap();
+        let mut task_handles = Vec::new();
+        let mut receivers = Vec::new();
+        for &policy in &[MissedTickPolicy::Burst, MissedTickPolicy::Skip, MissedTickPolicy::Delay] {
+            let (tx, rx) = mpsc::channel();
+            let tx = Mutex::new(tx);
+            let first = AtomicBool::new(true);
+            let task_handle = executor.task()
+                .every(Duration::from_millis(40))
+                .missed_ticks(policy)
+                .overlap_policy(OverlapPolicy::Queue)
+                .spawn(move |_| {
+                    if first.swap(false, Ordering::SeqCst) {
+                        thread::sleep(Duration::from_millis(220));
                     }
-                }
-            );
-            thread::sleep(Duration::from_millis(5500));
+                    tx.lock().unwrap().send(Instant::now()).unwrap();
+                })
+                .unwrap();
+            task_handles.push(task_handle);
+            receivers.push(rx);
         }
-        assert_eq!(*counter.read().unwrap(), 6);
+        // the gaps between the second and third executions, right after the slow one
+        let mut gaps = Vec::new();
+        for rx in &receivers {
+            rx.recv_timeout(Duration::from_secs(2)).unwrap();
+            let second = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+            let third = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+            gaps.push(third - second);
+        }
+        for task_handle in &task_handles {
+            task_handle.stop();
+        }
+        // 180ms behind: the missed ticks run back to back
+        assert!(gaps[0] < Duration::from_millis(20), "{:?}", gaps);
+        assert_eq!(task_handles[0].stats().skipped_ticks, 0);
+        // 4 missed ticks are skipped, the next one runs 20ms late, and the following one on time
+        assert!(gaps[1] >= Duration::from_millis(5) && gaps[1] < Duration::from_millis(38), "{:?}", gaps);
+        assert_eq!(task_handles[1].stats().skipped_ticks, 4);
+        // the ticks are shifted, and spaced by the interval again
+        assert!(gaps[2] >= Duration::from_millis(30), "{:?}", gaps);
+        assert_eq!(task_handles[2].stats().skipped_ticks, 0);
     }
 
     #[test]
-    fn fixed_rate_stop_test() {
-        let counter1 = Arc::new(RwLock::new(0));
-        let counter2 = Arc::new(RwLock::new(0));
-        let counter1_clone = Arc::clone(&counter1);
-        let counter2_clone = Arc::clone(&counter2);
-        {
-            let executor = CoreExecutor::new().unwrap();
-            let t1 = executor.schedule_fixed_rate(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
-                    let mut counter = counter1_clone.write().unwrap();
-                    (*counter) += 1;
-                }
-            );
-            executor.schedule_fixed_rate(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
-                    let mut counter = counter2_clone.write().unwrap();
-                    (*counter) += 1;
-                }
-            );
-            thread::sleep(Duration::from_millis(5500));
-            t1.stop();
-            thread::sleep(Duration::from_millis(5000));
+    fn zero_interval_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        let start = Instant::now();
+        let zero_handle = executor.schedule_fixed_rate(Duration::from_secs(0), Duration::from_secs(0), move |_| {
+            runs_clone.fetch_add(1, Ordering::SeqCst);
+        });
+        let (tx, rx) = mpsc::channel();
+        let task_handle = executor.schedule_fixed_rate(Duration::from_millis(100), Duration::from_millis(100), move |_| {
+            tx.send(()).unwrap();
+        });
+        for _ in 0..3 {
+            rx.recv_timeout(Duration::from_millis(500)).unwrap();
         }
-        assert_eq!(*counter1.read().unwrap(), 6);
-        assert_eq!(*counter2.read().unwrap(), 11);
+        zero_handle.stop();
+        task_handle.stop();
+        assert_eq!(zero_handle.interval(), Some(MIN_INTERVAL));
+        let max_runs = (start.elapsed().as_millis() / MIN_INTERVAL.as_millis()) as usize + 1;
+        assert!(runs.load(Ordering::SeqCst) <= max_runs);
+
+        let executor = ExecutorBuilder::new().min_interval(Duration::from_millis(50)).build().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task_handle = executor.schedule_fixed_interval(Duration::from_secs(0), Duration::from_millis(10), move |_| {
+            tx.send(Instant::now()).unwrap();
+        });
+        assert_eq!(task_handle.interval(), Some(Duration::from_millis(50)));
+        let first = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        task_handle.set_interval(Duration::from_secs(0));
+        let second = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        let third = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        task_handle.stop();
+        assert!(second - first >= Duration::from_millis(45));
+        assert!(third - second >= Duration::from_millis(45));
+        assert_eq!(task_handle.interval(), Some(Duration::from_millis(50)));
     }
 
     #[test]
-    fn fixed_interval_stop_test() {
-        let counter1 = Arc::new(RwLock::new(0));
-        let counter2 = Arc::new(RwLock::new(0));
-        let counter1_clone = Arc::clone(&counter1);
-        let counter2_clone = Arc::clone(&counter2);
-        {
-            let executor = CoreExecutor::new().unwrap();
-            let t1 = executor.schedule_fixed_interval(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
-                    let mut counter = counter1_clone.write().unwrap();
-                    (*counter) += 1;
-                }
-            );
-            executor.schedule_fixed_rate(
-                Duration::from_secs(0),
-                Duration::from_secs(1),
-                move |_handle| {
-                    let mut counter = counter2_clone.write().unwrap();
-                    (*counter) += 1;
+    fn stop_releases_function_test() {
+        fn released_within(resource: &Arc<()>, timeout: Duration) -> bool {
+            let start = Instant::now();
+            while Arc::strong_count(resource) > 1 {
+                if start.elapsed() > timeout {
+                    return false;
                 }
-            );
-            thread::sleep(Duration::from_millis(5500));
-            t1.stop();
-            thread::sleep(Duration::from_millis(5000));
+                thread::sleep(Duration::from_millis(1));
+            }
+            true
+        }
+        let executor = CoreExecutor::new().unwrap();
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let hour = Duration::from_secs(3600);
+
+        let resource = Arc::new(());
+        let captured = resource.clone();
+        let task_handle = executor.schedule_fixed_rate(hour, hour, move |_| drop(captured.clone()));
+        thread::sleep(Duration::from_millis(20));
+        task_handle.stop();
+        assert!(released_within(&resource, Duration::from_millis(100)));
+
+        let captured = resource.clone();
+        let task_handle = executor.schedule_once(hour, move |_| drop(captured));
+        thread::sleep(Duration::from_millis(20));
+        task_handle.stop();
+        assert!(released_within(&resource, Duration::from_millis(100)));
+
+        let captured = resource.clone();
+        let task_handle = pool_executor.schedule_fixed_interval(hour, hour, move |_| drop(captured.clone()));
+        thread::sleep(Duration::from_millis(20));
+        task_handle.stop();
+        assert!(released_within(&resource, Duration::from_millis(100)));
+
+        // the timers of a debouncer are released when superseded, cancelled or stopped
+        let captured = resource.clone();
+        let debouncer = executor.debouncer(hour, move |_| drop(captured.clone()));
+        for _ in 0..3 {
+            debouncer.trigger();
+        }
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(Arc::strong_count(&debouncer.inner), 2);
+        assert!(debouncer.cancel());
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(Arc::strong_count(&debouncer.inner), 1);
+        debouncer.trigger();
+        thread::sleep(Duration::from_millis(20));
+        debouncer.handle().stop();
+        drop(debouncer);
+        assert!(released_within(&resource, Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn debug_display_test() {
+        let executor = CoreExecutor::with_name("debug_executor").unwrap();
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task_handle = executor.schedule_fixed_rate_named("flush", Duration::from_secs(0), Duration::from_secs(1), move |_| {
+            tx.send(()).unwrap();
+        });
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        let debug = format!("{:?}", executor);
+        assert!(debug.contains("debug_executor"), "{}", debug);
+        assert!(debug.contains("running: true") && debug.contains("alive: true") && debug.contains("tasks: 1"), "{}", debug);
+        let debug = format!("{:?}", pool_executor);
+        assert!(debug.contains("pool_size: 2") && debug.contains("pool_thread_executor"), "{}", debug);
+        let debug = format!("{:?}", task_handle);
+        assert!(debug.contains("\"flush\"") && debug.contains("runs: 1") && debug.contains("stopped: false"), "{}", debug);
+        assert_eq!(task_handle.to_string(), format!("task {} (flush): 1 runs, every 1s, running", task_handle.id()));
+        task_handle.stop();
+        assert!(task_handle.to_string().ends_with("stopped"));
+        let task_handle = executor.schedule_once(Duration::from_secs(10), |_| ());
+        assert_eq!(task_handle.to_string(), format!("task {}: 0 runs, running", task_handle.id()));
+        task_handle.stop();
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn dump_json_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task_handle = executor.schedule_fixed_rate_named("flush", Duration::from_secs(0), Duration::from_millis(1500), move |_| {
+            tx.send(()).unwrap();
+        });
+        task_handle.add_tag("io");
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let dump: serde_json::Value = serde_json::from_str(&executor.dump_json()).unwrap();
+        assert_eq!(dump["metrics"], serde_json::json!({
+            "live_tasks": 1,
+            "executions": 1,
+            "recent_executions": 1,
+            "panics": 0,
+            "queued": 0,
+        }));
+        let task = &dump["tasks"][0];
+        let mut keys = task.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["busy_time_ms", "id", "interval_ms", "kind", "name", "paused", "runs", "stats", "stopped", "tags"]);
+        assert_eq!(task["name"], "flush");
+        assert_eq!(task["tags"], serde_json::json!(["io"]));
+        assert_eq!(task["kind"], "FixedRate");
+        assert_eq!(task["interval_ms"], 1500.0);
+        assert_eq!(task["runs"], 1);
+        let stats = &task["stats"];
+        assert_eq!(stats["executions"], 1);
+        assert!(stats["last_start_ms_ago"].as_f64().unwrap() >= 20.0);
+        assert!(stats["last_duration_ms"].as_f64().unwrap() < 20.0);
+        // the dump doesn't reset the counter of recent executions
+        assert_eq!(executor.metrics().recent_executions, 1);
+        task_handle.stop();
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn prometheus_text_sink_test() {
+        let sink = PrometheusTextSink::new(io::sink());
+        sink.gauge("tasks", &[], 3.0);
+        sink.gauge("runs", &[("task", "b")], 2.0);
+        sink.gauge("runs", &[("task", "a \"quoted\"")], 1.5);
+        sink.counter("done_total", &[], 4.0);
+        sink.histogram_observe("took", &[("task", "a")], 0.003);
+        sink.histogram_observe("took", &[("task", "a")], 100.0);
+        let text = sink.render();
+        let expected_start = "# TYPE done_total counter\n\
+                              done_total 4\n\
+                              # TYPE runs gauge\n\
+                              runs{task=\"a \\\"quoted\\\"\"} 1.5\n\
+                              runs{task=\"b\"} 2\n\
+                              # TYPE tasks gauge\n\
+                              tasks 3\n\
+                              # TYPE took histogram\n\
+                              took_bucket{task=\"a\",le=\"0.001\"} 0\n\
+                              took_bucket{task=\"a\",le=\"0.002\"} 0\n\
+                              took_bucket{task=\"a\",le=\"0.004\"} 1\n";
+        assert!(text.starts_with(expected_start), "{}", text);
+        assert!(text.ends_with("took_bucket{task=\"a\",le=\"60\"} 1\n\
+                                took_bucket{task=\"a\",le=\"+Inf\"} 2\n\
+                                took_sum{task=\"a\"} 100.003\n\
+                                took_count{task=\"a\"} 2\n"), "{}", text);
+
+        let buffer = SharedBuffer::default();
+        let executor = CoreExecutor::new().unwrap();
+        let sink = Arc::new(PrometheusTextSink::new(buffer.clone()));
+        let flusher = executor.set_metrics_sink(sink.clone(), Duration::from_millis(50));
+        let task_handle = executor.schedule_fixed_rate_named("job", Duration::from_secs(0), Duration::from_millis(10), |_| ());
+        let unnamed = executor.schedule_fixed_rate(Duration::from_secs(0), Duration::from_secs(10), |_| ());
+        thread::sleep(Duration::from_millis(130));
+        let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(written.contains("scheduled_executor_live_tasks 3\n"), "{}", written);
+        assert!(written.contains("# TYPE scheduled_executor_task_executions_total counter\n"), "{}", written);
+        assert!(written.contains("scheduled_executor_task_executions_total{task=\"job\"}"), "{}", written);
+        assert!(written.contains(&format!("scheduled_executor_task_executions_total{{task=\"{}\"}} 1\n", unnamed.id())), "{}", written);
+        assert!(written.contains("scheduled_executor_task_duration_seconds_count{task=\"job\"}"), "{}", written);
+        assert!(written.contains("# TYPE scheduled_executor_task_lateness_seconds histogram\n"), "{}", written);
+        // a new sink replaces the flusher
+        let other = executor.set_metrics_sink(Arc::new(PrometheusTextSink::new(io::sink())), Duration::from_secs(1));
+        assert!(flusher.stopped());
+        for task_handle in &[task_handle, unnamed, other] {
+            task_handle.stop();
+        }
+    }
+
+    /// Records the log lines of the targets passed to `capture_logs`, for all the tests.
+    struct CaptureLogger;
+
+    static CAPTURED_TARGETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static CAPTURED_LOGS: Mutex<Vec<(String, LogLevel, String)>> = Mutex::new(Vec::new());
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, metadata: &LogMetadata) -> bool {
+            CAPTURED_TARGETS.lock().unwrap().iter().any(|target| target == metadata.target())
+        }
+
+        fn log(&self, record: &LogRecord) {
+            if self.enabled(record.metadata()) {
+                let line = (record.target().to_owned(), record.level(), record.args().to_string());
+                CAPTURED_LOGS.lock().unwrap().push(line);
+            }
+        }
+    }
+
+    /// Starts capturing the lines logged with the given target, installing the logger if needed.
+    fn capture_logs(target: &str) {
+        static INSTALL: ::std::sync::Once = ::std::sync::Once::new();
+        INSTALL.call_once(|| {
+            let _ = log::set_logger(|max_level| {
+                max_level.set(LogLevelFilter::Trace);
+                Box::new(CaptureLogger)
+            });
+        });
+        CAPTURED_TARGETS.lock().unwrap().push(target.to_owned());
+    }
+
+    /// Stops capturing the given target, and returns the level and the message of the lines
+    /// captured with it.
+    fn captured_logs(target: &str) -> Vec<(LogLevel, String)> {
+        CAPTURED_TARGETS.lock().unwrap().retain(|captured| captured != target);
+        let mut logs = CAPTURED_LOGS.lock().unwrap();
+        let (lines, others) = logs.drain(..).partition::<Vec<_>, _>(|line| line.0 == target);
+        *logs = others;
+        lines.into_iter().map(|line| (line.1, line.2)).collect()
+    }
+
+    #[test]
+    fn task_log_target_test() {
+        let target = format!("{}::log-target-test", TASK_LOG_TARGET);
+        let quiet_target = format!("{}::log-target-quiet", TASK_LOG_TARGET);
+        capture_logs(&target);
+        capture_logs(&quiet_target);
+        let executor = CoreExecutor::new().unwrap();
+        executor.set_task_log_level(LogLevelFilter::Info);
+        let runs = AtomicUsize::new(0);
+        let task = executor.schedule_fixed_rate_named("log-target-test", Duration::from_millis(0),
+                                                      Duration::from_millis(40), move |_| {
+            if runs.fetch_add(1, Ordering::SeqCst) == 1 {
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        thread::sleep(Duration::from_millis(150));
+        task.stop();
+
+        let quiet = CoreExecutor::new().unwrap();
+        quiet.set_task_log_level(LogLevelFilter::Off);
+        let quiet_task = quiet.schedule_fixed_rate_named("log-target-quiet", Duration::from_millis(0),
+                                                         Duration::from_millis(10), |_| {});
+        thread::sleep(Duration::from_millis(50));
+        quiet_task.stop();
+
+        let lines = captured_logs(&target);
+        assert!(lines.iter().all(|&(level, _)| level == LogLevel::Info), "{:?}", lines);
+        assert!(lines[0].1.contains("scheduled"), "{:?}", lines);
+        assert!(lines[1].1.contains("run 0 started"), "{:?}", lines);
+        assert!(lines[2].1.contains("run 0 completed"), "{:?}", lines);
+        assert!(lines.iter().any(|line| line.1.contains("run 1 overran")), "{:?}", lines);
+        assert!(lines.last().unwrap().1.contains("stopped"), "{:?}", lines);
+        assert!(captured_logs(&quiet_target).is_empty());
+    }
+
+    #[test]
+    fn pool_saturation_test() {
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let saturations = Arc::new(Mutex::new(Vec::new()));
+        let saturations_clone = saturations.clone();
+        pool_executor.on_saturation(5, move |in_flight| saturations_clone.lock().unwrap().push(in_flight));
+        assert_eq!(pool_executor.in_flight(), 0);
+        let task = pool_executor.schedule_fixed_rate(Duration::from_millis(50), Duration::from_millis(100), |_| {
+            thread::sleep(Duration::from_secs(3));
+        });
+        task.set_overlap_policy(OverlapPolicy::Allow);
+        thread::sleep(Duration::from_millis(300));
+        let early = pool_executor.in_flight();
+        thread::sleep(Duration::from_millis(400));
+        let late = pool_executor.in_flight();
+        assert!(early >= 2 && late > early, "{} then {}", early, late);
+        assert_eq!(*saturations.lock().unwrap(), vec![5]);
+        task.stop();
+    }
+
+    #[test]
+    fn busy_time_test() {
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task = pool_executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(100), move |_| {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(()).unwrap();
+        });
+        for _ in 0..3 {
+            rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        thread::sleep(Duration::from_millis(10));
+        let busy = task.total_busy_time();
+        assert!(busy >= Duration::from_millis(150) && busy < Duration::from_millis(200), "{:?}", busy);
+        assert_eq!(pool_executor.tasks()[0].busy_time, busy);
+        let fraction = task.busy_fraction(Duration::from_secs(60));
+        assert!(fraction > 0.4 && fraction < 0.8, "{}", fraction);
+        let idle = pool_executor.schedule_fixed_rate(Duration::from_secs(60), Duration::from_secs(60), |_| ());
+        assert_eq!(idle.total_busy_time(), Duration::from_secs(0));
+        assert_eq!(idle.busy_fraction(Duration::from_secs(1)), 0.0);
+        task.stop();
+        idle.stop();
+    }
+
+    #[test]
+    fn slow_execution_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let fixed_rate_runs = AtomicUsize::new(0);
+        let fixed_rate = executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(30), move |_| {
+            if fixed_rate_runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                thread::sleep(Duration::from_millis(40));
+            }
+        });
+        let fixed_interval = executor.task()
+            .fixed_interval(Duration::from_millis(30))
+            .warn_if_slower_than(Duration::from_millis(5))
+            .spawn(|_| thread::sleep(Duration::from_millis(10)))
+            .unwrap();
+        let unchecked = executor.schedule_fixed_interval(Duration::from_millis(0), Duration::from_millis(30), |_| {
+            thread::sleep(Duration::from_millis(10));
+        });
+        thread::sleep(Duration::from_millis(150));
+        fixed_rate.stop();
+        fixed_interval.stop();
+        unchecked.stop();
+        assert_eq!(fixed_rate.stats().slow, 1);
+        let stats = fixed_interval.stats();
+        assert!(stats.slow >= 2 && stats.slow == stats.executions, "{:?}", stats);
+        assert_eq!(unchecked.stats().slow, 0);
+    }
+
+    #[test]
+    fn schedule_fixed_rate_str_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task = executor.schedule_fixed_rate_str("0s", "20ms", move |_| tx.send(()).unwrap()).unwrap();
+        assert_eq!(task.interval(), Some(Duration::from_millis(20)));
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        task.stop();
+        assert_eq!(executor.schedule_fixed_rate_str("0s", "5min", |_| ()).unwrap_err(),
+                   ParseError::UnknownUnit { token: "5min".to_owned(), unit: "min".to_owned() });
+
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let built = pool_executor.task().every_str("1h30m").unwrap().spawn(|_| ()).unwrap();
+        assert_eq!(built.interval(), Some(Duration::from_secs(5400)));
+        built.stop();
+        let error = pool_executor.task().every_str("90").err().unwrap();
+        assert_eq!(ExecutorError::from(error).to_string(),
+                   "invalid schedule: missing unit after \"90\", expected one of d, h, m, s, ms, us, ns");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn schedule_from_config_test() {
+        let config = |json: &str| serde_json::from_str::<ScheduleConfig>(json).unwrap();
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task = executor.schedule_from_config(
+            &config(r#"{"kind": "fixed_rate", "interval": "10ms", "name": "configured", "tags": ["a", "b"], "max_runs": 2}"#),
+            move |_| tx.send(()).unwrap()).unwrap();
+        assert_eq!(task.name().as_deref(), Some("configured"));
+        assert_eq!(task.tags(), vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(task.interval(), Some(Duration::from_millis(10)));
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        task.on_stop().wait().unwrap();
+        assert!(rx.try_recv().is_err());
+
+        let unknown = serde_json::from_str::<ScheduleConfig>(r#"{"kind": "fixed_rate", "intervall": "1s"}"#);
+        assert!(unknown.unwrap_err().to_string().contains("unknown field `intervall`"));
+
+        let error = |json: &str| executor.schedule_from_config(&config(json), |_| ()).unwrap_err().to_string();
+        assert_eq!(error(r#"{"kind": "fixed_interval"}"#), "invalid schedule: interval: required unless the schedule is cron");
+        assert!(error(r#"{"kind": "fixed_rate", "interval": "5x"}"#).starts_with("invalid schedule: interval: unknown unit"));
+        assert_eq!(error(r#"{"kind": "cron", "cron": "* * * * *", "jitter": "1s"}"#),
+                   "invalid schedule: jitter: not allowed for a cron schedule");
+        assert!(error(r#"{"kind": "cron", "cron": "61 * * * *"}"#).starts_with("invalid schedule: cron: "));
+        assert_eq!(error(r#"{"kind": "fixed_rate", "interval": "1s", "max_runs": 0}"#),
+                   "invalid schedule: max_runs: must be at least 1");
+        assert_eq!(error(r#"{"kind": "fixed_rate", "interval": "1s", "overlap_policy": "skip"}"#),
+                   "invalid schedule: overlap_policy: only supported on a ThreadPoolExecutor");
+
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let cron = pool_executor.schedule_from_config(
+            &config(r#"{"kind": "cron", "cron": "0 0 * * *", "tags": ["nightly"], "overlap_policy": {"concurrent": 2}}"#), |_| ()).unwrap();
+        assert_eq!(cron.overlap_policy(), OverlapPolicy::Concurrent(2));
+        assert_eq!(cron.tags(), vec!["nightly".to_owned()]);
+        assert_eq!(pool_executor.tasks()[0].kind, ScheduleKind::Cron);
+        cron.stop();
+    }
+
+    #[test]
+    fn into_interval_builder_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let task = executor.task().initial(60_000).every(1500).spawn(|_| ()).unwrap();
+        assert_eq!(task.interval(), Some(Duration::from_millis(1500)));
+        task.stop();
+        let error = executor.task().every(Duration::from_secs(1)).initial(Duration::from_secs(u64::MAX)).spawn(|_| ());
+        assert!(error.unwrap_err().to_string().starts_with("invalid schedule: initial: interval 213503982334601d"));
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn chrono_interval_builder_test() {
+        use chrono;
+
+        let pool_executor = ThreadPoolExecutor::new(1).unwrap();
+        let task = pool_executor.task().every(chrono::Duration::minutes(5)).spawn(|_| ()).unwrap();
+        assert_eq!(task.interval(), Some(Duration::from_secs(300)));
+        task.stop();
+        let error = pool_executor.task().every(chrono::Duration::seconds(-30)).spawn(|_| ()).unwrap_err();
+        assert_eq!(error.to_string(), "invalid schedule: interval: negative interval -PT30S");
+    }
+
+    /// Counts its executions, for `task_trait_test`.
+    struct CountingJob {
+        name: &'static str,
+        runs: mpsc::Sender<(&'static str, u64)>,
+    }
+
+    impl Task for CountingJob {
+        fn run(&mut self, _handle: &Handle, context: &TaskContext) {
+            self.runs.send((self.name, context.run())).unwrap();
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    impl Task<Remote> for CountingJob {
+        fn run(&mut self, _remote: &Remote, context: &TaskContext) {
+            self.runs.send((self.name, context.run())).unwrap();
+        }
+    }
+
+    #[test]
+    fn task_trait_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let jobs: Vec<Box<dyn Task>> = vec![
+            Box::new(CountingJob { name: "first", runs: tx.clone() }),
+            Box::new(CountingJob { name: "second", runs: tx.clone() }),
+        ];
+        let handles = jobs.into_iter()
+            .map(|job| executor.schedule_task_fixed_rate(Duration::from_millis(0), Duration::from_millis(20), job))
+            .collect::<Vec<_>>();
+        let mut runs = (0..4).map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap()).collect::<Vec<_>>();
+        runs.sort();
+        assert_eq!(runs, [("first", 0), ("first", 1), ("second", 0), ("second", 1)]);
+        let names = executor.tasks().into_iter().map(|task| task.name).collect::<Vec<_>>();
+        assert_eq!(names, [Some("first".to_owned()), Some("second".to_owned())]);
+        for handle in handles {
+            handle.stop();
+        }
+
+        let pool_executor = ThreadPoolExecutor::new(2).unwrap();
+        let job: Box<dyn Task<Remote>> = Box::new(CountingJob { name: "pooled", runs: tx });
+        let task = pool_executor.schedule_task(Duration::from_millis(0), Duration::from_millis(10), job);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap(), ("pooled", 0));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap(), ("pooled", 1));
+        assert_eq!(task.name(), None);
+        assert_eq!(task.overlap_policy(), OverlapPolicy::Queue);
+        task.stop();
+    }
+
+    #[test]
+    fn schedule_with_state_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task = executor.schedule_fixed_rate_with_state(Duration::from_millis(0), Duration::from_millis(10), Vec::new(),
+                                                           move |runs: &mut Vec<usize>, _| {
+            runs.push(runs.len());
+            tx.send(()).unwrap();
+        });
+        for _ in 0..3 {
+            rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        assert!(task.clone().into_state_receiver::<String>().is_none());
+        task.stop();
+        let runs = task.clone().into_state_receiver::<Vec<usize>>().unwrap().wait().unwrap();
+        assert!(runs.len() >= 3 && runs.iter().enumerate().all(|(index, &run)| index == run), "{:?}", runs);
+        assert!(task.into_state_receiver::<Vec<usize>>().is_none());
+
+        let pool_executor = ThreadPoolExecutor::new(4).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task = pool_executor.schedule_fixed_rate_with_state(Duration::from_millis(0), Duration::from_millis(5), 0,
+                                                                move |total: &mut u64, _| {
+            let before = *total;
+            thread::sleep(Duration::from_millis(10));
+            *total = before + 1;
+            tx.send(()).unwrap();
+        });
+        let receiver = task.clone().into_state_receiver::<u64>().unwrap();
+        for _ in 0..4 {
+            rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        task.stop();
+        // No execution was lost to a concurrent one
+        let total = receiver.wait().unwrap();
+        assert!(total >= 4);
+        assert_eq!(total, task.stats().executions);
+    }
+
+    #[test]
+    fn schedule_async_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let task = executor.schedule_async_interval(Duration::from_millis(0), Duration::from_millis(30), move |handle| {
+            let tx = tx.clone();
+            let started = Instant::now();
+            Timeout::new(Duration::from_millis(20), handle).unwrap()
+                .map(move |_| tx.send(started).unwrap())
+                .map_err(|_| ())
+        });
+        let starts = (0..3).map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap()).collect::<Vec<_>>();
+        task.stop();
+        for pair in starts.windows(2) {
+            // 20ms for the future to resolve, then the interval
+            assert!(pair[1] - pair[0] >= Duration::from_millis(50), "{:?}", pair[1] - pair[0]);
+        }
+        let stats = task.stats();
+        assert!(stats.last_duration.unwrap() >= Duration::from_millis(20), "{:?}", stats);
+
+        let (tx, rx) = mpsc::channel();
+        let rate = executor.schedule_async_fixed_rate(Duration::from_millis(0), Duration::from_millis(30), move |handle| {
+            tx.send(Instant::now()).unwrap();
+            Timeout::new(Duration::from_millis(20), handle).unwrap().map_err(|_| ())
+        });
+        let starts = (0..3).map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap()).collect::<Vec<_>>();
+        rate.stop();
+        for pair in starts.windows(2) {
+            assert!(pair[1] - pair[0] >= Duration::from_millis(25), "{:?}", pair[1] - pair[0]);
+        }
+        // Measured from the end of the executions, the two gaps would take at least 100ms
+        assert!(starts[2] - starts[0] < Duration::from_millis(100), "{:?}", starts);
+
+        executor.set_panic_policy(PanicPolicy::StopTask);
+        let failing = executor.schedule_async_interval(Duration::from_millis(0), Duration::from_millis(10), |_| future::err(()));
+        failing.on_stop().wait().unwrap();
+        assert_eq!(failing.terminated_reason(), Some(TerminationReason::Panicked));
+        assert_eq!(failing.stats().panics, 1);
+    }
+
+    #[test]
+    fn schedule_once_cancelable_test() {
+        // A resource that can't be cloned, moved into the function
+        struct Resource(mpsc::Sender<u32>);
+
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let resource = Resource(tx);
+        let once = executor.schedule_once_cancelable(Duration::from_millis(200), move |_| resource.0.send(1).unwrap());
+        let scheduled_fn = once.cancel().expect("the function didn't run yet");
+        assert!(once.cancel().is_none());
+        once.handle().on_stop().wait().unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_millis(300)), Err(mpsc::RecvTimeoutError::Timeout));
+        // The function, and the resource, are usable again
+        executor.spawn_fn(scheduled_fn).unwrap().wait().unwrap();
+        assert_eq!(rx.recv().unwrap(), 1);
+
+        let (tx, rx) = mpsc::channel();
+        let resource = Resource(tx);
+        let once = executor.schedule_once_cancelable(Duration::from_millis(10), move |_| resource.0.send(2).unwrap());
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(2));
+        assert!(once.cancel().is_none());
+
+        let pool = ThreadPoolExecutor::new(2).unwrap();
+        for i in 0..50u64 {
+            let (tx, rx) = mpsc::channel();
+            let resource = Resource(tx);
+            let once = pool.schedule_once_cancelable(Duration::from_millis(0), move |_| resource.0.send(i as u32).unwrap());
+            thread::sleep(Duration::from_micros(i * 20));
+            let returned = once.cancel().is_some();
+            once.handle().on_stop().wait().unwrap();
+            // Either the function ran, or it was returned, never both
+            let received = rx.recv_timeout(Duration::from_millis(100)).is_ok();
+            assert_ne!(received, returned, "iteration {}", i);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "compat")]
+    fn compat_test() {
+        use futures03::executor::block_on;
+        use futures03::StreamExt;
+
+        let executor = CoreExecutor::new().unwrap();
+        let (task, result) = executor.schedule_once_result_compat(Duration::from_millis(10), |_| 42);
+        assert_eq!(block_on(result), Ok(42));
+        block_on(task.on_stop_compat());
+
+        let (task, result) = executor.schedule_once_result_compat(Duration::from_secs(10), |_| 42);
+        task.stop();
+        assert_eq!(block_on(result), Err(Canceled));
+
+        let pool = ThreadPoolExecutor::new(1).unwrap();
+        let (_, result) = pool.schedule_once_result_compat(Duration::from_millis(10), |_| "done");
+        assert_eq!(block_on(result), Ok("done"));
+
+        let ticks = block_on(executor.interval_stream_compat(Duration::from_millis(0), Duration::from_millis(10)).take(3).collect::<Vec<_>>());
+        assert_eq!(ticks.len(), 3);
+        assert!(ticks[2] - ticks[0] >= Duration::from_millis(15), "{:?}", ticks);
+    }
+
+    /// Measures the CPU time and the memory used by 50k tasks ticking every second, with the timer
+    /// wheel, or with a `Timeout` per task, as before the wheel, if the `BENCHMARK_TIMERS`
+    /// environment variable is `per_task`. Only runs on Linux:
+    /// `cargo test --release timer_wheel_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn timer_wheel_benchmark() {
+        const TASKS: u64 = 50_000;
+
+        // The user and system time of the process, assuming 100 clock ticks per second
+        fn cpu_time() -> Duration {
+            let stat = ::std::fs::read_to_string("/proc/self/stat").unwrap();
+            let fields = stat.rsplit(") ").next().unwrap().split(' ').collect::<Vec<_>>();
+            let ticks = fields[11].parse::<u64>().unwrap() + fields[12].parse::<u64>().unwrap();
+            Duration::from_millis(ticks * 10)
+        }
+
+        fn resident_kb() -> u64 {
+            let status = ::std::fs::read_to_string("/proc/self/status").unwrap();
+            let line = status.lines().find(|line| line.starts_with("VmRSS:")).unwrap();
+            line.split_whitespace().nth(1).unwrap().parse().unwrap()
+        }
+
+        let executor = CoreExecutor::new().unwrap();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let (cpu_before, resident_before) = (cpu_time(), resident_kb());
+        let tasks = (0..TASKS)
+            .map(|i| {
+                let counter = counter.clone();
+                executor.task()
+                    .initial(Duration::from_millis(i % 1000))
+                    .every(Duration::from_secs(1))
+                    .spawn(move |_| {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    })
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        thread::sleep(Duration::from_secs(5));
+        let executions = counter.load(Ordering::Relaxed);
+        let resident = resident_kb().saturating_sub(resident_before);
+        let cpu = cpu_time() - cpu_before;
+        for task in &tasks {
+            task.stop();
+        }
+        println!("{}: {} executions, {:?} of CPU, {} KiB more resident memory",
+                 if super::per_task_timers() { "Timer per task" } else { "Timer wheel" }, executions, cpu, resident);
+    }
+
+    #[test]
+    fn timer_wheel_same_slot_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        // All in the same slot of the wheel, or nearly
+        let when = Instant::now() + Duration::from_millis(100);
+        let tasks = (0..300)
+            .map(|i| {
+                let tx = tx.clone();
+                executor.schedule_once_at(when, move |_| tx.send(i).unwrap())
+            })
+            .collect::<Vec<_>>();
+        let cancelables = (300..400)
+            .map(|i| {
+                let tx = tx.clone();
+                executor.schedule_once_cancelable(Duration::from_millis(100), move |_| tx.send(i).unwrap())
+            })
+            .collect::<Vec<_>>();
+        drop(tx);
+        for task in tasks.iter().step_by(3) {
+            assert!(task.stop());
+        }
+        for cancelable in cancelables.iter().step_by(2) {
+            assert!(cancelable.cancel().is_some());
+        }
+        let mut fired = Vec::new();
+        // Disconnected once all the functions ran or were dropped
+        while let Ok(i) = rx.recv_timeout(Duration::from_secs(5)) {
+            fired.push(i);
+        }
+        fired.sort();
+        let expected = (0..300).filter(|i| i % 3 != 0).chain((300..400).filter(|i| i % 2 != 0)).collect::<Vec<_>>();
+        assert_eq!(fired, expected);
+        assert!(tasks.iter().step_by(3).all(|task| task.stats().executions == 0));
+        for task in tasks.iter().chain(cancelables.iter().map(|cancelable| cancelable.handle())) {
+            task.on_stop().wait().unwrap();
+        }
+        assert!(executor.tasks().is_empty());
+    }
+
+    #[test]
+    fn huge_interval_test() {
+        let executor = CoreExecutor::new().unwrap();
+        let huge = executor.schedule_fixed_rate(Duration::from_millis(0), Duration::MAX, |_| ());
+        let once = executor.schedule_once(Duration::MAX, |_| ());
+        let anchored = executor.schedule_fixed_rate_anchored(Duration::MAX, Duration::from_secs(1), MissedTickPolicy::Burst, |_| ());
+        let aligned = executor.schedule_aligned(Duration::MAX, |_| ());
+        let debouncer = executor.debouncer(Duration::MAX, |_| ());
+        debouncer.trigger();
+        let (tx, rx) = mpsc::channel();
+        let task = executor.schedule_fixed_rate(Duration::from_millis(0), Duration::from_millis(10), move |_| {
+            let _ = tx.send(());
+        });
+        for _ in 0..3 {
+            rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        assert!(executor.is_running());
+        assert!(huge.next_run_in().unwrap() > Duration::from_secs(86_400));
+        assert_eq!(huge.interval(), Some(MAX_INTERVAL));
+        assert_eq!(aligned.interval(), Some(MAX_INTERVAL));
+        for task in [&huge, &once, &anchored, &aligned, debouncer.handle(), &task] {
+            assert!(!task.stopped());
         }
-        assert_eq!(*counter1.read().unwrap(), 6);
-        assert_eq!(*counter2.read().unwrap(), 11);
     }
 }
@@ -8,7 +8,7 @@
 //!
 //! - [`CoreExecutor`]: schedule and execute tasks on a single thread, ideal for short running tasks.
 //! - [`ThreadPoolExecutor`]: schedule and execute tasks on a thread pool. Can be used for long
-//! running tasks.
+//!   running tasks.
 //!
 //! [`CoreExecutor`]: https://fede1024.github.io/rust-scheduled-executor/scheduled_executor/executor/struct.CoreExecutor.html
 //! [`ThreadPoolExecutor`]: https://fede1024.github.io/rust-scheduled-executor/scheduled_executor/executor/struct.ThreadPoolExecutor.html
@@ -52,9 +52,24 @@
 extern crate futures;
 extern crate tokio_core;
 extern crate futures_cpupool;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "chrono")]
+extern crate chrono_tz;
+#[cfg(feature = "compat")]
+extern crate futures03;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
+pub mod duration;
 pub mod executor;
+pub mod schedule;
 pub mod task_group;
+mod timer_wheel;
 
-pub use executor::{CoreExecutor, ThreadPoolExecutor};
+pub use duration::{IntervalError, IntoInterval, ParseError};
+pub use executor::{CoreExecutor, ExecutorBuilder, ExecutorError, ThreadPoolExecutor};
+pub use schedule::{Aligned, Anchored, Backoff, CronError, CronSchedule, DelaySequence, FixedInterval, FixedRate, Jittered, MissedTickPolicy, RetryPolicy, Schedule, ScheduleKind, Until};
 pub use task_group::{TaskGroup, TaskGroupScheduler};
@@ -0,0 +1,1450 @@
+//! Schedules define when a task should run. Any type implementing the `Schedule` trait can be
+//! passed to the executors' `schedule_with` methods, allowing custom recurrence rules: the
+//! `FixedInterval` and `FixedRate` schedules provided by this module are the ones used by the
+//! `schedule_fixed_interval` and `schedule_fixed_rate` methods.
+//!
+//! ### Calendar schedules
+//!
+//! A `CronSchedule` describes the wall-clock times at which a task should run, using the
+//! familiar cron syntax. All the calculations are performed in UTC, using the system clock.
+//!
+//! Both the standard 5 fields format (`minute hour day-of-month month day-of-week`) and the 6
+//! fields format with a leading `second` field are supported. Each field can contain `*`, single
+//! values, ranges (`1-5`), steps (`*/15`, `10-40/10`) and comma separated lists of the above.
+//! Months and days of the week can also be specified using their three letters English name
+//! (`JAN`, `MON`, ...). As in most cron implementations, if both the day of the month and the day
+//! of the week are restricted, the task will run when either of them matches.
+//!
+//! With the `chrono` feature enabled, schedules can also be evaluated in a specific timezone, or
+//! in the local timezone of the system, see `CronSchedule::in_timezone` and
+//! `CronSchedule::in_local_timezone`.
+//!
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Local, LocalResult, TimeZone};
+#[cfg(feature = "chrono")]
+use chrono_tz::Tz;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use duration::{deadline_after, MAX_INTERVAL};
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Defines when a task should run. The executor will call `next_after` once when the task is
+/// scheduled, and then again after every execution, to know how long to wait before the next
+/// one. Returning `None` terminates the task.
+pub trait Schedule: Send + 'static {
+    /// Invoked right before every execution of the task, with the instant at which it started.
+    /// The default implementation does nothing.
+    fn on_run(&mut self, _start: Instant) {}
+
+    /// Returns how long to wait, starting from `now`, before the next execution of the task, or
+    /// `None` if the task should not run anymore.
+    fn next_after(&mut self, now: Instant) -> Option<Duration>;
+
+    /// Returns the interval between executions, for schedules that have one. The default
+    /// implementation returns `None`.
+    fn interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Changes the interval between executions, for schedules that have one. The new interval
+    /// will be used starting from the next call to `next_after`. The default implementation
+    /// does nothing.
+    fn set_interval(&mut self, _interval: Duration) {}
+
+    /// Returns how late the schedule currently is compared to its nominal times, for schedules
+    /// that keep track of it. The default implementation returns zero.
+    fn delay(&self) -> Duration {
+        Duration::from_secs(0)
+    }
+
+    /// Returns the number of ticks skipped so far because the task fell behind, for schedules
+    /// that skip them. The default implementation returns zero.
+    fn skipped_ticks(&self) -> u64 {
+        0
+    }
+
+    /// Returns the kind of the schedule, as reported by the executors' `tasks` methods. The
+    /// default implementation returns `ScheduleKind::Custom`.
+    fn kind(&self) -> ScheduleKind {
+        ScheduleKind::Custom
+    }
+}
+
+/// Describes the schedule of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum ScheduleKind {
+    /// A `FixedInterval` schedule.
+    FixedInterval,
+    /// A `FixedRate` schedule.
+    FixedRate,
+    /// A `CronSchedule`.
+    Cron,
+    /// A task running only once.
+    Once,
+    /// Any other schedule.
+    Custom,
+}
+
+/// Runs the task every `interval`; if one execution takes longer than `interval`, all the
+/// subsequent executions will be delayed.
+#[derive(Debug, Clone)]
+pub struct FixedInterval {
+    initial: Option<Duration>,
+    interval: Duration,
+    last_run: Option<Instant>,
+}
+
+impl FixedInterval {
+    /// Creates a new schedule, that will run the first execution after `initial`.
+    pub fn new(initial: Duration, interval: Duration) -> FixedInterval {
+        FixedInterval { initial: Some(initial), interval, last_run: None }
+    }
+
+    /// Creates a new schedule, that will run the first execution after a random delay, uniformly
+    /// distributed between zero and `interval`. This can be used to spread many similar tasks
+    /// over the period.
+    pub fn splayed(interval: Duration) -> FixedInterval {
+        FixedInterval::new(splay(interval), interval)
+    }
+}
+
+impl Schedule for FixedInterval {
+    fn on_run(&mut self, start: Instant) {
+        self.last_run = Some(start);
+    }
+
+    fn next_after(&mut self, now: Instant) -> Option<Duration> {
+        if let Some(initial) = self.initial.take() {
+            return Some(initial);
+        }
+        let execution = self.last_run.map(|start| now.duration_since(start))
+            .unwrap_or_else(|| Duration::from_secs(0));
+        if execution >= self.interval {
+            Some(Duration::from_secs(0))
+        } else {
+            Some(self.interval - execution)
+        }
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    fn kind(&self) -> ScheduleKind {
+        ScheduleKind::FixedInterval
+    }
+}
+
+/// Runs the task every `interval`; if an execution takes longer than `interval`, the wait time
+/// between the following executions will be reduced to decrease the overall delay. The ticks
+/// missed meanwhile are handled according to the `MissedTickPolicy`, `Burst` by default.
+#[derive(Debug, Clone)]
+pub struct FixedRate {
+    initial: Option<Duration>,
+    interval: Duration,
+    policy: MissedTickPolicy,
+    max_catchup: Option<Duration>,
+    delay: Duration,
+    skipped: u64,
+    last_run: Option<Instant>,
+}
+
+impl FixedRate {
+    /// Creates a new schedule, that will run the first execution after `initial`.
+    pub fn new(initial: Duration, interval: Duration) -> FixedRate {
+        FixedRate::with_policy(initial, interval, MissedTickPolicy::Burst)
+    }
+
+    /// Like `new`, but the missed ticks are handled with the given policy.
+    pub fn with_policy(initial: Duration, interval: Duration, policy: MissedTickPolicy) -> FixedRate {
+        FixedRate {
+            initial: Some(initial),
+            interval,
+            policy,
+            max_catchup: None,
+            delay: Duration::from_secs(0),
+            skipped: 0,
+            last_run: None,
+        }
+    }
+
+    /// Limits how late the schedule can fall behind: once the accumulated delay exceeds
+    /// `max_catchup`, it is discarded, and the following executions are spaced by the interval
+    /// again from the next one. By default, the delay is never discarded.
+    pub fn max_catchup(mut self, max_catchup: Duration) -> FixedRate {
+        self.max_catchup = Some(max_catchup);
+        self
+    }
+
+    /// Creates a new schedule, that will run the first execution after a random delay, uniformly
+    /// distributed between zero and `interval`. This can be used to spread many similar tasks
+    /// over the period.
+    pub fn splayed(interval: Duration) -> FixedRate {
+        FixedRate::new(splay(interval), interval)
+    }
+}
+
+/// Returns a random duration in `[0, interval)`.
+fn splay(interval: Duration) -> Duration {
+    interval.mul_f64(Rng::from_clock().next_f64())
+}
+
+/// Converts a number of nanoseconds to a duration, saturating at `Duration::MAX`.
+fn from_nanos(nanos: u128) -> Duration {
+    let secs = nanos / 1_000_000_000;
+    if secs > u128::from(u64::MAX) {
+        return Duration::MAX;
+    }
+    Duration::new(secs as u64, (nanos % 1_000_000_000) as u32)
+}
+
+/// The outcome of `calculate_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NextRun {
+    /// How long to wait before the next execution.
+    wait: Duration,
+    /// How late the next execution will be compared to its nominal time, which the following
+    /// ones will try to catch up.
+    delay: Duration,
+}
+
+/// Computes the next run of a fixed rate schedule, given the duration of the last execution and
+/// how late it started. A delay larger than `max_catchup` is discarded, so the schedule restarts
+/// from the next execution. The arithmetic saturates instead of overflowing: a delay that would
+/// exceed `Duration::MAX` stays at `Duration::MAX`.
+fn calculate_delay(interval: Duration, execution: Duration, delay: Duration, max_catchup: Option<Duration>) -> NextRun {
+    // How long after its nominal time the last execution ended
+    let late = delay.saturating_add(execution);
+    let next_run = match late.checked_sub(interval) {
+        Some(delay) => NextRun { wait: Duration::from_secs(0), delay },
+        None => NextRun { wait: interval - late, delay: Duration::from_secs(0) },
+    };
+    match max_catchup {
+        Some(max_catchup) if next_run.delay > max_catchup => NextRun { delay: Duration::from_secs(0), ..next_run },
+        _ => next_run,
+    }
+}
+
+impl Schedule for FixedRate {
+    fn on_run(&mut self, start: Instant) {
+        self.last_run = Some(start);
+    }
+
+    fn next_after(&mut self, now: Instant) -> Option<Duration> {
+        if let Some(initial) = self.initial.take() {
+            return Some(initial);
+        }
+        let execution = self.last_run.map(|start| now.duration_since(start))
+            .unwrap_or_else(|| Duration::from_secs(0));
+        let next_run = calculate_delay(self.interval, execution, self.delay, self.max_catchup);
+        self.delay = match self.policy {
+            MissedTickPolicy::Burst => next_run.delay,
+            MissedTickPolicy::Skip if self.interval > Duration::from_secs(0) => {
+                let interval = self.interval.as_nanos();
+                let skipped = (next_run.delay.as_nanos() / interval).min(u128::from(u64::MAX)) as u64;
+                self.skipped = self.skipped.saturating_add(skipped);
+                from_nanos(next_run.delay.as_nanos() % interval)
+            }
+            MissedTickPolicy::Skip | MissedTickPolicy::Delay => Duration::from_secs(0),
+        };
+        Some(next_run.wait)
+    }
+
+    fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    fn skipped_ticks(&self) -> u64 {
+        self.skipped
+    }
+
+    fn kind(&self) -> ScheduleKind {
+        ScheduleKind::FixedRate
+    }
+}
+
+/// What a schedule does with the ticks it missed, when it fell behind by more than one interval,
+/// for example because an execution took too long or the process was suspended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissedTickPolicy {
+    /// Runs the missed ticks back to back, until the schedule caught up.
+    #[default]
+    Burst,
+    /// Skips the missed ticks, and resumes with the next one; the skipped ticks are counted in
+    /// `TaskStats::skipped_ticks`.
+    Skip,
+    /// Runs the late tick right away, and shifts the following ones by as much, so they are
+    /// spaced by the interval again.
+    Delay,
+}
+
+/// Runs the task at fixed rate, with the Nth execution targeted at `start + initial + N *
+/// interval`, where `start` is when the task was scheduled. Unlike `FixedRate`, whose waits are
+/// computed from the previous execution, the ticks stay in phase with the start instant however
+/// long the task runs, so no drift accumulates over time. The ticks missed when the task falls
+/// behind by more than one interval are handled according to the `MissedTickPolicy`.
+#[derive(Debug, Clone)]
+pub struct Anchored {
+    initial: Duration,
+    interval: Duration,
+    policy: MissedTickPolicy,
+    /// The target of the tick number zero, set on the first call to `next_after`.
+    anchor: Option<Instant>,
+    /// The number of the next tick.
+    next_tick: u64,
+    delay: Duration,
+    skipped: u64,
+}
+
+impl Anchored {
+    /// Creates a new schedule, that will run the first execution after `initial`, and then every
+    /// `interval`, running the missed ticks back to back.
+    pub fn new(initial: Duration, interval: Duration) -> Anchored {
+        Anchored::with_policy(initial, interval, MissedTickPolicy::Burst)
+    }
+
+    /// Like `new`, but the missed ticks are handled with the given policy.
+    pub fn with_policy(initial: Duration, interval: Duration, policy: MissedTickPolicy) -> Anchored {
+        Anchored {
+            initial,
+            interval,
+            policy,
+            anchor: None,
+            next_tick: 0,
+            delay: Duration::from_secs(0),
+            skipped: 0,
+        }
+    }
+
+    /// Returns the target of the given tick.
+    fn target(&self, anchor: Instant, tick: u64) -> Instant {
+        let offset = self.interval.as_nanos().saturating_mul(u128::from(tick)).min(u128::from(u64::MAX));
+        deadline_after(anchor, Duration::from_nanos(offset as u64))
+    }
+}
+
+impl Schedule for Anchored {
+    fn next_after(&mut self, now: Instant) -> Option<Duration> {
+        let initial = self.initial;
+        let anchor = *self.anchor.get_or_insert_with(|| deadline_after(now, initial));
+        let mut target = self.target(anchor, self.next_tick);
+        let interval = self.interval.as_nanos();
+        match self.policy {
+            MissedTickPolicy::Burst => (),
+            MissedTickPolicy::Skip if interval > 0 => {
+                let behind = (now.saturating_duration_since(target).as_nanos() / interval) as u64;
+                if behind > 0 {
+                    self.next_tick += behind;
+                    self.skipped += behind;
+                    target = self.target(anchor, self.next_tick);
+                }
+            }
+            MissedTickPolicy::Skip => (),
+            // The schedule is anchored again on the late tick
+            MissedTickPolicy::Delay => if target < now {
+                self.anchor = Some(now);
+                self.next_tick = 0;
+                target = now;
+            },
+        }
+        self.next_tick += 1;
+        self.delay = now.saturating_duration_since(target);
+        Some(target.saturating_duration_since(now))
+    }
+
+    fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    /// The schedule is anchored again on the last tick, so the ticks keep their phase with it.
+    fn set_interval(&mut self, interval: Duration) {
+        if let Some(anchor) = self.anchor {
+            if self.next_tick > 0 {
+                self.anchor = Some(self.target(anchor, self.next_tick - 1));
+                self.next_tick = 1;
+            }
+        }
+        self.interval = interval;
+    }
+
+    fn skipped_ticks(&self) -> u64 {
+        self.skipped
+    }
+
+    fn kind(&self) -> ScheduleKind {
+        ScheduleKind::FixedRate
+    }
+}
+
+/// Waits, before each execution, for the next duration produced by the iterator. The task will
+/// stop when the iterator is exhausted.
+#[derive(Debug, Clone)]
+pub struct DelaySequence<I> {
+    delays: I,
+}
+
+impl<I> DelaySequence<I>
+    where I: Iterator<Item = Duration> + Send + 'static
+{
+    /// Creates a new schedule from the given iterator.
+    pub fn new(delays: I) -> DelaySequence<I> {
+        DelaySequence { delays }
+    }
+}
+
+impl<I> Schedule for DelaySequence<I>
+    where I: Iterator<Item = Duration> + Send + 'static
+{
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        self.delays.next()
+    }
+}
+
+/// An exponential backoff: the first execution happens after `initial`, and every following wait
+/// is `factor` times longer than the previous one, up to `max`. The backoff can be `reset` to start
+/// again from `initial`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    factor: f64,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    /// Creates a new exponential backoff. A `factor` lower than 1 is treated as 1.
+    pub fn exponential(initial: Duration, factor: f64, max: Duration) -> Backoff {
+        let factor = if factor >= 1.0 { factor } else { 1.0 };
+        let initial = if initial > max { max } else { initial };
+        Backoff { initial, factor, max, current: initial }
+    }
+
+    /// Returns the next wait, and moves the backoff forward. If the multiplication overflows,
+    /// the wait is clamped to `max`.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        let next = delay.as_secs_f64() * self.factor;
+        self.current = if next.is_finite() && next < self.max.as_secs_f64() {
+            Duration::from_secs_f64(next)
+        } else {
+            self.max
+        };
+        delay
+    }
+
+    /// Resets the backoff: the next wait will be `initial` again.
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+impl Schedule for Backoff {
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        Some(self.next_delay())
+    }
+}
+
+/// Defines how many times a failing job is attempted, and how long to wait between attempts. As a
+/// `Schedule`, the first attempt runs right away and the task stops after the last one.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    backoff: Backoff,
+    max_attempts: u32,
+    attempts: u32,
+}
+
+impl RetryPolicy {
+    /// Attempts the job up to `max_attempts` times, waiting `delay` between attempts.
+    pub fn fixed(delay: Duration, max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::with_backoff(Backoff::exponential(delay, 1.0, delay), max_attempts)
+    }
+
+    /// Attempts the job up to `max_attempts` times, waiting `initial` after the first failure, and
+    /// `factor` times longer after each following one, up to `max`.
+    pub fn exponential(initial: Duration, factor: f64, max: Duration, max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::with_backoff(Backoff::exponential(initial, factor, max), max_attempts)
+    }
+
+    /// Attempts the job up to `max_attempts` times, waiting between attempts according to
+    /// `backoff`.
+    pub fn with_backoff(backoff: Backoff, max_attempts: u32) -> RetryPolicy {
+        RetryPolicy { backoff, max_attempts, attempts: 0 }
+    }
+
+    /// Returns the maximum number of attempts.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+}
+
+impl Schedule for RetryPolicy {
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        if self.attempts >= self.max_attempts {
+            return None;
+        }
+        self.attempts += 1;
+        if self.attempts == 1 {
+            Some(Duration::from_secs(0))
+        } else {
+            Some(self.backoff.next_delay())
+        }
+    }
+}
+
+/// A small xorshift pseudo-random generator, used to randomize schedules.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        // Scramble the seed with a splitmix64 step, so that similar seeds produce different
+        // sequences. The state must never be zero.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng(if z == 0 { 1 } else { z })
+    }
+
+    /// Creates a generator seeded from the system clock. Generators created at the same time
+    /// will still produce different sequences.
+    pub(crate) fn from_clock() -> Rng {
+        static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() ^ u64::from(duration.subsec_nanos()))
+            .unwrap_or(0);
+        let instance = INSTANCES.fetch_add(1, Ordering::Relaxed) as u64;
+        Rng::new(nanos ^ instance.rotate_left(32))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number uniformly distributed in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Adds a random jitter to another schedule: every execution will happen up to `jitter` before or
+/// after the time chosen by the inner schedule. The jitter is applied around the times chosen by
+/// the inner schedule, so it doesn't accumulate and the long-run rate of the task is not affected.
+#[derive(Debug, Clone)]
+pub struct Jittered<S> {
+    inner: S,
+    jitter: Duration,
+    offset: f64,
+    rng: Rng,
+}
+
+impl<S: Schedule> Jittered<S> {
+    /// Adds a random jitter to the given schedule.
+    pub fn new(inner: S, jitter: Duration) -> Jittered<S> {
+        Jittered { inner, jitter, offset: 0.0, rng: Rng::from_clock() }
+    }
+
+    /// Adds a random jitter to the given schedule, using `seed` to initialize the random number
+    /// generator. Two schedules created with the same seed will produce the same jitter.
+    pub fn with_seed(inner: S, jitter: Duration, seed: u64) -> Jittered<S> {
+        Jittered { inner, jitter, offset: 0.0, rng: Rng::new(seed) }
+    }
+}
+
+impl<S: Schedule> Schedule for Jittered<S> {
+    fn on_run(&mut self, start: Instant) {
+        self.inner.on_run(start);
+    }
+
+    fn next_after(&mut self, now: Instant) -> Option<Duration> {
+        let nominal = self.inner.next_after(now)?.as_secs_f64();
+        // The previous execution was moved by `self.offset`, which has to be compensated
+        let offset = (self.rng.next_f64() * 2.0 - 1.0) * self.jitter.as_secs_f64();
+        let wait = nominal + offset - self.offset;
+        if wait > 0.0 {
+            self.offset = offset;
+            Some(Duration::from_secs_f64(wait))
+        } else {
+            // The execution can't be moved in the past: it will run right away
+            self.offset -= nominal;
+            Some(Duration::from_secs(0))
+        }
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.inner.interval()
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.inner.set_interval(interval);
+    }
+
+    fn delay(&self) -> Duration {
+        self.inner.delay()
+    }
+
+    fn skipped_ticks(&self) -> u64 {
+        self.inner.skipped_ticks()
+    }
+
+    fn kind(&self) -> ScheduleKind {
+        self.inner.kind()
+    }
+}
+
+/// Limits another schedule to a deadline: the task will stop as soon as its next execution would
+/// happen after `deadline`. If the deadline is already passed, the task won't run at all.
+#[derive(Debug, Clone)]
+pub struct Until<S> {
+    inner: S,
+    deadline: Instant,
+}
+
+impl<S: Schedule> Until<S> {
+    /// Limits the given schedule to `deadline`.
+    pub fn new(inner: S, deadline: Instant) -> Until<S> {
+        Until { inner, deadline }
+    }
+}
+
+impl<S: Schedule> Schedule for Until<S> {
+    fn on_run(&mut self, start: Instant) {
+        self.inner.on_run(start);
+    }
+
+    fn next_after(&mut self, now: Instant) -> Option<Duration> {
+        if now >= self.deadline {
+            return None;
+        }
+        match self.inner.next_after(now) {
+            Some(wait) if wait <= self.deadline - now => Some(wait),
+            _ => None,
+        }
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.inner.interval()
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.inner.set_interval(interval);
+    }
+
+    fn delay(&self) -> Duration {
+        self.inner.delay()
+    }
+
+    fn skipped_ticks(&self) -> u64 {
+        self.inner.skipped_ticks()
+    }
+
+    fn kind(&self) -> ScheduleKind {
+        self.inner.kind()
+    }
+}
+
+/// Runs a task on the wall-clock boundaries of a period: with a period of one minute, the task
+/// runs at the top of every minute. The boundaries are the multiples of the period since the
+/// Unix epoch, so a period of one day is aligned to midnight UTC. Every wait is computed from the
+/// system clock to the next boundary, so the ticks don't drift from the boundaries, however long
+/// the executions take; a boundary missed while an execution was running is skipped. The
+/// alignment is best effort: the ticks land shortly after the boundaries, with the precision of
+/// the timers of the event loop, which is about a millisecond.
+#[derive(Debug, Clone)]
+pub struct Aligned {
+    period: Duration,
+    last_fire: Option<SystemTime>,
+}
+
+impl Aligned {
+    /// Aligns the ticks to the boundaries of the given period. A zero period is treated as one
+    /// nanosecond.
+    pub fn new(period: Duration) -> Aligned {
+        Aligned { period: period.max(Duration::new(0, 1)), last_fire: None }
+    }
+
+    /// Returns the first boundary strictly after `after`.
+    fn next_boundary(&self, after: SystemTime) -> SystemTime {
+        let since_epoch = after.duration_since(UNIX_EPOCH).unwrap_or_else(|_| Duration::from_secs(0)).as_nanos();
+        let period = self.period.as_nanos();
+        let boundary = (since_epoch / period + 1) * period;
+        // A boundary too far to be represented is clamped to `MAX_INTERVAL` after `after`
+        UNIX_EPOCH.checked_add(from_nanos(boundary)).unwrap_or_else(|| after + MAX_INTERVAL)
+    }
+}
+
+impl Schedule for Aligned {
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        let now = SystemTime::now();
+        // A timer firing slightly early must not run the same boundary twice
+        let after = match self.last_fire {
+            Some(last_fire) if last_fire > now => last_fire,
+            _ => now,
+        };
+        let next_fire = self.next_boundary(after);
+        self.last_fire = Some(next_fire);
+        Some(next_fire.duration_since(now).unwrap_or_else(|_| Duration::from_secs(0)))
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(self.period)
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.period = interval.max(Duration::new(0, 1));
+    }
+}
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// How many years in the future to look for a matching time before giving up.
+const MAX_SEARCH_YEARS: i64 = 30;
+
+const MONTH_NAMES: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"
+];
+const DAY_NAMES: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// The error returned when a cron expression is not valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CronError {
+    /// The expression doesn't contain 5 or 6 fields.
+    FieldCount(usize),
+    /// One of the fields contains an invalid value.
+    InvalidField {
+        /// The name of the field.
+        field: &'static str,
+        /// The content of the field.
+        value: String,
+    },
+    /// The expression is well formed but it will never match any date (e.g. `0 0 30 2 *`).
+    NeverFires,
+}
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CronError::FieldCount(count) =>
+                write!(f, "cron expression should have 5 or 6 fields, found {}", count),
+            CronError::InvalidField { field, ref value } =>
+                write!(f, "invalid value for cron field {}: {:?}", field, value),
+            CronError::NeverFires =>
+                write!(f, "cron expression never matches any date"),
+        }
+    }
+}
+
+impl Error for CronError {
+    fn description(&self) -> &str {
+        "invalid cron expression"
+    }
+}
+
+/// A set of allowed values for one of the fields of the expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FieldSet(u64);
+
+impl FieldSet {
+    fn range(min: u64, max: u64) -> FieldSet {
+        FieldSet((min..=max).fold(0, |set, n| set | 1 << n))
+    }
+
+    fn contains(&self, value: u64) -> bool {
+        self.0 & (1 << value) != 0
+    }
+}
+
+/// The timezone whose local time a `CronSchedule` is matched against, instead of UTC.
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CronTimezone {
+    Named(Tz),
+    /// The timezone of the system, looked up on every computation.
+    Local,
+}
+
+/// A parsed cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    seconds: FieldSet,
+    minutes: FieldSet,
+    hours: FieldSet,
+    days_of_month: FieldSet,
+    months: FieldSet,
+    days_of_week: FieldSet,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+    #[cfg(feature = "chrono")]
+    timezone: Option<CronTimezone>,
+    last_fire: Option<SystemTime>,
+}
+
+fn parse_value(value: &str, names: &[&str], offset: u64) -> Option<u64> {
+    if let Ok(n) = value.parse::<u64>() {
+        return Some(n);
+    }
+    names.iter()
+        .position(|name| name.eq_ignore_ascii_case(value))
+        .map(|position| position as u64 + offset)
+}
+
+fn parse_field(field: &'static str, value: &str, min: u64, max: u64, names: &[&str]) -> Result<FieldSet, CronError> {
+    let invalid = || CronError::InvalidField { field, value: value.to_owned() };
+    let mut set = 0u64;
+    for part in value.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(position) => {
+                let step = part[position + 1..].parse::<u64>().map_err(|_| invalid())?;
+                (&part[..position], step)
+            },
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = if range == "*" || range == "?" {
+            (min, max)
+        } else if let Some(position) = range.find('-') {
+            let start = parse_value(&range[..position], names, min).ok_or_else(invalid)?;
+            let end = parse_value(&range[position + 1..], names, min).ok_or_else(invalid)?;
+            (start, end)
+        } else {
+            let start = parse_value(range, names, min).ok_or_else(invalid)?;
+            // A single value with a step, like `5/15`, means "from 5 to the end".
+            (start, if part.contains('/') { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        let mut n = start;
+        while n <= end {
+            set |= 1 << n;
+            n = match n.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+    Ok(FieldSet(set))
+}
+
+fn is_restricted(value: &str) -> bool {
+    !(value.starts_with('*') || value.starts_with('?'))
+}
+
+/// Returns the number of days since the Unix epoch of the given date.
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the date corresponding to the given number of days since the Unix epoch.
+fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl CronSchedule {
+    /// Parses a cron expression.
+    pub fn parse(expression: &str) -> Result<CronSchedule, CronError> {
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let (second, rest) = match fields.len() {
+            5 => ("0", &fields[..]),
+            6 => (fields[0], &fields[1..]),
+            count => return Err(CronError::FieldCount(count)),
+        };
+        let mut days_of_week = parse_field("day of week", rest[4], 0, 7, DAY_NAMES)?;
+        if days_of_week.contains(7) {
+            // Both 0 and 7 are Sunday
+            days_of_week = FieldSet((days_of_week.0 | 1) & !(1 << 7));
+        }
+        let schedule = CronSchedule {
+            seconds: parse_field("second", second, 0, 59, &[])?,
+            minutes: parse_field("minute", rest[0], 0, 59, &[])?,
+            hours: parse_field("hour", rest[1], 0, 23, &[])?,
+            days_of_month: parse_field("day of month", rest[2], 1, 31, &[])?,
+            months: parse_field("month", rest[3], 1, 12, MONTH_NAMES)?,
+            days_of_week,
+            day_of_month_restricted: is_restricted(rest[2]),
+            day_of_week_restricted: is_restricted(rest[4]),
+            #[cfg(feature = "chrono")]
+            timezone: None,
+            last_fire: None,
+        };
+        if schedule.next_fire_after(SystemTime::now()).is_none() {
+            return Err(CronError::NeverFires);
+        }
+        Ok(schedule)
+    }
+
+    /// Creates a schedule that fires every day at the given UTC time, or at the given local time
+    /// of the timezone set with `in_timezone` or `in_local_timezone`. Returns an error if the time
+    /// is not valid.
+    pub fn daily(hour: u32, minute: u32, second: u32) -> Result<CronSchedule, CronError> {
+        let check = |field: &'static str, value: u32, max: u32| {
+            if value > max {
+                Err(CronError::InvalidField { field, value: value.to_string() })
+            } else {
+                Ok(FieldSet(1 << value))
+            }
+        };
+        Ok(CronSchedule {
+            seconds: check("second", second, 59)?,
+            minutes: check("minute", minute, 59)?,
+            hours: check("hour", hour, 23)?,
+            days_of_month: FieldSet::range(1, 31),
+            months: FieldSet::range(1, 12),
+            days_of_week: FieldSet::range(0, 6),
+            day_of_month_restricted: false,
+            day_of_week_restricted: false,
+            #[cfg(feature = "chrono")]
+            timezone: None,
+            last_fire: None,
+        })
+    }
+
+    fn day_matches(&self, day_of_month: u64, day_of_week: u64) -> bool {
+        let dom = self.days_of_month.contains(day_of_month);
+        let dow = self.days_of_week.contains(day_of_week);
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    /// Returns the first time strictly after `after` (in seconds since the epoch) that matches the
+    /// expression.
+    fn next_fire_after_secs(&self, after: u64) -> Option<u64> {
+        let mut t = after + 1;
+        let (start_year, _, _) = civil_from_days((t / SECONDS_PER_DAY) as i64);
+        loop {
+            let days = t / SECONDS_PER_DAY;
+            let (year, month, day) = civil_from_days(days as i64);
+            if year > start_year + MAX_SEARCH_YEARS {
+                return None;
+            }
+            if !self.months.contains(month) {
+                let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+                t = days_from_civil(next_year, next_month, 1) as u64 * SECONDS_PER_DAY;
+                continue;
+            }
+            // 1970-01-01 was a Thursday
+            let day_of_week = (days + 4) % 7;
+            if !self.day_matches(day, day_of_week) {
+                t = (days + 1) * SECONDS_PER_DAY;
+                continue;
+            }
+            let second_of_day = t % SECONDS_PER_DAY;
+            let hour = second_of_day / 3600;
+            if !self.hours.contains(hour) {
+                t = days * SECONDS_PER_DAY + (hour + 1) * 3600;
+                continue;
+            }
+            let minute = (second_of_day % 3600) / 60;
+            if !self.minutes.contains(minute) {
+                t = days * SECONDS_PER_DAY + hour * 3600 + (minute + 1) * 60;
+                continue;
+            }
+            if !self.seconds.contains(second_of_day % 60) {
+                t += 1;
+                continue;
+            }
+            return Some(t);
+        }
+    }
+
+    /// Returns a schedule that matches the expression against the local time of the given
+    /// timezone, instead of UTC. If a matching local time is skipped by a DST transition, the
+    /// task will fire at the first valid time after the transition; if a matching local time
+    /// occurs twice, the task will fire only the first time.
+    #[cfg(feature = "chrono")]
+    pub fn in_timezone(self, timezone: Tz) -> CronSchedule {
+        CronSchedule { timezone: Some(CronTimezone::Named(timezone)), ..self }
+    }
+
+    /// Like `in_timezone`, but with the local timezone of the system.
+    #[cfg(feature = "chrono")]
+    pub fn in_local_timezone(self) -> CronSchedule {
+        CronSchedule { timezone: Some(CronTimezone::Local), ..self }
+    }
+
+    /// Like `next_fire_after_secs`, but matching against the local time of `timezone`.
+    #[cfg(feature = "chrono")]
+    fn next_fire_after_secs_in<Z: TimeZone>(&self, after: u64, timezone: Z) -> Option<u64> {
+        let to_naive = |secs: i64| DateTime::from_timestamp(secs, 0).map(|time| time.naive_utc());
+        let mut local = timezone.from_utc_datetime(&to_naive(after as i64)?)
+            .naive_local()
+            .and_utc()
+            .timestamp();
+        loop {
+            let local_fire = self.next_fire_after_secs(local as u64)? as i64;
+            let fire = match timezone.from_local_datetime(&to_naive(local_fire)?) {
+                LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.timestamp(),
+                LocalResult::None => {
+                    // The local time doesn't exist: look for the end of the DST gap
+                    (1..24 * 60)
+                        .filter_map(|minutes| match timezone.from_local_datetime(&to_naive(local_fire + minutes * 60)?) {
+                            LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => Some(time.timestamp()),
+                            LocalResult::None => None,
+                        })
+                        .next()?
+                }
+            };
+            if fire > after as i64 {
+                return Some(fire as u64);
+            }
+            // This local time already happened (this is the second occurrence of a repeated hour)
+            local = local_fire;
+        }
+    }
+
+    /// Returns the first time strictly after `after` that matches the expression, or `None` if
+    /// no such time could be found.
+    pub fn next_fire_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let after_secs = after.duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        #[cfg(feature = "chrono")]
+        {
+            let next_fire = match self.timezone {
+                Some(CronTimezone::Named(timezone)) => Some(self.next_fire_after_secs_in(after_secs, timezone)),
+                Some(CronTimezone::Local) => Some(self.next_fire_after_secs_in(after_secs, Local)),
+                None => None,
+            };
+            if let Some(next_fire) = next_fire {
+                return next_fire.map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+            }
+        }
+        self.next_fire_after_secs(after_secs)
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+}
+
+impl CronSchedule {
+    /// The wait before the next fire, when the system clock reads `now`.
+    fn next_wait_at(&mut self, now: SystemTime) -> Option<Duration> {
+        let after = match self.last_fire {
+            Some(last_fire) if last_fire > now => last_fire,
+            _ => now,
+        };
+        let next_fire = self.next_fire_after(after)?;
+        self.last_fire = Some(next_fire);
+        Some(next_fire.duration_since(now).unwrap_or_else(|_| Duration::from_secs(0)))
+    }
+}
+
+impl Schedule for CronSchedule {
+    fn next_after(&mut self, _now: Instant) -> Option<Duration> {
+        // The wait is recomputed from the system clock before every sleep, so that clock
+        // adjustments only affect the current iteration.
+        self.next_wait_at(SystemTime::now())
+    }
+
+    fn kind(&self) -> ScheduleKind {
+        ScheduleKind::Cron
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = CronError;
+
+    fn from_str(s: &str) -> Result<CronSchedule, CronError> {
+        CronSchedule::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// Seconds since the epoch, at the given UTC date and time.
+    fn ts(year: i64, month: u64, day: u64, hour: u64, minute: u64, second: u64) -> u64 {
+        days_from_civil(year, month, day) as u64 * 86_400 + hour * 3600 + minute * 60 + second
+    }
+
+    fn next(expression: &str, after: u64) -> Option<u64> {
+        CronSchedule::parse(expression).unwrap().next_fire_after_secs(after)
+    }
+
+    #[test]
+    fn calculate_delay_overflow_test() {
+        let values = [
+            Duration::from_secs(0),
+            Duration::new(0, 1),
+            Duration::from_secs(1),
+            Duration::from_secs(u64::MAX / 2),
+            Duration::MAX - Duration::new(0, 1),
+            Duration::MAX,
+        ];
+        for &interval in &values {
+            for &execution in &values {
+                for &delay in &values {
+                    for max_catchup in values.iter().cloned().map(Some).chain(Some(None)) {
+                        let next_run = calculate_delay(interval, execution, delay, max_catchup);
+                        assert!(next_run.wait <= interval);
+                        assert!(next_run.wait == Duration::from_secs(0) || next_run.delay == Duration::from_secs(0));
+                        assert!(max_catchup.is_none_or(|max_catchup| next_run.delay <= max_catchup));
+                    }
+                }
+            }
+        }
+        let max = Duration::MAX;
+        assert_eq!(calculate_delay(Duration::from_secs(1), max, max, None).delay, max - Duration::from_secs(1));
+        assert_eq!(calculate_delay(max, Duration::from_secs(1), Duration::from_secs(2), None).wait, max - Duration::from_secs(3));
+
+        let mut schedule = FixedRate::with_policy(Duration::from_secs(0), Duration::new(0, 1), MissedTickPolicy::Skip);
+        schedule.next_after(Instant::now());
+        schedule.delay = max;
+        schedule.on_run(Instant::now());
+        assert_eq!(schedule.next_after(Instant::now()), Some(Duration::from_secs(0)));
+        assert_eq!(schedule.skipped_ticks(), u64::MAX);
+    }
+
+    #[test]
+    fn calculate_delay_test() {
+        fn s(n: u64) -> Duration { Duration::from_secs(n) }
+        fn run(wait: Duration, delay: Duration) -> NextRun { NextRun { wait, delay } }
+        assert_eq!(calculate_delay(s(10), s(3), s(0), None), run(s(7), s(0)));
+        assert_eq!(calculate_delay(s(10), s(11), s(0), None), run(s(0), s(1)));
+        assert_eq!(calculate_delay(s(10), s(3), s(3), None), run(s(4), s(0)));
+        assert_eq!(calculate_delay(s(10), s(3), s(9), None), run(s(0), s(2)));
+        assert_eq!(calculate_delay(s(10), s(12), s(15), None), run(s(0), s(17)));
+        // capped catch-up
+        assert_eq!(calculate_delay(s(10), s(12), s(15), Some(s(20))), run(s(0), s(17)));
+        assert_eq!(calculate_delay(s(10), s(12), s(15), Some(s(17))), run(s(0), s(17)));
+        assert_eq!(calculate_delay(s(10), s(12), s(15), Some(s(16))), run(s(0), s(0)));
+        assert_eq!(calculate_delay(s(10), s(3), s(9), Some(s(1))), run(s(0), s(0)));
+        assert_eq!(calculate_delay(s(10), s(3), s(3), Some(s(1))), run(s(4), s(0)));
+        // a single execution exceeding the cap
+        assert_eq!(calculate_delay(s(10), s(40), s(0), Some(s(5))), run(s(0), s(0)));
+        assert_eq!(calculate_delay(s(10), s(40), s(0), Some(s(0))), run(s(0), s(0)));
+    }
+
+    #[test]
+    fn fixed_schedules_test() {
+        fn s(n: u64) -> Duration { Duration::from_secs(n) }
+        let start = Instant::now();
+        let mut interval = FixedInterval::new(s(1), s(10));
+        let mut rate = FixedRate::new(s(1), s(10));
+        assert_eq!(interval.next_after(start), Some(s(1)));
+        assert_eq!(rate.next_after(start), Some(s(1)));
+        // A slow execution
+        interval.on_run(start);
+        rate.on_run(start);
+        assert_eq!(interval.next_after(start + s(12)), Some(s(0)));
+        assert_eq!(rate.next_after(start + s(12)), Some(s(0)));
+        assert_eq!(rate.delay(), s(2));
+        assert_eq!(interval.delay(), s(0));
+        // followed by a fast one
+        interval.on_run(start + s(12));
+        rate.on_run(start + s(12));
+        assert_eq!(interval.next_after(start + s(13)), Some(s(9)));
+        assert_eq!(rate.next_after(start + s(13)), Some(s(7)));
+        // with a new interval
+        interval.set_interval(s(5));
+        rate.set_interval(s(5));
+        assert_eq!(interval.interval(), Some(s(5)));
+        interval.on_run(start + s(22));
+        rate.on_run(start + s(20));
+        assert_eq!(interval.next_after(start + s(23)), Some(s(4)));
+        assert_eq!(rate.next_after(start + s(21)), Some(s(4)));
+    }
+
+    #[test]
+    fn backoff_test() {
+        fn ms(n: u64) -> Duration { Duration::from_millis(n) }
+        let mut backoff = Backoff::exponential(ms(100), 2.0, ms(1000));
+        let delays = (0..6).map(|_| backoff.next_delay()).collect::<Vec<_>>();
+        assert_eq!(delays, vec![ms(100), ms(200), ms(400), ms(800), ms(1000), ms(1000)]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), ms(100));
+        assert_eq!(backoff.next_after(Instant::now()), Some(ms(200)));
+
+        let max = Duration::new(u64::MAX, 0);
+        let mut backoff = Backoff::exponential(Duration::from_secs(u64::MAX / 2), 1e300, max);
+        backoff.next_delay();
+        assert_eq!(backoff.next_delay(), max);
+        assert_eq!(backoff.next_delay(), max);
+
+        let mut backoff = Backoff::exponential(ms(100), 0.5, ms(1000));
+        assert_eq!(backoff.next_delay(), ms(100));
+        assert_eq!(backoff.next_delay(), ms(100));
+    }
+
+    #[test]
+    fn retry_policy_test() {
+        fn ms(n: u64) -> Duration { Duration::from_millis(n) }
+        let now = Instant::now();
+        let mut fixed = RetryPolicy::fixed(ms(100), 3);
+        let delays = (0..4).map(|_| fixed.next_after(now)).collect::<Vec<_>>();
+        assert_eq!(delays, vec![Some(ms(0)), Some(ms(100)), Some(ms(100)), None]);
+        let mut exponential = RetryPolicy::exponential(ms(100), 2.0, ms(300), 5);
+        let delays = (0..6).map(|_| exponential.next_after(now)).collect::<Vec<_>>();
+        assert_eq!(delays, vec![Some(ms(0)), Some(ms(100)), Some(ms(200)), Some(ms(300)), Some(ms(300)), None]);
+        assert_eq!(RetryPolicy::fixed(ms(100), 0).next_after(now), None);
+    }
+
+    #[test]
+    fn jittered_test() {
+        fn ms(n: u64) -> Duration { Duration::from_millis(n) }
+        let waits = |seed| {
+            let mut schedule = Jittered::with_seed(FixedRate::new(ms(100), ms(1000)), ms(200), seed);
+            let start = Instant::now();
+            let mut now = start;
+            let mut waits = Vec::new();
+            for _ in 0..100 {
+                let wait = schedule.next_after(now).unwrap();
+                now += wait;
+                schedule.on_run(now);
+                waits.push(wait);
+            }
+            // The executions stay within the jitter of their nominal time
+            for (n, _) in waits.iter().enumerate() {
+                let time = waits[..n + 1].iter().fold(Duration::from_secs(0), |sum, wait| sum + *wait);
+                let nominal = ms(100) + ms(1000) * n as u32;
+                assert!(time.abs_diff(nominal) <= ms(200) + Duration::from_micros(1));
+            }
+            waits
+        };
+        assert_eq!(waits(42), waits(42));
+        assert_ne!(waits(42), waits(43));
+        assert!(waits(42).iter().any(|wait| *wait != ms(1000)));
+    }
+
+    #[test]
+    fn until_test() {
+        fn s(n: u64) -> Duration { Duration::from_secs(n) }
+        let start = Instant::now();
+        let mut schedule = Until::new(FixedRate::new(s(1), s(10)), start + s(15));
+        assert_eq!(schedule.next_after(start), Some(s(1)));
+        schedule.on_run(start + s(1));
+        assert_eq!(schedule.next_after(start + s(1)), Some(s(10)));
+        schedule.on_run(start + s(11));
+        assert_eq!(schedule.next_after(start + s(11)), None);
+        let mut past = Until::new(FixedRate::new(s(0), s(10)), start);
+        assert_eq!(past.next_after(start + s(1)), None);
+    }
+
+    #[test]
+    fn splayed_test() {
+        let interval = Duration::from_secs(10);
+        let initials = (0..50)
+            .map(|_| FixedRate::splayed(interval).next_after(Instant::now()).unwrap())
+            .collect::<Vec<_>>();
+        assert!(initials.iter().all(|initial| *initial < interval));
+        let min = initials.iter().min().unwrap();
+        let max = initials.iter().max().unwrap();
+        assert!(*max - *min > Duration::from_secs(5));
+        assert!(FixedInterval::splayed(interval).next_after(Instant::now()).unwrap() < interval);
+    }
+
+    #[test]
+    fn aligned_test() {
+        let at = |secs: u64, millis: u32| UNIX_EPOCH + Duration::new(secs, millis * 1_000_000);
+        let minute = Aligned::new(Duration::from_secs(60));
+        assert_eq!(minute.next_boundary(at(ts(2024, 3, 1, 10, 15, 42), 500)), at(ts(2024, 3, 1, 10, 16, 0), 0));
+        // a boundary is never returned for itself
+        assert_eq!(minute.next_boundary(at(ts(2024, 3, 1, 10, 16, 0), 0)), at(ts(2024, 3, 1, 10, 17, 0), 0));
+        let sub_second = Aligned::new(Duration::from_millis(250));
+        assert_eq!(sub_second.next_boundary(at(100, 600)), at(100, 750));
+
+        let mut schedule = Aligned::new(Duration::from_millis(100));
+        let first = schedule.next_after(Instant::now()).unwrap();
+        assert!(first <= Duration::from_millis(100));
+        // called again before the boundary, as when a timer fires early, the next one is returned
+        let second = schedule.next_after(Instant::now()).unwrap();
+        assert!(second > Duration::from_millis(90) && second <= Duration::from_millis(200));
+
+        // periods too long to be represented from the epoch are clamped
+        let now = SystemTime::now();
+        assert_eq!(Aligned::new(Duration::MAX).next_boundary(now), now + MAX_INTERVAL);
+        schedule.set_interval(Duration::MAX);
+        assert!(schedule.next_after(Instant::now()).unwrap() > MAX_INTERVAL - Duration::from_secs(1));
+    }
+
+    #[test]
+    fn anchored_test() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut schedule = Anchored::new(ms(5), ms(10));
+        assert_eq!(schedule.next_after(start), Some(ms(5)));
+        // the targets don't depend on when the executions end
+        assert_eq!(schedule.next_after(start + ms(8)), Some(ms(7)));
+        assert_eq!(schedule.next_after(start + ms(19)), Some(ms(6)));
+        // 32ms behind: the missed ticks run right away
+        assert_eq!(schedule.next_after(start + ms(67)), Some(ms(0)));
+        assert_eq!(schedule.delay(), ms(32));
+        assert_eq!(schedule.next_after(start + ms(68)), Some(ms(0)));
+        assert_eq!(schedule.skipped_ticks(), 0);
+
+        let mut schedule = Anchored::with_policy(ms(0), ms(10), MissedTickPolicy::Skip);
+        assert_eq!(schedule.next_after(start), Some(ms(0)));
+        assert_eq!(schedule.next_after(start + ms(4)), Some(ms(6)));
+        // 32ms behind the tick at 20ms: the ticks at 20, 30 and 40ms are skipped, and the one at
+        // 50ms runs right away
+        assert_eq!(schedule.next_after(start + ms(52)), Some(ms(0)));
+        assert_eq!(schedule.delay(), ms(2));
+        assert_eq!(schedule.skipped_ticks(), 3);
+        // less than an interval behind: the tick runs late
+        assert_eq!(schedule.next_after(start + ms(65)), Some(ms(0)));
+        assert_eq!(schedule.skipped_ticks(), 3);
+
+        // a new interval is anchored on the last tick
+        schedule.set_interval(ms(100));
+        assert_eq!(schedule.next_after(start + ms(70)), Some(ms(90)));
+
+        let mut schedule = Anchored::with_policy(ms(0), ms(10), MissedTickPolicy::Delay);
+        assert_eq!(schedule.next_after(start), Some(ms(0)));
+        // 15ms behind: the ticks are shifted by as much
+        assert_eq!(schedule.next_after(start + ms(25)), Some(ms(0)));
+        assert_eq!(schedule.next_after(start + ms(28)), Some(ms(7)));
+        assert_eq!(schedule.skipped_ticks(), 0);
+    }
+
+    #[test]
+    fn civil_conversion_test() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        for days in -1000..30_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn parse_test() {
+        assert!(CronSchedule::parse("* * * * *").is_ok());
+        assert!(CronSchedule::parse("*/5 * * * * *").is_ok());
+        assert!(CronSchedule::parse("0 9 * * MON-FRI").is_ok());
+        assert!(CronSchedule::parse("0 0 1,15 jan,jul *").is_ok());
+        assert_eq!(CronSchedule::parse("* * * *"), Err(CronError::FieldCount(4)));
+        assert_eq!(
+            CronSchedule::parse("60 * * * *"),
+            Err(CronError::InvalidField { field: "minute", value: "60".to_owned() })
+        );
+        assert!(CronSchedule::parse("* 5-2 * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        // A step larger than the range only matches the start
+        assert_eq!(CronSchedule::parse("1/18446744073709551615 * * * *"), CronSchedule::parse("1 * * * *"));
+        assert!(CronSchedule::parse("* * 0 * *").is_err());
+        assert!(CronSchedule::parse("* * * FOO *").is_err());
+        assert_eq!(CronSchedule::parse("0 0 30 2 *"), Err(CronError::NeverFires));
+    }
+
+    #[test]
+    fn next_fire_test() {
+        let now = ts(2017, 6, 14, 10, 20, 30);  // Wednesday
+        assert_eq!(next("* * * * * *", now), Some(now + 1));
+        assert_eq!(next("* * * * *", now), Some(ts(2017, 6, 14, 10, 21, 0)));
+        assert_eq!(next("30 2 * * *", now), Some(ts(2017, 6, 15, 2, 30, 0)));
+        assert_eq!(next("30 12 * * *", now), Some(ts(2017, 6, 14, 12, 30, 0)));
+        assert_eq!(next("*/15 * * * *", now), Some(ts(2017, 6, 14, 10, 30, 0)));
+        assert_eq!(next("0 9 * * MON", now), Some(ts(2017, 6, 19, 9, 0, 0)));
+        assert_eq!(next("0 9 * * 0", now), Some(ts(2017, 6, 18, 9, 0, 0)));
+        assert_eq!(next("0 9 * * 7", now), Some(ts(2017, 6, 18, 9, 0, 0)));
+        assert_eq!(next("0 0 1 * *", now), Some(ts(2017, 7, 1, 0, 0, 0)));
+        assert_eq!(next("0 0 1 1 *", now), Some(ts(2018, 1, 1, 0, 0, 0)));
+        assert_eq!(next("0 0 29 2 *", now), Some(ts(2020, 2, 29, 0, 0, 0)));
+        assert_eq!(next("0 0 31 * *", now), Some(ts(2017, 7, 31, 0, 0, 0)));
+        // Either the day of the month or the day of the week
+        assert_eq!(next("0 0 20 * FRI", now), Some(ts(2017, 6, 16, 0, 0, 0)));
+        assert_eq!(next("15,45 20 * * * *", now), Some(ts(2017, 6, 14, 10, 20, 45)));
+        assert_eq!(next("0 0 0 31 12 *", ts(2017, 12, 31, 0, 0, 0)), Some(ts(2018, 12, 31, 0, 0, 0)));
+    }
+
+    #[test]
+    fn daily_test() {
+        let daily = CronSchedule::daily(2, 30, 0).unwrap();
+        assert_eq!(daily, CronSchedule::parse("0 30 2 * * *").unwrap());
+        // Later today
+        assert_eq!(daily.next_fire_after_secs(ts(2017, 6, 14, 1, 0, 0)), Some(ts(2017, 6, 14, 2, 30, 0)));
+        // Earlier today, so tomorrow
+        assert_eq!(daily.next_fire_after_secs(ts(2017, 6, 14, 10, 0, 0)), Some(ts(2017, 6, 15, 2, 30, 0)));
+        // Exactly now, so tomorrow
+        assert_eq!(daily.next_fire_after_secs(ts(2017, 6, 14, 2, 30, 0)), Some(ts(2017, 6, 15, 2, 30, 0)));
+        assert_eq!(daily.next_fire_after_secs(ts(2017, 12, 31, 3, 0, 0)), Some(ts(2018, 1, 1, 2, 30, 0)));
+        assert_eq!(
+            CronSchedule::daily(25, 0, 0),
+            Err(CronError::InvalidField { field: "hour", value: "25".to_owned() })
+        );
+        assert!(CronSchedule::daily(0, 60, 0).is_err());
+        assert!(CronSchedule::daily(0, 0, 60).is_err());
+    }
+
+    #[test]
+    fn daily_wait_test() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut daily = CronSchedule::daily(2, 30, 0).unwrap();
+        assert_eq!(daily.next_wait_at(at(ts(2017, 6, 14, 10, 0, 0))), Some(Duration::from_secs(59_400)));
+        // Fired on time, the next fire is a day later
+        assert_eq!(daily.next_wait_at(at(ts(2017, 6, 15, 2, 30, 0))), Some(Duration::from_secs(86_400)));
+        // After a suspension of two days, the schedule is anchored on the time of day again
+        assert_eq!(daily.next_wait_at(at(ts(2017, 6, 18, 1, 0, 0))), Some(Duration::from_secs(5_400)));
+        // The clock was set back after the fire at 2:30, which doesn't run again
+        assert_eq!(daily.next_wait_at(at(ts(2017, 6, 18, 2, 0, 0))), Some(Duration::from_secs(88_200)));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn daily_in_timezone_test() {
+        use chrono_tz::America::New_York;
+
+        let daily = CronSchedule::daily(2, 30, 0).unwrap().in_timezone(New_York);
+        // Normal day: 02:30 EST is 07:30 UTC
+        assert_eq!(daily.next_fire_after_secs_in(ts(2017, 3, 10, 12, 0, 0), New_York), Some(ts(2017, 3, 11, 7, 30, 0)));
+        // Spring forward: 02:30 doesn't exist on 2017-03-12, fire at 03:00 EDT (07:00 UTC)
+        assert_eq!(daily.next_fire_after_secs_in(ts(2017, 3, 11, 12, 0, 0), New_York), Some(ts(2017, 3, 12, 7, 0, 0)));
+        // and go back to normal the day after: 02:30 EDT is 06:30 UTC
+        assert_eq!(daily.next_fire_after_secs_in(ts(2017, 3, 12, 7, 0, 0), New_York), Some(ts(2017, 3, 13, 6, 30, 0)));
+
+        let daily = CronSchedule::daily(1, 30, 0).unwrap().in_timezone(New_York);
+        // Fall back: 01:30 happens twice on 2017-11-05, fire at 01:30 EDT (05:30 UTC)
+        assert_eq!(daily.next_fire_after_secs_in(ts(2017, 11, 4, 12, 0, 0), New_York), Some(ts(2017, 11, 5, 5, 30, 0)));
+        // but not at 01:30 EST (06:30 UTC)
+        assert_eq!(daily.next_fire_after_secs_in(ts(2017, 11, 5, 5, 30, 0), New_York), Some(ts(2017, 11, 6, 6, 30, 0)));
+        assert_eq!(daily.next_fire_after_secs_in(ts(2017, 11, 5, 6, 10, 0), New_York), Some(ts(2017, 11, 6, 6, 30, 0)));
+    }
+}
@@ -28,7 +28,7 @@ pub trait TaskGroup: Send + Sync + Sized + 'static {
     fn get_tasks(&self) -> Vec<Self::TaskId>;
 
     /// Runs once per task id per cycle.
-    fn execute(&self, Self::TaskId);
+    fn execute(&self, task_id: Self::TaskId);
 }
 
 fn schedule_tasks_local<T: TaskGroup>(task_group: &Arc<T>, interval: Duration, handle: &Handle) {
@@ -39,7 +39,14 @@ fn schedule_tasks_local<T: TaskGroup>(task_group: &Arc<T>, interval: Duration, h
     let task_interval = interval / tasks.len() as u32;
     for (i, task) in tasks.into_iter().enumerate() {
         let task_group_clone = task_group.clone();
-        let t = Timeout::new(task_interval * i as u32, handle).unwrap()
+        let timeout = match Timeout::new(task_interval * i as u32, handle) {
+            Ok(timeout) => timeout,
+            Err(e) => {
+                error!("Failed to create timer, skipping task: {}", e);
+                continue;
+            }
+        };
+        let t = timeout
             .then(move |_| {
                 task_group_clone.execute(task);
                 Ok::<(), ()>(())
@@ -61,7 +68,14 @@ fn schedule_tasks_remote<T: TaskGroup>(task_group: &Arc<T>, interval: Duration,
         remote.spawn(move |handle| {
             let task_group = task_group.clone();
             let pool = pool.clone();
-            let t = Timeout::new(task_interval * i as u32, handle).unwrap()
+            let timeout = match Timeout::new(task_interval * i as u32, handle) {
+                Ok(timeout) => timeout,
+                Err(e) => {
+                    error!("Failed to create timer, skipping task: {}", e);
+                    return Ok::<(), ()>(());
+                }
+            };
+            let t = timeout
                 .then(move |_| {
                     task_group.execute(task);
                     Ok::<(), ()>(())
@@ -101,7 +115,7 @@ impl TaskGroupScheduler for ThreadPoolExecutor {
             initial,
             interval,
             move |remote| {
-                schedule_tasks_remote(&task_group_clone, interval, remote, &pool);
+                schedule_tasks_remote(&task_group_clone, interval, remote, &pool.current());
             }
         );
         task_group
@@ -0,0 +1,315 @@
+//! A hashed timer wheel, driving the timers of all the tasks of a core with a single `Timeout`.
+//!
+//! The wheel is divided in `SLOTS` slots of `RESOLUTION` each, and every entry is placed in the
+//! slot of its deadline, rounded up to the resolution. A single future spawned on the core, the
+//! driver, fires the entries of each slot once its time comes, then sleeps until the next slot
+//! holding an entry of the current revolution. Entries due at the same time are fired by
+//! increasing deadline, and in the order they were inserted for equal deadlines, so no task can
+//! be starved by the others of its slot.
+//!
+use futures::{Async, Future, Poll};
+use futures::task::{self, AtomicTask};
+use tokio_core::reactor::{CoreId, Handle, Timeout};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The duration of a slot: deadlines are rounded up to it.
+const RESOLUTION: Duration = Duration::from_millis(1);
+
+/// The number of slots, covering about 4 seconds per revolution.
+const SLOTS: u64 = 4096;
+
+thread_local! {
+    /// The wheels of the cores running on the current thread. The drivers own the wheels, so a
+    /// wheel is released, with its entries, when its core is dropped.
+    static WHEELS: RefCell<HashMap<CoreId, Weak<RefCell<Wheel>>>> = RefCell::new(HashMap::new());
+}
+
+/// Identifies an entry of a wheel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerKey {
+    tick: u64,
+    seq: u64,
+}
+
+/// Fired with the key of its entry.
+type Callback = Box<dyn FnOnce(&Handle, TimerKey)>;
+
+struct Entry {
+    tick: u64,
+    deadline: Instant,
+    seq: u64,
+    callback: Callback,
+}
+
+impl Entry {
+    fn key(&self) -> TimerKey {
+        TimerKey { tick: self.tick, seq: self.seq }
+    }
+}
+
+/// The part of a wheel shared with the other threads, which can cancel its entries.
+struct Shared {
+    canceled: Mutex<Vec<TimerKey>>,
+    driver: AtomicTask,
+}
+
+struct Wheel {
+    origin: Instant,
+    /// The last tick whose slot was fired.
+    cursor: u64,
+    slots: Vec<Vec<Entry>>,
+    /// The entries inserted with a deadline already reached, fired on the next turn of the event
+    /// loop.
+    ready: Vec<Entry>,
+    len: usize,
+    next_seq: u64,
+    /// The tick the driver will wake up at, if it's sleeping on its timeout.
+    armed: Option<u64>,
+    shared: Arc<Shared>,
+}
+
+impl Wheel {
+    fn new() -> Wheel {
+        Wheel {
+            origin: Instant::now(),
+            cursor: 0,
+            slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+            ready: Vec::new(),
+            len: 0,
+            next_seq: 0,
+            armed: None,
+            shared: Arc::new(Shared { canceled: Mutex::new(Vec::new()), driver: AtomicTask::new() }),
+        }
+    }
+
+    fn ticks_since_origin(&self, instant: Instant) -> (u64, bool) {
+        let elapsed = instant.saturating_duration_since(self.origin).as_nanos();
+        let resolution = RESOLUTION.as_nanos();
+        ((elapsed / resolution) as u64, !elapsed.is_multiple_of(resolution))
+    }
+
+    fn instant_of(&self, tick: u64) -> Instant {
+        self.origin + Duration::from_nanos(RESOLUTION.as_nanos() as u64 * tick)
+    }
+
+    fn slot(&mut self, tick: u64) -> &mut Vec<Entry> {
+        &mut self.slots[(tick % SLOTS) as usize]
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        self.len += 1;
+        if entry.tick <= self.cursor {
+            self.ready.push(entry);
+            self.shared.driver.notify();
+            return;
+        }
+        if self.armed.is_none_or(|armed| entry.tick < armed) {
+            self.shared.driver.notify();
+        }
+        let tick = entry.tick;
+        self.slot(tick).push(entry);
+    }
+
+    /// Takes the entries that were cancelled, so that they're dropped right away.
+    fn take_canceled(&mut self) -> Vec<Entry> {
+        let keys = mem::take(&mut *self.shared.canceled.lock().unwrap());
+        let mut canceled = Vec::with_capacity(keys.len());
+        for key in keys {
+            // Entries that already fired are not found
+            if let Some(position) = self.slot(key.tick).iter().position(|entry| entry.seq == key.seq) {
+                canceled.push(self.slot(key.tick).swap_remove(position));
+            } else if let Some(position) = self.ready.iter().position(|entry| entry.seq == key.seq) {
+                canceled.push(self.ready.swap_remove(position));
+            }
+        }
+        self.len -= canceled.len();
+        canceled
+    }
+
+    /// Takes the entries due at `now`, in the order they have to be fired.
+    fn advance(&mut self, now: Instant) -> Vec<Entry> {
+        let now_tick = self.ticks_since_origin(now).0;
+        let mut due = mem::take(&mut self.ready);
+        if now_tick > self.cursor {
+            // After a full revolution all the slots have been visited
+            for tick in self.cursor + 1..=now_tick.min(self.cursor + SLOTS) {
+                let slot = self.slot(tick);
+                let mut i = 0;
+                while i < slot.len() {
+                    if slot[i].tick <= now_tick {
+                        due.push(slot.swap_remove(i));
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            self.cursor = now_tick;
+        }
+        self.len -= due.len();
+        due.sort_by_key(|entry| (entry.deadline, entry.seq));
+        due
+    }
+
+    /// The tick of the next entry due in the current revolution, or the end of the revolution if
+    /// all the entries are due in the following ones.
+    fn next_tick(&self) -> Option<u64> {
+        if self.len == 0 {
+            return None;
+        }
+        (self.cursor + 1..=self.cursor + SLOTS)
+            .find(|&tick| self.slots[(tick % SLOTS) as usize].iter().any(|entry| entry.tick == tick))
+            .or(Some(self.cursor + SLOTS))
+    }
+}
+
+/// Cancels an entry of a wheel, from any thread: the entry is dropped by the driver without
+/// being fired, unless it already was.
+pub struct TimerCanceller {
+    shared: Arc<Shared>,
+    key: TimerKey,
+}
+
+impl TimerCanceller {
+    /// The key of the entry.
+    pub fn key(&self) -> TimerKey {
+        self.key
+    }
+
+    /// Cancels the entry. The driver is woken up to drop it.
+    pub fn cancel(&self) {
+        self.shared.canceled.lock().unwrap().push(self.key);
+        self.shared.driver.notify();
+    }
+}
+
+/// The timer wheel of a core. Can only be used on the thread of the core.
+pub struct TimerWheel {
+    wheel: Rc<RefCell<Wheel>>,
+}
+
+impl TimerWheel {
+    /// Returns the wheel of the core of `handle`, creating it and spawning its driver on first
+    /// use.
+    pub fn of(handle: &Handle) -> io::Result<TimerWheel> {
+        WHEELS.with(|wheels| {
+            let mut wheels = wheels.borrow_mut();
+            if let Some(wheel) = wheels.get(&handle.id()).and_then(Weak::upgrade) {
+                return Ok(TimerWheel { wheel });
+            }
+            let wheel = Rc::new(RefCell::new(Wheel::new()));
+            let timeout = Timeout::new_at(wheel.borrow().origin, handle)?;
+            wheels.retain(|_, wheel| wheel.strong_count() > 0);
+            wheels.insert(handle.id(), Rc::downgrade(&wheel));
+            handle.spawn(Driver { wheel: wheel.clone(), timeout, handle: handle.clone() });
+            Ok(TimerWheel { wheel })
+        })
+    }
+
+    /// Fires `callback` on the core once `deadline` is reached, with the key of its entry. Even
+    /// with a deadline already reached, the callback is only fired on the next turn of the event
+    /// loop.
+    pub fn insert<F>(&self, deadline: Instant, callback: F) -> TimerCanceller
+        where F: FnOnce(&Handle, TimerKey) + 'static
+    {
+        let mut wheel = self.wheel.borrow_mut();
+        let (tick, rounded) = wheel.ticks_since_origin(deadline);
+        let key = TimerKey { tick: if rounded { tick + 1 } else { tick }, seq: wheel.next_seq };
+        wheel.next_seq += 1;
+        wheel.insert(Entry { tick: key.tick, deadline, seq: key.seq, callback: Box::new(callback) });
+        TimerCanceller { shared: wheel.shared.clone(), key }
+    }
+}
+
+/// Fires the entries of a wheel.
+struct Driver {
+    wheel: Rc<RefCell<Wheel>>,
+    timeout: Timeout,
+    handle: Handle,
+}
+
+impl Future for Driver {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            let (canceled, due) = {
+                let mut wheel = self.wheel.borrow_mut();
+                wheel.shared.driver.register();
+                (wheel.take_canceled(), wheel.advance(Instant::now()))
+            };
+            // The wheel isn't borrowed while the entries are dropped or fired, as they can insert
+            // new entries
+            drop(canceled);
+            for entry in due {
+                let key = entry.key();
+                (entry.callback)(&self.handle, key);
+            }
+            let mut wheel = self.wheel.borrow_mut();
+            if !wheel.ready.is_empty() {
+                // Let the other futures of the core run before firing them
+                wheel.armed = None;
+                task::current().notify();
+                return Ok(Async::NotReady);
+            }
+            wheel.armed = wheel.next_tick();
+            let tick = match wheel.armed {
+                Some(tick) => tick,
+                None => return Ok(Async::NotReady),
+            };
+            self.timeout.reset(wheel.instant_of(tick));
+            drop(wheel);
+            match self.timeout.poll() {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::Ready(())) => continue,
+                Err(e) => {
+                    error!("Timer wheel failed, the timers of the core won't fire anymore: {}", e);
+                    return Err(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_core::reactor::{Core, Timeout};
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn firing_order_test() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        let wheel = TimerWheel::of(&handle).unwrap();
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        let now = Instant::now();
+        // Inserted in reverse order of deadline, with ties
+        for &(name, delay) in &[("c", 30), ("b1", 20), ("b2", 20), ("a", 10), ("b3", 20), ("now", 0)] {
+            let fired = fired.clone();
+            wheel.insert(now + Duration::from_millis(delay), move |_, _| fired.borrow_mut().push(name));
+        }
+        let canceled = wheel.insert(now + Duration::from_millis(15), |_, _| panic!("the entry was cancelled"));
+        canceled.cancel();
+        core.run(Timeout::new(Duration::from_millis(50), &handle).unwrap()).unwrap();
+        assert_eq!(*fired.borrow(), vec!["now", "a", "b1", "b2", "b3", "c"]);
+
+        // Entries spanning multiple revolutions
+        let (tx, rx) = ::std::sync::mpsc::channel();
+        let far = Instant::now() + RESOLUTION * (SLOTS as u32 + 10);
+        wheel.insert(far, move |_, _| tx.send(Instant::now()).unwrap());
+        core.run(Timeout::new_at(far + Duration::from_millis(20), &handle).unwrap()).unwrap();
+        assert!(rx.try_recv().unwrap() >= far);
+    }
+}